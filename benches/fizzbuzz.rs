@@ -0,0 +1,55 @@
+// Times writing fizzbuzz up to ten million to nowhere, serially in each format and with
+// --parallel's fan-in at a few thread counts. Run with cargo bench --bench fizzbuzz.
+use learning_rust::fizzbuzz::{self, FizzBuzz, Format, Range, Rules};
+use std::io::{self, BufWriter, Write};
+use std::thread;
+use std::time::Instant;
+
+const END: u128 = 10_000_000;
+
+fn range() -> Range {
+    Range::new(1, END, 1).expect("step is never zero")
+}
+
+fn time<F: FnOnce() -> io::Result<()>>(name: &str, f: F) {
+    let start = Instant::now();
+    f().expect("writing to a sink can't fail");
+    println!("{:<16} {:>8.3}s", name, start.elapsed().as_secs_f64());
+}
+
+fn main() {
+    time("serial", || {
+        let mut out = BufWriter::new(io::sink());
+        for line in FizzBuzz::new(Rules::classic(), range()) {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()
+    });
+    // What --format and --template cost over writing each line as it is.
+    let template = "{n}\t{label}".parse().expect("the template is valid");
+    let formats = [
+        ("text", Format::Text),
+        ("json", Format::Json),
+        ("csv", Format::Csv),
+        ("template", Format::Template(template)),
+    ];
+    for (name, format) in &formats {
+        time(&format!("format/{}", name), || {
+            let mut out = BufWriter::new(io::sink());
+            for line in FizzBuzz::new(Rules::classic(), range()) {
+                writeln!(out, "{}", format.record(&line))?;
+            }
+            out.flush()
+        });
+    }
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads = 1;
+    while threads <= cpus {
+        time(&format!("parallel/{}", threads), || {
+            let mut out = BufWriter::new(io::sink());
+            fizzbuzz::write_parallel(&Rules::classic(), range(), &Format::Text, threads, &mut out)?;
+            out.flush()
+        });
+        threads *= 2;
+    }
+}
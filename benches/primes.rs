@@ -0,0 +1,63 @@
+// Times each algorithm in the registry finding the primes up to a few maxima, as primes would,
+// from setting it up to taking the last prime. The naive ones stop at the smaller maxima, which
+// is as far as they can get in a reasonable time. The parallel ones are timed on 1, 2, 4 and so
+// on threads, up to one per CPU, to show how they scale. Run with cargo bench --bench primes.
+use clap::Parser;
+use learning_rust::primes::options::Opt;
+use learning_rust::primes::registry::REGISTRY;
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+const MAXIMA: [u64; 4] = [10_000, 1_000_000, 10_000_000, 100_000_000];
+
+// Quadratic, so a hundred times the max is ten thousand times the wait.
+const NAIVE_UP_TO: u64 = 1_000_000;
+
+// Doubling each time, and then all of them, if that isn't a power of 2.
+fn thread_counts() -> Vec<usize> {
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts: Vec<usize> = (0..)
+        .map(|shift| 1 << shift)
+        .take_while(|&n| n < cpus)
+        .collect();
+    counts.push(cpus);
+    counts
+}
+
+fn main() {
+    let thread_counts = thread_counts();
+    for entry in REGISTRY {
+        for &max in &MAXIMA {
+            if entry.name.starts_with("naive") && max > NAIVE_UP_TO {
+                continue;
+            }
+            // Each algorithm takes its settings from the command line, so it gets the defaults,
+            // but for the threads.
+            let threads: Vec<Option<usize>> = if entry.parallel {
+                thread_counts.iter().copied().map(Some).collect()
+            } else {
+                vec![None]
+            };
+            for threads in threads {
+                let max_arg = max.to_string();
+                let threads_arg = threads.map(|n| n.to_string());
+                let mut args = vec!["primes", entry.name, &max_arg];
+                if let Some(n) = &threads_arg {
+                    args.extend(["--threads", n]);
+                }
+                let opt = Opt::parse_from(&args);
+                let start = Instant::now();
+                let count = (entry.primes)(&opt, max).map(black_box).count();
+                println!(
+                    "{:<16} {:>3} {:>11} {:>10} primes {:>8.3}s",
+                    entry.name,
+                    threads_arg.as_deref().unwrap_or(""),
+                    max,
+                    count,
+                    start.elapsed().as_secs_f64()
+                );
+            }
+        }
+    }
+}
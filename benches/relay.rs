@@ -0,0 +1,97 @@
+// Times relaying the output of several commands at once, the way execute-in-dirs does: each on a
+// thread of its own, a line at a time through a PrefixedWriter, by each --strategy. The lines go
+// to a writer that only counts them, so it's the relaying that's timed rather than a terminal.
+// Run with cargo bench --bench relay.
+use learning_rust::locked_write::buffered::LineBuffer;
+use learning_rust::locked_write::channel;
+use learning_rust::locked_write::prefixed::PrefixedWriter;
+use learning_rust::locked_write::shared::SharedLineWriter;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LINES: usize = 200_000;
+
+const COMMANDS: [usize; 3] = [1, 4, 16];
+
+// Throws everything away, keeping count of the bytes, so there's something to check. Clones
+// share the count.
+#[derive(Clone, Default)]
+struct Count(Arc<AtomicUsize>);
+
+impl Write for Count {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.fetch_add(buf.len(), Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn time<F: FnOnce(Count) -> io::Result<()>>(name: &str, commands: usize, f: F) {
+    let count = Count::default();
+    let start = Instant::now();
+    f(count.clone()).expect("counting can't fail");
+    let bytes = count.0.load(Ordering::Relaxed);
+    println!(
+        "{:<16} {:>2} commands {:>10} bytes {:>8.3}s",
+        name,
+        commands,
+        bytes,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+// Splits LINES between this many commands, each writing its share to a writer of its own.
+fn relay<W, F>(commands: usize, writer: F) -> io::Result<()>
+where
+    W: Write + Send,
+    F: Fn() -> W,
+{
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..commands)
+            .map(|c| {
+                let mut out = PrefixedWriter::new(writer(), format!("/src/command-{}: ", c));
+                scope.spawn(move || -> io::Result<()> {
+                    for i in (c..LINES).step_by(commands) {
+                        writeln!(out, "compiling module {} of {}", i, LINES)?;
+                    }
+                    out.flush()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("a command thread panicked"))
+    })
+}
+
+fn main() {
+    for &commands in &COMMANDS {
+        for (name, interval) in [
+            ("locked", Duration::ZERO),
+            ("locked/100ms", Duration::from_millis(100)),
+        ] {
+            time(name, commands, |count| {
+                let out = Arc::new(Mutex::new(count));
+                relay(commands, || {
+                    LineBuffer::new(Arc::clone(&out)).interval(interval)
+                })
+            });
+        }
+        time("shared", commands, |count| {
+            let shared = SharedLineWriter::new(count);
+            relay(commands, || shared.clone())
+        });
+        time("channel", commands, |count| {
+            let (sender, writer) = channel::channel(count);
+            relay(commands, || sender.clone())?;
+            drop(sender);
+            writer.join().expect("the writer panicked").map(drop)
+        });
+    }
+}
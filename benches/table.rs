@@ -0,0 +1,82 @@
+// Counts the allocations it takes to build a large table, and times it, with the text of every
+// cell but one borrowed through Row, and then with every cell copied into a String of its own.
+// Run with cargo bench --bench table.
+use learning_rust::table::row::Row;
+use learning_rust::table::Table;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const ROWS: usize = 100_000;
+
+const NAMES: [&str; 4] = ["alpha", "beta", "gamma", "delta"];
+
+// The system allocator, keeping count of each allocation it's asked for.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn measure<F: FnOnce() -> Table<'static>>(name: &str, f: F) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let table = f();
+    let built = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    let mut out = Vec::new();
+    write!(out, "{}", table).expect("writing to a Vec can't fail");
+    println!(
+        "{:<10} {:>10} allocations {:>8.3}s to build {:>8.3}s to write",
+        name,
+        allocations,
+        built.as_secs_f64(),
+        (start.elapsed() - built).as_secs_f64()
+    );
+}
+
+fn main() {
+    measure("borrowed", || {
+        let mut table = Table::new();
+        for i in 0..ROWS {
+            let row = Row::with_capacity(4)
+                .cell(NAMES[i % NAMES.len()])
+                .cell("static")
+                .cell("-")
+                .cell(i.to_string());
+            table.row(row);
+        }
+        table
+    });
+    measure("owned", || {
+        let mut table = Table::new();
+        for i in 0..ROWS {
+            let row: Vec<String> = vec![
+                NAMES[i % NAMES.len()].to_string(),
+                "static".to_string(),
+                "-".to_string(),
+                i.to_string(),
+            ];
+            table.row(row);
+        }
+        table
+    });
+}
@@ -0,0 +1,159 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::{IoError, Report};
+use learning_rust::locked_write::buffered::LineBuffer;
+use learning_rust::locked_write::channel;
+use learning_rust::locked_write::shared::SharedLineWriter;
+use learning_rust::log;
+use learning_rust::span;
+use learning_rust::table::{Numbers, Table};
+use std::io::{self, BufWriter, Write};
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "bench-io",
+    version,
+    about = "Time each of the ways of writing to stdout used here, writing the same lines with \
+        each.\nThe lines go to stdout and the timings to stderr, so send stdout somewhere, e.g. \
+        /dev/null."
+)]
+struct Opt {
+    /// How many lines each way writes
+    #[arg(long, default_value = "1000000")]
+    lines: usize,
+
+    /// How many threads share the lines between them, for the ways that can take more than one
+    #[arg(long, default_value = "4", value_parser = parse_threads)]
+    threads: usize,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+fn parse_threads(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(threads) => Ok(threads),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    if let Err(err) = run(&opt).map_err(IoError::context("writing to stdout")) {
+        eprintln!("error: {}", Report(&err));
+        exit(2);
+    }
+}
+
+fn run(opt: &Opt) -> io::Result<()> {
+    let n = opt.lines;
+    let mut timings = Vec::new();
+    let mut time = |name: &str, f: &dyn Fn() -> io::Result<()>| -> io::Result<()> {
+        let _span = span!(Info, "timing {}", name);
+        let start = Instant::now();
+        f()?;
+        timings.push((name.to_string(), start.elapsed()));
+        Ok(())
+    };
+
+    // println! locks stdout all over again for every line.
+    time("println", &|| {
+        for i in 0..n {
+            println!("line {}", i);
+        }
+        Ok(())
+    })?;
+    // Locking once, stdout still writes each line as it ends, being line buffered.
+    time("locked writeln", &|| {
+        let mut out = io::stdout().lock();
+        for i in 0..n {
+            writeln!(out, "line {}", i)?;
+        }
+        Ok(())
+    })?;
+    time("BufWriter", &|| {
+        let mut out = BufWriter::new(io::stdout().lock());
+        for i in 0..n {
+            writeln!(out, "line {}", i)?;
+        }
+        out.flush()
+    })?;
+    time("LineBuffer", &|| {
+        let mut out = LineBuffer::new(io::stdout());
+        for i in 0..n {
+            writeln!(out, "line {}", i)?;
+        }
+        out.flush()
+    })?;
+    let threads = opt.threads;
+    time(&format!("channel/{}", threads), &|| {
+        let (sender, writer) = channel::channel(io::stdout());
+        spread(n, threads, || sender.clone())?;
+        drop(sender);
+        writer.join().expect("the writer panicked").map(drop)
+    })?;
+    time(&format!("shared/{}", threads), &|| {
+        let shared = SharedLineWriter::new(io::stdout());
+        spread(n, threads, || shared.clone())
+    })?;
+
+    let bytes = bytes(n) as f64;
+    let mut table = Table::new();
+    table.header(vec!["strategy", "seconds", "lines/s", "MB/s"]);
+    for (name, elapsed) in timings {
+        let seconds = elapsed.max(Duration::from_nanos(1)).as_secs_f64();
+        table.row(vec![
+            name,
+            format!("{}", seconds),
+            format!("{}", n as f64 / seconds),
+            format!("{}", bytes / seconds / 1e6),
+        ]);
+    }
+    let rounded = |decimals, separators| Numbers {
+        separators,
+        decimals: Some(decimals),
+    };
+    table
+        .numbers(1, rounded(3, false))
+        .numbers(2, rounded(0, true))
+        .numbers(3, rounded(1, false));
+    eprint!("{}", table);
+    Ok(())
+}
+
+// Shares n lines between this many threads, each writing its share to a writer of its own.
+fn spread<W, F>(n: usize, threads: usize, writer: F) -> io::Result<()>
+where
+    W: Write + Send,
+    F: Fn() -> W,
+{
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let mut out = writer();
+                scope.spawn(move || -> io::Result<()> {
+                    for i in (t..n).step_by(threads) {
+                        writeln!(out, "line {}", i)?;
+                    }
+                    out.flush()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("a writer thread panicked"))
+    })
+}
+
+// How many bytes n lines come to.
+fn bytes(n: usize) -> usize {
+    let digits = |i: usize| i.checked_ilog10().map_or(1, |log| log as usize + 1);
+    (0..n).map(|i| "line \n".len() + digits(i)).sum()
+}
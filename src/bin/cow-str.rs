@@ -1,39 +1,48 @@
-use std::borrow::Cow;
-use std::io;
-use std::io::Write;
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::log;
+use learning_rust::table::row::Row;
+use learning_rust::table::Table;
+use learning_rust::{debug, info};
 
-fn print_table<T: AsRef<str>>(table: Vec<Vec<T>>) -> io::Result<()> {
-    let stdout = io::stdout();
-    let mut stdout = stdout.lock();
-    for row in table {
-        for col in row {
-            stdout.write_all(col.as_ref().as_bytes())?;
-            stdout.write(b" ")?;
-        }
-        stdout.write(b"\n")?;
-    }
-    Ok(())
+#[derive(Debug, Parser)]
+#[command(
+    name = "cow-str",
+    version,
+    about = "Build a table of borrowed and owned cells, saying which each one is."
+)]
+struct Opt {
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
 }
 
 fn main() {
-    let mut table = Vec::new();
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let mut table = Table::new();
     let dash = "-";
 
     for i in 0..10 {
-        let mut row: Vec<Cow<str>> = Vec::new();
-        row.push("hello".into());
-        row.push("world".into());
-        row.push(dash.into());
-        row.push(format!("{}", i).into());
+        // Only the number has to be worked out, so it's the only cell that allocates.
+        let row = Row::with_capacity(4)
+            .cell("hello")
+            .cell("world")
+            .cell(dash)
+            .cell(format!("{}", i));
 
-        for item in &row {
-            match item {
-                Cow::Borrowed(x) => eprintln!("borrowed: {}", x),
-                Cow::Owned(x) => eprintln!("owned: {}", x),
+        for cell in row.cells() {
+            if cell.is_borrowed() {
+                eprintln!("borrowed: {}", cell.text());
+            } else {
+                eprintln!("owned: {}", cell.text());
             }
         }
 
-        table.push(row);
+        debug!("row {} has {} cells", i, row.cells().len());
+        table.row(row);
     }
-    print_table(table).expect("oy");
+    info!("writing the table");
+    print!("{}", table);
 }
@@ -0,0 +1,134 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::du::{self, Usage};
+use learning_rust::error::{IoError, Report};
+use learning_rust::log;
+use learning_rust::table::{Align, Border, Table};
+use learning_rust::{debug, span};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+use std::thread;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "du-lite",
+    version,
+    about = "Say how much space each directory takes up, with everything under it, like du.",
+    disable_help_flag = true
+)]
+struct Opt {
+    /// Directories to measure, the current one when there are none
+    paths: Vec<PathBuf>,
+
+    /// Only list directories at most this far below those given, which are 0. Everything
+    /// further down still counts towards them
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
+
+    /// Write sizes in K, M, G and so on, rather than in kilobytes
+    // -h is for this, as in du, so help is only --help.
+    #[arg(short = 'h', long = "human-readable")]
+    human: bool,
+
+    /// Count how long files are, rather than how much of the disk they take up
+    #[arg(long = "apparent-size")]
+    apparent: bool,
+
+    /// What to sort by: name, with each directory before those in it, or size, largest first
+    #[arg(long, default_value = "name")]
+    sort: Sort,
+
+    /// What to draw around the sizes and names: none, ascii, unicode or markdown
+    #[arg(long, default_value = "none")]
+    border: Border,
+
+    /// How many directories to read at once (default: one per CPU)
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Print help
+    // In place of clap's own, which would have -h too. It exits as soon as it's seen, so the
+    // field is never read.
+    #[arg(long = "help", action = ArgAction::Help)]
+    _help: Option<bool>,
+}
+
+impl Opt {
+    fn threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    fn size(&self, size: u64) -> String {
+        if self.human {
+            du::human(size)
+        } else {
+            size.div_ceil(1024).to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Sort {
+    Name,
+    Size,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Sort::Name),
+            "size" => Ok(Sort::Size),
+            _ => Err(format!("unknown sort {}, expected name or size", s)),
+        }
+    }
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let paths = if opt.paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        opt.paths.clone()
+    };
+
+    debug!("walking {} paths on {} threads", paths.len(), opt.threads());
+    let span = span!(Info, "walking");
+    let (mut usages, errors) = du::walk(&paths, opt.threads(), opt.apparent);
+    drop(span);
+    for err in &errors {
+        eprintln!("error: {}", Report(err));
+    }
+
+    usages.retain(|usage| opt.max_depth.is_none_or(|max| usage.depth <= max));
+    match opt.sort {
+        Sort::Name => usages.sort_by(|a, b| a.path.cmp(&b.path)),
+        Sort::Size => usages.sort_by(|a, b| b.size.cmp(&a.size).then(a.path.cmp(&b.path))),
+    }
+    let mut table = Table::new();
+    table.border(opt.border).align(0, Align::Right);
+    for Usage { path, size, .. } in &usages {
+        table.row([opt.size(*size), path.display().to_string()]);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match write!(out, "{}", table).and_then(|()| out.flush()) {
+        // Whoever was reading, e.g. head, has all they wanted.
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("error: {}", Report(&IoError::new("writing to stdout", err)));
+            exit(1);
+        }
+        _ => {}
+    }
+    exit(if errors.is_empty() { 0 } else { 1 });
+}
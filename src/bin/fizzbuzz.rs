@@ -1,96 +1,334 @@
-#![cfg_attr(feature = "nightly", feature(int_error_matching))]
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::{IoError, Report};
+use learning_rust::fizzbuzz::{
+    self, Combination, FizzBuzz, Format, Only, Range, Rule, Rules, Summary, Template, Vocabulary,
+};
+use learning_rust::log;
+use learning_rust::primes::memory::Bytes;
+use learning_rust::{debug, span};
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fs::{self, File};
 use std::io::{self, BufWriter, Write};
-use std::{env, fmt, num};
+use std::num::{IntErrorKind, ParseIntError};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread;
+use thiserror::Error;
 
-#[cfg(feature = "nightly")]
-use core::num::IntErrorKind;
+#[derive(Debug, Parser)]
+#[command(
+    name = "fizzbuzz",
+    version,
+    about = "Play fizzbuzz, with the classic rules or your own."
+)]
+struct Opt {
+    /// The first number
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = parse_number,
+        allow_negative_numbers = true
+    )]
+    start: u128,
 
-#[cfg(feature = "nightly")]
-enum Error {
-    ArgumentMissing,
-    ArgumentEmpty,
-    ArgumentOverflow,
-    ArgumentInvalid,
-    IO,
+    /// The last number, or the bound past which to stop
+    #[arg(
+        long,
+        required_unless_present = "forever",
+        conflicts_with = "forever",
+        value_parser = parse_number,
+        allow_negative_numbers = true
+    )]
+    end: Option<u128>,
+
+    /// Keep going until interrupted or whoever's reading stops, wrapping around at the ends of
+    /// a u128
+    #[arg(long)]
+    forever: bool,
+
+    /// How far to go from one number to the next; negative counts down
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = parse_step,
+        allow_negative_numbers = true
+    )]
+    step: i128,
+
+    /// Say <word> for multiples of <divisor>, e.g. 7=bazz. Words from several rules are
+    /// concatenated, and any rules replace the classic ones
+    #[arg(long = "rule")]
+    rules: Vec<Rule>,
+
+    /// Read an ordered list of rules from this TOML file, before any --rule
+    #[arg(long)]
+    rules_file: Option<PathBuf>,
+
+    /// Say the words in this language: en, de or fr
+    #[arg(long, value_parser = Vocabulary::language, conflicts_with = "words_file")]
+    lang: Option<Vocabulary>,
+
+    /// Say the words in this TOML file's table of word = "translation" instead of their own
+    #[arg(long)]
+    words_file: Option<PathBuf>,
+
+    /// How the words of several matching rules come together: concat joins them, except where
+    /// a rules file says a rule overrides; override keeps only the last; priority only the first
+    #[arg(long, default_value = "concat")]
+    combine: Combination,
+
+    /// How to write each line: text, the number or its words; json, an object per line; or csv,
+    /// number,label rows under a header
+    #[arg(long, default_value = "text")]
+    format: Format,
+
+    // clap takes {n} in help to mean a line break, so the help can only mention {number}.
+    /// Write each line shaped like this instead, with placeholders {number}, or just n in braces,
+    /// and {label} for its words, if any, e.g. "{number}\t{label}"
+    #[arg(long, conflicts_with = "format")]
+    template: Option<Template>,
+
+    /// Write to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// How much to buffer before each write, e.g. 64K; 0 writes every line as it comes
+    #[arg(long, default_value = "8K")]
+    buffer_size: Bytes,
+
+    /// Format everything, but rather than write it, say how many lines & bytes it came to
+    #[arg(long, conflicts_with_all = ["output", "buffer_size"])]
+    count_only: bool,
+
+    /// Only write the lines saying these words, e.g. fizzbuzz, or number for the plain ones
+    #[arg(long)]
+    only: Option<Only>,
+
+    /// Leave out this many lines, after --only
+    #[arg(long, default_value = "0")]
+    skip: usize,
+
+    /// Stop after this many lines, after --only and --skip
+    #[arg(long)]
+    take: Option<usize>,
+
+    /// Rather than every line, say how many numbers each rule matched, how many matched more
+    /// than one, and how many none
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "forever",
+            "count_only",
+            "format",
+            "template",
+            "parallel",
+            "only",
+            "skip",
+            "take",
+        ]
+    )]
+    summary: bool,
+
+    /// Format chunks of the range on several threads at once, still written in order
+    #[arg(long, conflicts_with_all = ["only", "skip", "take"])]
+    parallel: bool,
+
+    /// How many threads --parallel uses (default: one per CPU)
+    #[arg(long, requires = "parallel", value_parser = parse_threads)]
+    threads: Option<usize>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
 }
 
-#[cfg(feature = "nightly")]
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let reason = match self {
-            Error::ArgumentMissing => "not enough arguments specified",
-            Error::ArgumentInvalid => "specified argument is invalid",
-            Error::ArgumentEmpty => "specified argument is empty",
-            Error::ArgumentOverflow => "specified argument is too large",
-            Error::IO => "io error",
-        };
-        write!(f, "{}", reason)
+impl Opt {
+    fn threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
     }
 }
 
-#[cfg(feature = "nightly")]
-impl From<num::ParseIntError> for Error {
-    fn from(err: num::ParseIntError) -> Error {
-        match err.kind() {
-            IntErrorKind::Empty => Error::ArgumentEmpty,
-            IntErrorKind::Overflow => Error::ArgumentOverflow,
-            _ => Error::ArgumentInvalid,
-        }
+// Numbers go up to u128::MAX, and saying so beats "number too large to fit in target type".
+fn parse_number(s: &str) -> Result<u128, String> {
+    // A u128 doesn't know about minus signs, so it would call these invalid digits.
+    let digits = |d: &str| !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit());
+    if s.strip_prefix('-').is_some_and(digits) {
+        return Err(format!("{} is negative, the least is 0", s));
     }
+    s.parse()
+        .map_err(|err| out_of_range(s, &err, &0, &u128::MAX))
 }
 
-#[cfg(not(feature = "nightly"))]
-enum Error {
-    ArgumentMissing,
-    ArgumentInvalid,
-    IO,
+// A step of zero would never get anywhere.
+fn parse_step(s: &str) -> Result<i128, String> {
+    match s.parse::<i128>() {
+        Ok(0) => Err("must not be 0".to_string()),
+        Ok(step) => Ok(step),
+        Err(err) => Err(out_of_range(s, &err, &i128::MIN, &i128::MAX)),
+    }
 }
 
-#[cfg(not(feature = "nightly"))]
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let reason = match self {
-            Error::ArgumentMissing => "not enough arguments specified",
-            Error::ArgumentInvalid => "specified argument is invalid",
-            Error::IO => "io error",
-        };
-        write!(f, "{}", reason)
+// What was wrong with a number that didn't parse, down to how far it can go if it went too far.
+fn out_of_range(s: &str, err: &ParseIntError, min: &dyn Display, max: &dyn Display) -> String {
+    match err.kind() {
+        IntErrorKind::PosOverflow => format!("{} is too large, the most is {}", s, max),
+        IntErrorKind::NegOverflow => format!("{} is too small, the least is {}", s, min),
+        _ => err.to_string(),
     }
 }
 
-#[cfg(not(feature = "nightly"))]
-impl From<num::ParseIntError> for Error {
-    fn from(_err: num::ParseIntError) -> Error {
-        Error::ArgumentInvalid
+fn parse_threads(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(threads) => Ok(threads),
+        Err(err) => Err(err.to_string()),
     }
 }
 
-impl From<io::Error> for Error {
-    fn from(_err: io::Error) -> Error {
-        Error::IO
+// What can go wrong once the command line has parsed.
+#[derive(Debug, Error)]
+enum Error {
+    #[error("invalid rules file {}: {reason}", path.display())]
+    RulesFile { path: PathBuf, reason: String },
+
+    #[error("invalid words file {}: {reason}", path.display())]
+    WordsFile { path: PathBuf, reason: String },
+
+    #[error(transparent)]
+    Io(#[from] IoError),
+}
+
+fn read_rules(path: &Path) -> Result<Vec<Rule>, Error> {
+    let contents = fs::read_to_string(path)
+        .map_err(IoError::context(format!("reading {}", path.display())))?;
+    Rules::parse_toml(&contents).map_err(|reason| Error::RulesFile {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+fn read_words(path: &Path) -> Result<Vocabulary, Error> {
+    let contents = fs::read_to_string(path)
+        .map_err(IoError::context(format!("reading {}", path.display())))?;
+    Vocabulary::parse_toml(&contents).map_err(|reason| Error::WordsFile {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    if let Err(err) = run(opt) {
+        eprintln!("error: {}", Report(&err));
+        exit(2);
     }
 }
 
-fn main() -> Result<(), Error> {
-    let args: Vec<String> = env::args().collect();
-    let arg = args.get(1).ok_or(Error::ArgumentMissing)?;
-    let max: u64 = arg.parse()?;
+fn run(opt: Opt) -> Result<(), Error> {
+    let mut rules = match &opt.rules_file {
+        Some(path) => read_rules(path)?,
+        None => Vec::new(),
+    };
+    rules.extend(opt.rules.iter().cloned());
+    // Rules of our own replace the classic ones, which are only there when there are no others.
+    let rules = if rules.is_empty() {
+        Rules::classic()
+    } else {
+        Rules::new(rules)
+    }
+    .combination(opt.combine);
+    let rules = match (&opt.lang, &opt.words_file) {
+        (Some(vocabulary), _) => rules.translate(vocabulary),
+        (None, Some(path)) => rules.translate(&read_words(path)?),
+        (None, None) => rules,
+    };
+    // parse_step has already made sure the step isn't zero, and clap that there's an end
+    // unless we're going on forever.
+    let range = if opt.forever {
+        Range::forever(opt.start, opt.step)
+    } else {
+        Range::new(opt.start, opt.end.expect("--end is required"), opt.step)
+    }
+    .expect("step is never zero");
+    debug!("playing {:?}", rules);
+    debug!("over {:?}", range);
+
+    if opt.count_only {
+        let mut counter = Counter::default();
+        play(&opt, rules, range, &mut counter).expect("counting can't fail");
+        println!("{} lines, {} bytes", counter.lines, counter.bytes);
+        return Ok(());
+    }
 
     // By locking stdout ourselves & using writeln! instead of println!, we avoid having to
     // re-acquire the lock with each write. Then by using a BufWriter instead of stdout directly,
     // we batch many writes together into a single write syscall.
-    let stdout = io::stdout();
-    let stdout = stdout.lock();
-
-    let mut stdout = BufWriter::new(stdout);
-
-    for i in 1..=max {
-        match i {
-            v if v % 15 == 0 => writeln!(stdout, "FizzBuzz!"),
-            v if v % 3 == 0 => writeln!(stdout, "fizz"),
-            v if v % 5 == 0 => writeln!(stdout, "buzz"),
-            v => writeln!(stdout, "{}", v),
-        }?;
+    let (out, name): (Box<dyn Write>, String) = match &opt.output {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(IoError::context(format!("creating {}", path.display())))?;
+            (Box::new(file), path.display().to_string())
+        }
+        None => (Box::new(io::stdout().lock()), "stdout".to_string()),
+    };
+    // Anything that doesn't fit in memory would fail to allocate anyway.
+    let capacity = usize::try_from(opt.buffer_size.0).unwrap_or(usize::MAX);
+    let mut out = BufWriter::with_capacity(capacity, out);
+    let _span = span!(Info, "writing to {}", name);
+
+    match play(&opt, rules, range, &mut out).and_then(|()| out.flush()) {
+        // Whoever was reading, e.g. head, has all they wanted, so there's nothing more to do.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => {
+            result.map_err(|source| IoError::new(format!("writing to {}", name), source).into())
+        }
+    }
+}
+
+// A writer that throws everything away, but keeps count of it first.
+#[derive(Default)]
+struct Counter {
+    lines: u64,
+    bytes: u64,
+}
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        self.bytes += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn play<W: Write>(opt: &Opt, rules: Rules, range: Range, out: &mut W) -> io::Result<()> {
+    if opt.summary {
+        return writeln!(out, "{}", Summary::collect(&rules, range));
+    }
+    let format = match &opt.template {
+        Some(template) => Format::Template(template.clone()),
+        None => opt.format.clone(),
+    };
+    if let Some(header) = format.header() {
+        writeln!(out, "{}", header)?;
+    }
+    if opt.parallel {
+        return fizzbuzz::write_parallel(&rules, range, &format, opt.threads(), out);
+    }
+    let lines = FizzBuzz::new(rules, range)
+        .filter(|line| opt.only.as_ref().is_none_or(|only| only.keeps(line)))
+        .skip(opt.skip)
+        .take(opt.take.unwrap_or(usize::MAX));
+    for line in lines {
+        writeln!(out, "{}", format.record(&line))?;
     }
     Ok(())
 }
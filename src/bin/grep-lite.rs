@@ -0,0 +1,197 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::{IoError, Report};
+use learning_rust::grep::{self, Search};
+use learning_rust::locked_write::{write_locked, Flow, Policy};
+use learning_rust::log;
+use learning_rust::pool;
+use learning_rust::{debug, span};
+use regex::bytes::RegexBuilder;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "grep-lite",
+    version,
+    about = "Print the lines that match a regular expression, searching several files at once."
+)]
+struct Opt {
+    /// What to look for, a regular expression as the regex crate has them
+    pattern: String,
+
+    /// Files to search, or - for stdin, which is searched when there are none. With
+    /// --recursive, directories are searched too, the current one when there are none
+    paths: Vec<PathBuf>,
+
+    /// Search everything in each directory, and in theirs
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Put each line's number before it
+    #[arg(short = 'n', long = "line-number")]
+    line_numbers: bool,
+
+    /// Only write the names of the files with matching lines
+    #[arg(short = 'l', long = "files-with-matches")]
+    files_only: bool,
+
+    /// The lines that don't match, instead of those that do
+    #[arg(short = 'v', long = "invert-match")]
+    invert: bool,
+
+    /// Match upper and lower case alike
+    #[arg(short, long)]
+    ignore_case: bool,
+
+    /// How many files to search at once (default: one per CPU)
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Say what's going on, on stderr: once for the main steps, twice for more detail and three
+    /// times for everything. -v is for --invert-match, as in grep
+    #[arg(long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+impl Opt {
+    fn threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+// What's being searched: a file, or stdin.
+enum Input {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Input {
+    // Files are read whole, which is simplest, and what a regex wants anyway.
+    fn read(&self) -> Result<Vec<u8>, IoError> {
+        match self {
+            Input::File(path) => fs::read(path).map_err(IoError::context(name_of(path))),
+            Input::Stdin => {
+                let mut contents = Vec::new();
+                io::stdin()
+                    .lock()
+                    .read_to_end(&mut contents)
+                    .map_err(IoError::context("(standard input)"))?;
+                Ok(contents)
+            }
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Input::File(path) => name_of(path),
+            Input::Stdin => "(standard input)".to_string(),
+        }
+    }
+}
+
+// What grep calls a file it found by searching the current directory, without the ./ in front.
+fn name_of(path: &Path) -> String {
+    path.strip_prefix(".").unwrap_or(path).display().to_string()
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let regex = match RegexBuilder::new(&opt.pattern)
+        .case_insensitive(opt.ignore_case)
+        .build()
+    {
+        Ok(regex) => regex,
+        Err(err) => {
+            eprintln!("error: invalid pattern: {}", Report(&err));
+            exit(2);
+        }
+    };
+    let search = Search {
+        regex,
+        invert: opt.invert,
+        line_numbers: opt.line_numbers,
+        files_only: opt.files_only,
+    };
+
+    let paths = match (&opt.paths[..], opt.recursive) {
+        ([], true) => vec![PathBuf::from(".")],
+        (paths, _) => paths.to_vec(),
+    };
+    let inputs: Vec<Result<Input, IoError>> = if paths.is_empty() {
+        vec![Ok(Input::Stdin)]
+    } else {
+        grep::files(&paths, opt.recursive)
+            .into_iter()
+            .map(|file| {
+                file.map(|path| match path.to_str() {
+                    Some("-") => Input::Stdin,
+                    _ => Input::File(path),
+                })
+            })
+            .collect()
+    };
+    // Whenever there might be more than one file, each line says which it's from.
+    let named = inputs.len() > 1 || (opt.recursive && paths.iter().any(|path| path.is_dir()));
+    debug!(
+        "searching {} files on {} threads",
+        inputs.len(),
+        opt.threads()
+    );
+
+    // Each file's lines are written together, in the order of the files, however many are
+    // searched at once. Those that couldn't be found by walking have nothing to search, and
+    // their error already there to report.
+    let work = |input: &Result<Input, IoError>| -> Option<Result<(bool, Vec<u8>), IoError>> {
+        let input = input.as_ref().ok()?;
+        Some(input.read().map(|contents| {
+            let mut out = Vec::new();
+            let found = search.search(&input.name(), named, &contents, &mut out);
+            (found, out)
+        }))
+    };
+    let report = |err: &IoError| eprintln!("error: {}", Report(err));
+    let stdout = io::stdout();
+    let (mut found, mut failed, mut writing) = (false, false, true);
+    let span = span!(Info, "searching");
+    pool::map_ordered(&inputs, opt.threads(), work, |i, result| match result {
+        Some(Ok((matched, out))) => {
+            found |= matched;
+            if !writing || out.is_empty() {
+                return;
+            }
+            match Policy::default().check(write_locked(&stdout, &[&out])) {
+                Ok(Flow::Continue) => {}
+                // Whoever was reading, e.g. head, has all they wanted.
+                Ok(Flow::Stop) => writing = false,
+                Err(err) => {
+                    eprintln!("error: {}", Report(&IoError::new("writing to stdout", err)));
+                    exit(2);
+                }
+            }
+        }
+        Some(Err(err)) => {
+            report(&err);
+            failed = true;
+        }
+        None => {
+            if let Err(err) = &inputs[i] {
+                report(err);
+            }
+            failed = true;
+        }
+    });
+    drop(span);
+
+    // As grep does: 2 if anything went wrong, else 0 for something found or 1 for nothing.
+    exit(match (failed, found) {
+        (true, _) => 2,
+        (false, true) => 0,
+        (false, false) => 1,
+    });
+}
@@ -0,0 +1,304 @@
+use clap::{ArgAction, Args, Parser, Subcommand};
+use learning_rust::completions;
+use learning_rust::error::{IoError, Report};
+use learning_rust::execute::parse_interval;
+use learning_rust::headtail::follow::Follower;
+use learning_rust::headtail::{self, Count};
+use learning_rust::locked_write::buffered::LineBuffer;
+use learning_rust::locked_write::{Flow, Policy};
+use learning_rust::log;
+use learning_rust::{debug, info};
+use std::fs::File;
+use std::io::{self, BufReader, Seek, SeekFrom, Stdout, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "headtail",
+    version,
+    about = "Write the start or the end of files, like head and tail, following them with tail -f."
+)]
+struct Opt {
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write the first lines of each file
+    Head {
+        #[command(flatten)]
+        which: Which,
+    },
+
+    /// Write the last lines of each file
+    Tail {
+        #[command(flatten)]
+        which: Which,
+
+        /// Keep writing whatever's added to the files, until interrupted
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Look for what's been added every --sleep-interval, rather than being told by the
+        /// system
+        #[arg(long)]
+        poll: bool,
+
+        /// How long to wait between looks with --poll, in seconds
+        #[arg(
+            short = 's',
+            long = "sleep-interval",
+            default_value = "1",
+            value_parser = parse_interval,
+        )]
+        interval: Duration,
+    },
+}
+
+// What to write, of which files.
+#[derive(Debug, Args)]
+struct Which {
+    /// Files to write from, or - for stdin, which is used when there are none
+    files: Vec<PathBuf>,
+
+    /// How many lines, or for tail, +N to start from the Nth line
+    #[arg(short = 'n', long, default_value = "10", value_parser = parse_amount)]
+    lines: Amount,
+
+    /// How many bytes, instead of lines, or for tail, +N to start from the Nth byte
+    #[arg(short = 'c', long, value_parser = parse_amount)]
+    bytes: Option<Amount>,
+
+    /// Leave out the headers that say which file is which when there are several
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+// A number of lines or bytes, which with a + in front is where the tail starts rather than how
+// long it is. head pays the + no mind, as head does.
+#[derive(Debug, Clone, Copy)]
+struct Amount {
+    n: u64,
+    from_start: bool,
+}
+
+fn parse_amount(s: &str) -> Result<Amount, std::num::ParseIntError> {
+    let (digits, from_start) = match s.strip_prefix('+') {
+        Some(digits) => (digits, true),
+        None => (s, false),
+    };
+    Ok(Amount {
+        n: digits.parse()?,
+        from_start,
+    })
+}
+
+impl Which {
+    fn amount(&self) -> Amount {
+        self.bytes.unwrap_or(self.lines)
+    }
+
+    fn count(&self) -> Count {
+        let n = self.amount().n;
+        match self.bytes {
+            Some(_) => Count::Bytes(n),
+            None => Count::Lines(n),
+        }
+    }
+
+    fn inputs(&self) -> Vec<Input> {
+        if self.files.is_empty() {
+            return vec![Input::Stdin];
+        }
+        self.files
+            .iter()
+            .map(|path| match path.to_str() {
+                Some("-") => Input::Stdin,
+                _ => Input::File(path.clone()),
+            })
+            .collect()
+    }
+}
+
+enum Input {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Input {
+    fn name(&self) -> String {
+        match self {
+            Input::File(path) => path.display().to_string(),
+            Input::Stdin => "standard input".to_string(),
+        }
+    }
+}
+
+enum Error {
+    // Couldn't read from one of the files, which is no reason not to carry on with the rest.
+    Input(IoError),
+    Output(io::Error),
+}
+
+type Out = LineBuffer<Stdout>;
+
+// Which part of each input to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Head,
+    Tail,
+    // The tail that starts so far from the start.
+    From,
+}
+
+// Writes part of input, after its header if it has one, which is left out if it can't be
+// opened. For a tail of a file, the file is handed back, where the tail ended, for following.
+fn write_one(
+    input: &Input,
+    header: Option<&str>,
+    part: Part,
+    count: Count,
+    out: &mut Out,
+) -> Result<Option<File>, Error> {
+    let name = input.name();
+    let path = match input {
+        Input::Stdin => {
+            write_header(out, header)?;
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            let result = match part {
+                Part::Head => headtail::head(stdin, count, out),
+                Part::Tail => headtail::tail(stdin, count, out),
+                Part::From => headtail::skip_to(&mut stdin, count)
+                    .and_then(|()| io::copy(&mut stdin, out).map(drop)),
+            };
+            // There's no knowing whether it was the reading or the writing that failed, so it's
+            // taken to be the writing, which will fail again when it's flushed if it was.
+            return result.map(|()| None).map_err(Error::Output);
+        }
+        Input::File(path) => path,
+    };
+    let input_error = |err| Error::Input(IoError::new(name.clone(), err));
+    let mut file = File::open(path).map_err(input_error)?;
+    let seekable = file.metadata().map_err(input_error)?.is_file();
+    write_header(out, header)?;
+    match part {
+        Part::Head => {
+            headtail::head(BufReader::new(file), count, out).map_err(Error::Output)?;
+            return Ok(None);
+        }
+        Part::From => {
+            // Reading to the end leaves the file there, ready to be followed.
+            let mut reader = BufReader::new(&mut file);
+            headtail::skip_to(&mut reader, count).map_err(input_error)?;
+            io::copy(&mut reader, out).map_err(Error::Output)?;
+            drop(reader);
+            return Ok(seekable.then_some(file));
+        }
+        Part::Tail => {}
+    }
+    if !seekable {
+        headtail::tail(BufReader::new(file), count, out).map_err(Error::Output)?;
+        return Ok(None);
+    }
+    let start = headtail::tail_start(&mut file, count).map_err(input_error)?;
+    file.seek(SeekFrom::Start(start)).map_err(input_error)?;
+    io::copy(&mut file, out).map_err(Error::Output)?;
+    Ok(Some(file))
+}
+
+fn write_header(out: &mut Out, header: Option<&str>) -> Result<(), Error> {
+    match header {
+        Some(header) => out.write_all(header.as_bytes()).map_err(Error::Output),
+        None => Ok(()),
+    }
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let (which, part, follow) = match &opt.command {
+        Command::Head { which } => (which, Part::Head, None),
+        Command::Tail {
+            which,
+            follow,
+            poll,
+            interval,
+        } => {
+            let part = if which.amount().from_start {
+                Part::From
+            } else {
+                Part::Tail
+            };
+            (which, part, follow.then_some((*poll, *interval)))
+        }
+    };
+    let inputs = which.inputs();
+    let headers = inputs.len() > 1 && !which.quiet;
+    debug!("{:?} of {} files", part, inputs.len());
+
+    let mut out = LineBuffer::new(io::stdout());
+    let mut failed = false;
+    let mut written = None;
+    let mut followed = Vec::new();
+    let mut result = Ok(());
+    let mut any = false;
+    for input in &inputs {
+        let header = headers.then(|| {
+            let blank = if any { "\n" } else { "" };
+            format!("{}==> {} <==\n", blank, input.name())
+        });
+        match write_one(input, header.as_deref(), part, which.count(), &mut out) {
+            Ok(file) => {
+                any = true;
+                written = None;
+                if let (Some(file), Input::File(path)) = (file, input) {
+                    written = Some(followed.len());
+                    followed.push((input.name(), path, file));
+                }
+            }
+            Err(Error::Input(err)) => {
+                // Whatever's been written so far comes before the error, as it did.
+                result = out.flush();
+                eprintln!("error: {}", Report(&err));
+                failed = true;
+            }
+            Err(Error::Output(err)) => result = Err(err),
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+    result = result.and_then(|()| out.flush());
+
+    if let (Ok(()), Some((poll, interval))) = (&result, follow) {
+        // Only files can be followed, so with headers, the last one written from may not be
+        // among them, and the next to be written from will need its header.
+        let mut follower = Follower::new(headers, written, poll, interval);
+        for (name, path, file) in followed {
+            if let Err(err) = follower.add(name, path, file) {
+                eprintln!("error: {}", Report(&err));
+                failed = true;
+            }
+        }
+        info!("following");
+        result = follower.run(&mut out);
+    }
+
+    match Policy::default().check(result) {
+        Ok(Flow::Continue) | Ok(Flow::Stop) => {}
+        Err(err) => {
+            eprintln!("error: {}", Report(&IoError::new("writing to stdout", err)));
+            exit(1);
+        }
+    }
+    exit(if failed { 1 } else { 0 });
+}
@@ -1,37 +1,1071 @@
-use failure::{err_msg, Error};
-use learning_rust::primes::options::{Algorithm, Opt};
-use std::io::{self, BufWriter, Write};
-use structopt::StructOpt;
-
-// By having main return a result, we can have it exit non-zero and print an error when we
-// experience an error by using the ? operator.
-fn main() -> Result<(), Error> {
-    let opt = Opt::from_args();
-
-    let primes = match opt.algorithm {
-        Algorithm::Naive => learning_rust::primes::naive::primes(opt.max),
-        Algorithm::Sieve => {
-            // Sieve allocates a vector sized at opt.max + 1. This limits us to addressable memory
-            // on the system based on the size of usize.
-            if opt.max > (std::usize::MAX - 1) as u64 {
-                return Err(err_msg(format!(
-                    "<max> must be less than {} on this platform",
-                    (std::usize::MAX - 1)
-                )));
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use learning_rust::completions;
+use learning_rust::error::Report;
+use learning_rust::locked_write::tee::TeeWriter;
+use learning_rust::locked_write::{OnError, Policy};
+use learning_rust::log;
+use learning_rust::primes::arith::arith;
+#[cfg(feature = "bigint")]
+use learning_rust::primes::bigint;
+use learning_rust::primes::cache::{self, Bitmap};
+use learning_rust::primes::checkpoint::Checkpoint;
+use learning_rust::primes::constellation::Constellations;
+use learning_rust::primes::count::{self, PI_POWERS_OF_TEN};
+use learning_rust::primes::deadline::{Deadline, Expiry};
+use learning_rust::primes::error::{Error, Result};
+use learning_rust::primes::estimate;
+use learning_rust::primes::factor::Method;
+use learning_rust::primes::format::{Encoder, Format, Radix};
+use learning_rust::primes::gaps::Histogram;
+use learning_rust::primes::limit::Limit;
+use learning_rust::primes::mersenne::lucas_lehmer;
+use learning_rust::primes::options::{Algorithm, Command, MillerRabin, Number, Opt};
+use learning_rust::primes::plot::Plot;
+use learning_rust::primes::primality::{
+    error_bound, next_prime, pow_mod, prev_prime, Primality, Witnesses,
+};
+use learning_rust::primes::primorial::{self, primorial};
+use learning_rust::primes::progress::Progress;
+use learning_rust::primes::query::Answerer;
+use learning_rust::primes::random::{random_prime, random_prime_in};
+use learning_rust::primes::registry::{self, REGISTRY};
+use learning_rust::primes::sieve::{Backend, Table};
+use learning_rust::primes::stats::Stats;
+use learning_rust::primes::{
+    filter, goldbach, memory, pseudoprime, ranges, segmented, serve, sieve, Generator,
+};
+use learning_rust::{debug, info, span};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    // Everything below run returns our own Error, so we can report it the same way every time and
+    // exit with a code that says what kind of failure it was.
+    if let Err(err) = run(&opt) {
+        eprintln!("error: {}", Report(&err));
+        exit(err.exit_code());
+    }
+}
+
+fn run(opt: &Opt) -> Result<()> {
+    if opt.threads == Some(0) {
+        return Err(Error::Argument {
+            argument: "--threads",
+            message: "must be at least 1".to_string(),
+        });
+    }
+    match opt.command {
+        Some(Command::IsPrime { ref n, ref mr }) => {
+            // 0 and 1 are neither prime nor composite.
+            if *n < Number::from(2u8) {
+                return Err(Error::Argument {
+                    argument: "<n>",
+                    message: "must be at least 2".to_string(),
+                });
+            }
+            let verdict = match test_primality(n, mr) {
+                Primality::Prime => "prime".to_string(),
+                Primality::ProbablePrime => probably_prime(mr),
+                Primality::Composite => "composite".to_string(),
+            };
+            println!("{}: {}", n, verdict);
+            Ok(())
+        }
+        Some(Command::Factor { ref n, algorithm }) => {
+            // Every number divides 0, so it has no factorization.
+            if *n == Number::from(0u8) {
+                return Err(Error::Argument {
+                    argument: "<n>",
+                    message: "must be at least 1".to_string(),
+                });
+            }
+            println!("{}: {}", n, factors(n, algorithm)?.join(" "));
+            Ok(())
+        }
+        Some(Command::Arith { n }) => {
+            let result = arith(n).ok_or_else(|| Error::Argument {
+                argument: "<n>",
+                message: "must be at least 1".to_string(),
+            })?;
+            let sigma = result
+                .sigma
+                .map_or("too large for a u128".to_string(), |s| s.to_string());
+            println!("n: {}", n);
+            println!("phi: {}", result.phi);
+            println!("sigma: {}", sigma);
+            println!("d: {}", result.divisors);
+            Ok(())
+        }
+        Some(Command::Next { n, ref mr }) => {
+            let prime = next_prime(n, mr.rounds, mr.witnesses).ok_or_else(|| {
+                Error::NoAnswer(format!("there is no prime >= {} below 2^128", n))
+            })?;
+            println!("{}", prime);
+            Ok(())
+        }
+        Some(Command::Prev { n, ref mr }) => {
+            let prime = prev_prime(n, mr.rounds, mr.witnesses)
+                .ok_or_else(|| Error::NoAnswer(format!("there is no prime <= {}", n)))?;
+            println!("{}", prime);
+            Ok(())
+        }
+        Some(Command::Random { bits, seed, rounds }) => {
+            let mut rng = seeded(seed);
+            let prime = random_prime(bits, rounds, &mut rng).ok_or_else(|| Error::Argument {
+                argument: "--bits",
+                message: "must be between 2 and 128".to_string(),
+            })?;
+            println!("{}", prime);
+            Ok(())
+        }
+        Some(Command::Sample {
+            low,
+            high,
+            count,
+            seed,
+            rounds,
+        }) => sample(low, high, count, seed, rounds),
+        Some(Command::Goldbach { n, all }) => goldbach(opt, n, all),
+        Some(Command::Primorial { n, digits }) => {
+            let product = primorial(generator(opt, Algorithm::Auto, n)?);
+            if digits {
+                println!("{}", primorial::digits(&product));
+            } else {
+                println!("{}", product);
             }
-            learning_rust::primes::sieve::primes(opt.max)
+            Ok(())
         }
+        Some(Command::Pseudoprimes {
+            max,
+            base,
+            carmichael,
+        }) => pseudoprimes(opt, max, base, carmichael),
+        Some(Command::Powmod { base, exp, modulus }) => {
+            if modulus == 0 {
+                return Err(Error::Argument {
+                    argument: "<modulus>",
+                    message: "must be at least 1".to_string(),
+                });
+            }
+            println!("{}", pow_mod(base, exp, modulus));
+            Ok(())
+        }
+        Some(Command::Mersenne { p }) => {
+            let started = Instant::now();
+            let verdict = if lucas_lehmer(p) {
+                "prime"
+            } else {
+                "composite"
+            };
+            println!(
+                "2^{} - 1: {} ({:.3}s)",
+                p,
+                verdict,
+                started.elapsed().as_secs_f64()
+            );
+            Ok(())
+        }
+        Some(Command::Query { ref input, rounds }) => query(opt, input, rounds),
+        Some(Command::Serve { max, ref socket }) => serve(opt, max, socket),
+        Some(Command::Algorithms) => algorithms(),
+        Some(Command::Count { x }) => {
+            memory::guard("<x>", count::footprint(x), opt.max_memory)?;
+            println!("{}", count::count(x));
+            Ok(())
+        }
+        Some(Command::EstimateNth { k }) => {
+            let estimate = estimate::nth(k).ok_or_else(|| Error::Argument {
+                argument: "<k>",
+                message: "must be at least 1".to_string(),
+            })?;
+            println!("lower: {:.0}", estimate.lower);
+            println!("estimate: {:.0}", estimate.estimate);
+            println!("upper: {:.0}", estimate.upper);
+            Ok(())
+        }
+        None => match &opt.ranges {
+            Some(path) => list_ranges(opt, path),
+            None => list(opt),
+        },
+    }
+}
+
+// With the bigint feature, is-prime and factor work on numbers of any size.
+#[cfg(not(feature = "bigint"))]
+fn test_primality(n: &Number, mr: &MillerRabin) -> Primality {
+    learning_rust::primes::primality::is_prime_with(*n, mr.rounds, mr.witnesses)
+}
+
+#[cfg(feature = "bigint")]
+fn test_primality(n: &Number, mr: &MillerRabin) -> Primality {
+    bigint::is_prime(n, mr.rounds, mr.witnesses)
+}
+
+// The verdict for a probable prime, with how likely it is to be wrong.
+fn probably_prime(mr: &MillerRabin) -> String {
+    let bound = error_bound(mr.rounds, mr.witnesses);
+    match mr.witnesses {
+        Witnesses::Random => format!("probably prime (error probability <= {:.1e})", bound),
+        Witnesses::Fixed => format!(
+            "probably prime (error probability <= {:.1e}, unless it was built to fool fixed \
+             witnesses)",
+            bound
+        ),
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn factors(n: &Number, method: Method) -> Result<Vec<String>> {
+    let factors = learning_rust::primes::factor::factor_with(*n, method);
+    Ok(factors.iter().map(|f| f.to_string()).collect())
+}
+
+#[cfg(feature = "bigint")]
+fn factors(n: &Number, method: Method) -> Result<Vec<String>> {
+    let factors = bigint::factor(n, method).ok_or_else(|| Error::Argument {
+        argument: "--algorithm",
+        message: format!("{} only works up to 2^128, use rho or auto", method),
+    })?;
+    Ok(factors.iter().map(|f| f.to_string()).collect())
+}
+
+fn list(opt: &Opt) -> Result<()> {
+    // clap won't let us get here without both positionals, so these expects can't fire.
+    let algorithm = opt.algorithm.as_ref().expect("algorithm is required");
+    let max = opt.max.expect("max is required");
+
+    // Checkpointing always uses the segmented sieve, which is fine as long as nobody explicitly
+    // asked for something else.
+    if opt.checkpoint.is_some() && !matches!(algorithm, Algorithm::Auto | Algorithm::Segmented) {
+        return Err(Error::Argument {
+            argument: "--checkpoint",
+            message: "is only supported by the segmented algorithm".to_string(),
+        });
+    }
+    check_format(opt)?;
+    if opt.checkpoint.is_some() && opt.backend == Backend::Mmap {
+        return Err(Error::Argument {
+            argument: "--checkpoint",
+            message: "can't be combined with --backend mmap".to_string(),
+        });
+    }
+
+    info!(
+        "listing primes up to {} with {}",
+        max,
+        registry::lookup(*algorithm, max).name
+    );
+    if opt.verify {
+        let _span = span!(Info, "verifying");
+        return verify(opt, registry::lookup(*algorithm, max).algorithm, max);
+    }
+    if opt.self_test {
+        let _span = span!(Info, "self-testing");
+        return self_test(opt, registry::lookup(*algorithm, max).algorithm, max);
+    }
+
+    // When checkpointing, we hang on to the most recent checkpoint so we can update it as we go.
+    let mut checkpoint = None;
+    let primes = match &opt.checkpoint {
+        None => generator(opt, *algorithm, max)?,
+        Some(path) => {
+            memory::guard(
+                "<max>",
+                segmented::footprint(max, opt.segment_size.0),
+                opt.max_memory,
+            )?;
+            let saved = if opt.resume {
+                let saved = Checkpoint::load(path)?;
+                info!("resuming from {} at {}", path.display(), saved.next);
+                if saved.max != max {
+                    return Err(Error::Checkpoint {
+                        path: path.clone(),
+                        message: format!("it was saved for <max> {}, not {}", saved.max, max),
+                    });
+                }
+                cut_back(opt, path, &saved)?;
+                saved
+            } else {
+                Checkpoint {
+                    max,
+                    next: 2,
+                    written: None,
+                    base: segmented::base_primes(max),
+                }
+            };
+            let primes = segmented::Primes::new(max, saved.next, saved.base.clone())
+                .segment_size(opt.segment_size.0);
+            checkpoint = Some((path, saved));
+            Box::new(primes)
+        }
+    };
+    let primes = filter(opt, primes)?;
+    // The limit goes on before the progress bar, so the bar finishes when the limit is reached.
+    let primes: Box<dyn Generator> = match opt.limit {
+        Some(limit) => Box::new(Limit::new(primes, limit)),
+        None => primes,
+    };
+    // The deadline goes on before the progress bar too, so the bar stops when time runs out.
+    let (primes, expiry): (Box<dyn Generator>, _) = match opt.max_seconds {
+        Some(seconds) => {
+            let budget = Duration::try_from_secs_f64(seconds)
+                .ok()
+                .filter(|budget| !budget.is_zero())
+                .ok_or_else(|| Error::Argument {
+                    argument: "--max-seconds",
+                    message: "must be a positive number of seconds".to_string(),
+                })?;
+            let (deadline, expiry) = Deadline::new(primes, budget);
+            (Box::new(deadline), Some(expiry))
+        }
+        None => (primes, None),
+    };
+    let mut primes: Box<dyn Generator> = if opt.progress {
+        Box::new(Progress::new(primes))
+    } else {
+        primes
     };
 
-    // By locking stdout ourselves & using writeln! instead of println!, we avoid having to
-    // re-acquire the lock with each write. Then by using a BufWriter instead of stdout directly,
-    // we batch many writes together into a single write syscall.
-    let stdout = io::stdout();
-    let stdout = stdout.lock();
-    let mut stdout = BufWriter::new(stdout);
+    let mut out = output(opt)?;
+    let written = Error::io(format!("writing to {}", destination(opt)));
+    // Most algorithms find their primes as they're asked for them, so this covers finding them
+    // as well as writing them out.
+    let _span = span!(Info, "writing to {}", destination(opt));
+
+    if opt.stats {
+        let mut stats = Stats::collect(&mut primes, max);
+        // Checkpoints and caches never run the chosen algorithm, so they're never parallel.
+        if checkpoint.is_none()
+            && opt.cache_dir.is_none()
+            && opt.load_bitmap.is_none()
+            && opt.dump_bitmap.is_none()
+            && registry::lookup(*algorithm, max).parallel
+        {
+            stats.threads = Some(opt.threads());
+        }
+        // If the limit or the deadline cut us short, the densities should only cover the part of
+        // the range we actually got through.
+        if opt.limit.is_some() || expiry.as_ref().is_some_and(|e| e.reached().is_some()) {
+            stats.max = primes.cursor().saturating_sub(1).min(max);
+        }
+        writeln!(out, "{}", stats)
+            .and_then(|()| finish(out))
+            .map_err(written)?;
+        return in_time(opt, &expiry);
+    }
+    if opt.plot {
+        writeln!(out, "{}", Plot::collect(primes, max, opt.buckets))
+            .and_then(|()| finish(out))
+            .map_err(written)?;
+        return in_time(opt, &expiry);
+    }
+    if opt.gap_histogram {
+        writeln!(out, "{}", Histogram::collect(primes))
+            .and_then(|()| finish(out))
+            .map_err(written)?;
+        return in_time(opt, &expiry);
+    }
+
+    let mut encoder = Encoder::new(opt.format, opt.radix).template(opt.template.clone());
+    let mut last_save = Instant::now();
+    while let Some(i) = primes.next() {
+        if let Err(err) = encoder.write(&mut out, i) {
+            return Err(written(err));
+        }
+        if let Some((path, saved)) = checkpoint.as_mut() {
+            if last_save.elapsed() >= CHECKPOINT_INTERVAL {
+                // The checkpoint claims everything before the cursor has been written, so make
+                // sure it actually has been before saving it.
+                if let Err(err) = out.flush() {
+                    return Err(written(err));
+                }
+                saved.next = primes.cursor();
+                saved.written = output_length(opt)?;
+                saved.save(path)?;
+                debug!("saved a checkpoint at {} to {}", saved.next, path.display());
+                last_save = Instant::now();
+            }
+        }
+    }
+    finish(out).map_err(written)?;
+    if let Some((path, saved)) = checkpoint.as_mut() {
+        saved.next = primes.cursor();
+        saved.written = output_length(opt)?;
+        saved.save(path)?;
+    }
+    in_time(opt, &expiry)
+}
+
+// How long the file the primes are going to is, if they're going to one. --output and --tee get
+// the same bytes, so either will do.
+fn output_length(opt: &Opt) -> Result<Option<u64>> {
+    let path = match opt.output.as_ref().or(opt.tee.as_ref()) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let context = format!("reading the length of {}", path.display());
+    Ok(Some(fs::metadata(path).map_err(Error::io(context))?.len()))
+}
+
+// Cuts the output files back to what they held when the checkpoint was saved, since the primes
+// written after that are about to be written again.
+fn cut_back(opt: &Opt, checkpoint: &Path, saved: &Checkpoint) -> Result<()> {
+    let written = match saved.written {
+        Some(written) => written,
+        None => return Ok(()),
+    };
+    for path in opt.output.iter().chain(&opt.tee) {
+        let context = || format!("cutting {} back to {} bytes", path.display(), written);
+        let file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map_err(Error::io(context()))?;
+        let len = file.metadata().map_err(Error::io(context()))?.len();
+        if len < written {
+            return Err(Error::Checkpoint {
+                path: checkpoint.to_owned(),
+                message: format!(
+                    "it was saved when {} had {} bytes, but it only has {}",
+                    path.display(),
+                    written,
+                    len
+                ),
+            });
+        }
+        file.set_len(written).map_err(Error::io(context()))?;
+    }
+    Ok(())
+}
+
+// Whatever was found before --max-seconds ran out has been written by now, but the exit code
+// should still say it's not everything.
+fn in_time(opt: &Opt, expiry: &Option<Expiry>) -> Result<()> {
+    match expiry.as_ref().and_then(Expiry::reached) {
+        Some(reached) => Err(Error::OutOfTime {
+            seconds: opt.max_seconds.unwrap_or_default(),
+            count: reached.count,
+            largest: reached.largest,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn list_ranges(opt: &Opt, path: &Path) -> Result<()> {
+    if opt.backend == Backend::Mmap {
+        return Err(Error::Argument {
+            argument: "--ranges",
+            message: "can't be combined with --backend mmap".to_string(),
+        });
+    }
+    // Without --merge, each range gets a header line, which only makes sense in text.
+    if !opt.merge && opt.format != Format::Text {
+        return Err(Error::Argument {
+            argument: "--format",
+            message: format!("{} needs --merge with --ranges", opt.format),
+        });
+    }
+    check_format(opt)?;
+
+    let context = || format!("reading {}", path.display());
+    let file = BufReader::new(File::open(path).map_err(Error::io(context()))?);
+    let mut parsed = Vec::new();
+    for line in file.lines() {
+        let line = line.map_err(Error::io(context()))?;
+        parsed.extend(ranges::parse(&line)?);
+    }
+    if opt.merge {
+        parsed = ranges::merge(parsed);
+    }
+    let highest = parsed.iter().map(|&(_, high)| high).max().unwrap_or(0);
+    memory::guard(
+        "--ranges",
+        segmented::footprint(highest, opt.segment_size.0),
+        opt.max_memory,
+    )?;
+
+    let mut out = output(opt)?;
+    let written = format!("writing to {}", destination(opt));
+    // One encoder for everything, so that varint gaps carry on across merged ranges. Ranges that
+    // aren't merged can overlap or go backwards, so each starts again with one of its own.
+    let new_encoder = || Encoder::new(opt.format, opt.radix).template(opt.template.clone());
+    let mut encoder = new_encoder();
+    let mut remaining = opt.limit.unwrap_or(u64::MAX);
+    for (&(low, high), primes) in parsed
+        .iter()
+        .zip(ranges::primes(&parsed, opt.segment_size.0))
+    {
+        if !opt.merge {
+            writeln!(out, "# {}..={}", low, high).map_err(Error::io(written.clone()))?;
+            encoder = new_encoder();
+        }
+        // --limit counts the primes across every range, not in each one.
+        for p in Limit::new(filter(opt, primes)?, remaining) {
+            encoder
+                .write(&mut out, p)
+                .map_err(Error::io(written.clone()))?;
+            remaining -= 1;
+        }
+    }
+    finish(out).map_err(Error::io(written))
+}
+
+// --radix and --template only make sense for text.
+fn check_format(opt: &Opt) -> Result<()> {
+    if opt.format != Format::Text && opt.radix != Radix::Decimal {
+        return Err(Error::Argument {
+            argument: "--radix",
+            message: format!("doesn't apply to --format {}", opt.format),
+        });
+    }
+    if opt.format != Format::Text && opt.template.is_some() {
+        return Err(Error::Argument {
+            argument: "--template",
+            message: format!("doesn't apply to --format {}", opt.format),
+        });
+    }
+    Ok(())
+}
+
+// Wraps primes in whichever filters were asked for. They go on before the limit, so that it counts
+// the primes that make it through.
+fn filter(opt: &Opt, primes: Box<dyn Generator>) -> Result<Box<dyn Generator>> {
+    let mut primes = primes;
+    // The pattern needs to see every prime, so it goes before anything that drops some.
+    if let Some(ref pattern) = opt.pattern {
+        primes = Box::new(Constellations::new(primes, pattern.clone()));
+    }
+    if let (Some(modulus), Some(residue)) = (opt.modulus, opt.residue) {
+        if modulus == 0 {
+            return Err(Error::Argument {
+                argument: "--mod",
+                message: "must be at least 1".to_string(),
+            });
+        }
+        if residue >= modulus {
+            return Err(Error::Argument {
+                argument: "--residue",
+                message: format!("must be less than --mod {}", modulus),
+            });
+        }
+        primes = Box::new(filter::residue(primes, modulus, residue));
+    }
+    if opt.palindromic {
+        primes = Box::new(filter::palindromic(primes, opt.radix));
+    }
+    if opt.sophie_germain {
+        primes = Box::new(filter::sophie_germain(primes));
+    }
+    if opt.safe {
+        primes = Box::new(filter::safe(primes));
+    }
+    Ok(primes)
+}
+
+// ChaCha rather than StdRng since its output for a given seed is guaranteed not to change between
+// releases, which is the whole point of taking a seed.
+fn seeded(seed: Option<u64>) -> ChaCha20Rng {
+    match seed {
+        Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+        None => ChaCha20Rng::from_entropy(),
+    }
+}
+
+// Sampling is with replacement. Without it, we'd have to know there were count primes in the
+// range before starting, and counting them could take far longer than picking them.
+fn sample(low: u128, high: u128, count: u64, seed: Option<u64>, rounds: usize) -> Result<()> {
+    if low > high {
+        return Err(Error::Argument {
+            argument: "<high>",
+            message: format!("must be at least <low> {}", low),
+        });
+    }
+    let mut rng = seeded(seed);
+    let mut out = io::stdout().lock();
+    for _ in 0..count {
+        let prime = random_prime_in(low, high, rounds, &mut rng)
+            .ok_or_else(|| Error::NoAnswer(format!("there are no primes in {}..={}", low, high)))?;
+        writeln!(out, "{}", prime).map_err(Error::io("writing to stdout"))?;
+    }
+    Ok(())
+}
+
+fn goldbach(opt: &Opt, n: u64, all: bool) -> Result<()> {
+    if n < 4 || !n.is_multiple_of(2) {
+        return Err(Error::Argument {
+            argument: "<n>",
+            message: "must be an even number greater than 2".to_string(),
+        });
+    }
+    if n > (usize::MAX - 1) as u64 {
+        return Err(Error::Argument {
+            argument: "<n>",
+            message: format!("must be less than {} on this platform", (usize::MAX - 1)),
+        });
+    }
+    memory::guard("<n>", sieve::footprint(n), opt.max_memory)?;
+    let table = Table::new(n);
 
-    for i in primes {
-        writeln!(stdout, "{}", i)?;
+    let mut out = io::stdout().lock();
+    let mut found = false;
+    for (p, q) in goldbach::pairs(&table, n) {
+        found = true;
+        writeln!(out, "{} = {} + {}", n, p, q).map_err(Error::io("writing to stdout"))?;
+        if !all {
+            break;
+        }
+    }
+    if !found {
+        // If this ever fires, it's time to write a paper.
+        return Err(Error::NoAnswer(format!(
+            "{} is not the sum of two primes",
+            n
+        )));
     }
     Ok(())
 }
+
+fn pseudoprimes(opt: &Opt, max: u64, base: Option<u64>, carmichael: bool) -> Result<()> {
+    let base = base.unwrap_or(2);
+    if base < 2 {
+        return Err(Error::Argument {
+            argument: "--base",
+            message: "must be at least 2".to_string(),
+        });
+    }
+    if max > (usize::MAX - 1) as u64 {
+        return Err(Error::Argument {
+            argument: "<max>",
+            message: format!("must be less than {} on this platform", (usize::MAX - 1)),
+        });
+    }
+    memory::guard("<max>", sieve::footprint(max), opt.max_memory)?;
+    let table = Table::new(max);
+
+    let found: Box<dyn Iterator<Item = u64>> = if carmichael {
+        Box::new(pseudoprime::carmichael(&table))
+    } else {
+        Box::new(pseudoprime::fermat(&table, base))
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    for n in found {
+        writeln!(out, "{}", n).map_err(Error::io("writing to stdout"))?;
+    }
+    out.flush().map_err(Error::io("writing to stdout"))
+}
+
+// Answers go out one per line in the same order as the queries came in, so a bad query gets an
+// error line of its own rather than throwing everything after it out of step.
+fn query(opt: &Opt, input: &Path, rounds: usize) -> Result<()> {
+    let (name, input): (String, Box<dyn BufRead>) = if input == Path::new("-") {
+        ("stdin".to_string(), Box::new(io::stdin().lock()))
+    } else {
+        let context = format!("opening {}", input.display());
+        let file = File::open(input).map_err(Error::io(context))?;
+        (input.display().to_string(), Box::new(BufReader::new(file)))
+    };
+    let mut answerer = Answerer::new(rounds, opt.max_memory);
+    let mut out = io::stdout().lock();
+    for line in input.lines() {
+        let line = line.map_err(Error::io(format!("reading {}", name)))?;
+        let answer = match answerer.answer(&line) {
+            Ok(Some(answer)) => answer,
+            Ok(None) => continue,
+            Err(err) => format!("error: {}", Report(&err)),
+        };
+        writeln!(out, "{}", answer).map_err(Error::io("writing to stdout"))?;
+    }
+    Ok(())
+}
+
+fn serve(opt: &Opt, max: u64, socket: &Path) -> Result<()> {
+    let needed = memory::Bytes(
+        serve::footprint(max)
+            .0
+            .saturating_add(segmented::footprint(max, opt.segment_size.0).0),
+    );
+    memory::guard("--max", needed, opt.max_memory)?;
+
+    // A socket left behind by a server that didn't get to clean up would stop us binding, but only
+    // clear it away if nothing is listening on it anymore.
+    if socket.exists() && UnixStream::connect(socket).is_err() {
+        let context = format!("removing stale socket {}", socket.display());
+        fs::remove_file(socket).map_err(Error::io(context))?;
+    }
+    let context = format!("listening on {}", socket.display());
+    let listener = UnixListener::bind(socket).map_err(Error::io(context))?;
+
+    // Sieve after binding, so we don't spend the time only to find the socket's taken.
+    let primes = {
+        let _span = span!(Info, "sieving up to {}", max);
+        Arc::new(serve::Primes::new(max, opt.segment_size.0))
+    };
+    eprintln!(
+        "serving primes up to {} on {}",
+        primes.max(),
+        socket.display()
+    );
+    for stream in listener.incoming() {
+        let stream = stream.map_err(Error::io("accepting a connection"))?;
+        debug!("accepted a connection");
+        let primes = Arc::clone(&primes);
+        thread::spawn(move || {
+            // A client that goes away mid-conversation is its own problem, not the server's.
+            let _ = answer_connection(&primes, stream);
+        });
+    }
+    Ok(())
+}
+
+// Like query, a bad request gets an error line of its own so the answers stay in step.
+fn answer_connection(primes: &serve::Primes, stream: UnixStream) -> io::Result<()> {
+    let mut out = BufWriter::new(stream.try_clone()?);
+    for line in BufReader::new(stream).lines() {
+        let answer = match primes.answer(&line?) {
+            Ok(Some(answer)) => answer,
+            Ok(None) => continue,
+            Err(err) => format!("error: {}", Report(&err)),
+        };
+        writeln!(out, "{}", answer)?;
+        // Clients wait for each answer before sending the next request.
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn generator(opt: &Opt, algorithm: Algorithm, max: u64) -> Result<Box<dyn Generator>> {
+    if opt.backend == Backend::Mmap {
+        return mapped(algorithm, max);
+    }
+    if let Some(dir) = &opt.cache_dir {
+        return cached(opt, algorithm, max, dir);
+    }
+    if let Some(path) = &opt.dump_bitmap {
+        return dumped(opt, algorithm, max, path);
+    }
+    if let Some(path) = &opt.load_bitmap {
+        return loaded(algorithm, max, path);
+    }
+    registered(opt, algorithm, max)
+}
+
+// The primes up to max from the bitmap in the cache directory, if it covers max. Otherwise we
+// sieve a new one and leave it there for next time.
+fn cached(opt: &Opt, algorithm: Algorithm, max: u64, dir: &Path) -> Result<Box<dyn Generator>> {
+    bitmap_algorithm("--cache-dir", algorithm)?;
+    let path = cache::path(dir);
+    let context = format!("reading cache {}", path.display());
+    if let Some(bitmap) = Bitmap::load(&path).map_err(Error::io(context))? {
+        if bitmap.max() >= max {
+            debug!("using the cached bitmap up to {}", bitmap.max());
+            return Ok(bitmap.primes(max));
+        }
+        debug!("the cached bitmap only goes up to {}", bitmap.max());
+    }
+
+    let bitmap = sieve_bitmap(opt, "--cache-dir", algorithm, max)?;
+    let context = format!("creating cache directory {}", dir.display());
+    fs::create_dir_all(dir).map_err(Error::io(context))?;
+    let context = format!("writing cache {}", path.display());
+    bitmap.save(&path).map_err(Error::io(context))?;
+    Ok(bitmap.primes(max))
+}
+
+// Sieves a bitmap to write to --dump-bitmap, and lists the primes from it.
+fn dumped(opt: &Opt, algorithm: Algorithm, max: u64, path: &Path) -> Result<Box<dyn Generator>> {
+    let bitmap = sieve_bitmap(opt, "--dump-bitmap", algorithm, max)?;
+    let context = format!("writing bitmap {}", path.display());
+    bitmap.save(path).map_err(Error::io(context))?;
+    Ok(bitmap.primes(max))
+}
+
+// The primes up to max from a bitmap that --dump-bitmap or --cache-dir wrote, without sieving.
+fn loaded(algorithm: Algorithm, max: u64, path: &Path) -> Result<Box<dyn Generator>> {
+    bitmap_algorithm("--load-bitmap", algorithm)?;
+    let context = format!("reading bitmap {}", path.display());
+    // A missing cache is fine, since we'd just sieve a new one, but a missing bitmap isn't.
+    fs::metadata(path).map_err(Error::io(context.clone()))?;
+    let bitmap = Bitmap::load(path)
+        .map_err(Error::io(context))?
+        .ok_or_else(|| Error::Argument {
+            argument: "--load-bitmap",
+            message: format!("{} isn't a prime bitmap", path.display()),
+        })?;
+    if bitmap.max() < max {
+        return Err(Error::Argument {
+            argument: "<max>",
+            message: format!(
+                "must be at most {}, which is as far as {} goes",
+                bitmap.max(),
+                path.display()
+            ),
+        });
+    }
+    Ok(bitmap.primes(max))
+}
+
+// Either sieve gives the same bitmap, but the naive algorithms are only worth running to see how
+// slow they are, which a bitmap would defeat.
+fn bitmap_algorithm(argument: &'static str, algorithm: Algorithm) -> Result<()> {
+    if !matches!(
+        algorithm,
+        Algorithm::Auto | Algorithm::Sieve | Algorithm::Segmented
+    ) {
+        return Err(Error::Argument {
+            argument,
+            message: "is only supported by the sieve algorithms".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn sieve_bitmap(
+    opt: &Opt,
+    argument: &'static str,
+    algorithm: Algorithm,
+    max: u64,
+) -> Result<Bitmap> {
+    bitmap_algorithm(argument, algorithm)?;
+    if max == u64::MAX {
+        return Err(Error::Argument {
+            argument: "<max>",
+            message: format!("must be less than {} with {}", u64::MAX, argument),
+        });
+    }
+    let needed = cache::footprint(max).0;
+    let needed = needed.saturating_add(segmented::footprint(max, opt.segment_size.0).0);
+    memory::guard("<max>", memory::Bytes(needed), opt.max_memory)?;
+    let _span = span!(Info, "sieving a bitmap up to {}", max);
+    Ok(Bitmap::sieve(max, opt.segment_size.0))
+}
+
+// An algorithm straight from the registry, ignoring --backend.
+fn registered(opt: &Opt, algorithm: Algorithm, max: u64) -> Result<Box<dyn Generator>> {
+    let entry = registry::lookup(algorithm, max);
+    if max > entry.max {
+        return Err(Error::Argument {
+            argument: "<max>",
+            message: format!("must be less than {} on this platform", entry.max),
+        });
+    }
+    if let Some(footprint) = entry.footprint {
+        memory::guard("<max>", footprint(opt, max), opt.max_memory).map_err(|err| {
+            if entry.algorithm == Algorithm::Sieve {
+                err.hint(" (--backend mmap can sieve on disk instead)")
+            } else {
+                err
+            }
+        })?;
+    }
+    // Some algorithms do all their work up front, others only start once asked for a prime.
+    let _span = span!(Debug, "setting up {}", entry.name);
+    Ok((entry.primes)(opt, max))
+}
+
+// The sieve, with its table in a temporary file. It's only there to get past the memory guard, so
+// it's not in the registry for auto to pick, but auto can mean it when asked for by --backend.
+fn mapped(algorithm: Algorithm, max: u64) -> Result<Box<dyn Generator>> {
+    if !matches!(algorithm, Algorithm::Auto | Algorithm::Sieve) {
+        return Err(Error::Argument {
+            argument: "--backend",
+            message: "mmap is only supported by the sieve algorithm".to_string(),
+        });
+    }
+    sieve::mapped(max).map_err(Error::io("creating the sieve's temporary file"))
+}
+
+fn algorithms() -> Result<()> {
+    write_algorithms(&mut io::stdout().lock()).map_err(Error::io("writing to stdout"))
+}
+
+fn write_algorithms<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:<14} {:<18} {:<12} {:<20} auto",
+        "name", "time", "space", "max"
+    )?;
+    for entry in REGISTRY {
+        let auto = match entry.auto_up_to {
+            Some(up_to) => format!("up to {}", up_to),
+            None => "never".to_string(),
+        };
+        writeln!(
+            out,
+            "{:<14} {:<18} {:<12} {:<20} {}",
+            entry.name, entry.time, entry.space, entry.max, auto
+        )?;
+    }
+    Ok(())
+}
+
+// Runs the chosen algorithm alongside a reference and stops at the first prime they disagree on.
+// Naive is far too slow to be a useful reference, so we check against the sieve, or against the
+// segmented sieve when it's the sieve being checked.
+fn verify(opt: &Opt, algorithm: Algorithm, max: u64) -> Result<()> {
+    let reference = match algorithm {
+        Algorithm::Sieve => Algorithm::Segmented,
+        _ => Algorithm::Sieve,
+    };
+    // --backend only applies to the algorithm being checked.
+    let mut expected = registered(opt, reference, max)?;
+    let mut actual = generator(opt, algorithm, max)?;
+    if let Some(limit) = opt.limit {
+        expected = Box::new(Limit::new(expected, limit));
+        actual = Box::new(Limit::new(actual, limit));
+    }
+
+    let mut index = 0;
+    loop {
+        index += 1;
+        match (expected.next(), actual.next()) {
+            (None, None) => break,
+            (Some(e), Some(a)) if e == a => continue,
+            (e, a) => {
+                let show = |p: Option<u64>| p.map_or("nothing".to_string(), |p| p.to_string());
+                return Err(Error::Verify {
+                    index,
+                    reference: reference.to_string(),
+                    expected: show(e),
+                    algorithm: algorithm.to_string(),
+                    actual: show(a),
+                });
+            }
+        }
+    }
+    println!(
+        "{} and {} agree on all {} primes up to {}",
+        algorithm,
+        reference,
+        index - 1,
+        max
+    );
+    Ok(())
+}
+
+// Counts the primes as they come, checking the count each time we pass a power of 10.
+fn self_test(opt: &Opt, algorithm: Algorithm, max: u64) -> Result<()> {
+    let mut primes = generator(opt, algorithm, max)?.peekable();
+    let mut found = 0;
+    let mut checked = 0;
+    for (power, &expected) in PI_POWERS_OF_TEN.iter().enumerate() {
+        let x = 10u64.pow(power as u32);
+        if x > max {
+            break;
+        }
+        while primes.next_if(|&p| p <= x).is_some() {
+            found += 1;
+        }
+        if found != expected {
+            return Err(Error::SelfTest {
+                algorithm: algorithm.to_string(),
+                power: power as u32,
+                expected,
+                actual: found,
+            });
+        }
+        println!("pi(10^{}) = {}", power, found);
+        checked += 1;
+    }
+    println!("{} passed all {} checks up to {}", algorithm, checked, max);
+    Ok(())
+}
+
+fn output(opt: &Opt) -> Result<BufWriter<Output>> {
+    // Resuming carries on from where the last run's files were cut back to, rather than starting
+    // them over.
+    let open = |path: &Path| {
+        let file = if opt.resume {
+            OpenOptions::new().append(true).create(true).open(path)
+        } else {
+            File::create(path)
+        };
+        file.map_err(Error::io(format!("creating {}", path.display())))
+    };
+    // By locking stdout ourselves & using writeln! instead of println!, we avoid having to
+    // re-acquire the lock with each write.
+    let out: Box<dyn Write> = match &opt.output {
+        Some(path) => Box::new(open(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    // The copy's made before compressing, so both get exactly the same bytes. Whatever's reading
+    // the primes going away only stops them going there, so the copy still gets all of them, but
+    // anything else going wrong with either is an error, the same as it would be on its own.
+    let out: Box<dyn Write> = match &opt.tee {
+        Some(path) => {
+            let copy = open(path)?;
+            let strict = Policy {
+                broken_pipe: OnError::Propagate,
+                other: OnError::Propagate,
+            };
+            Box::new(
+                TeeWriter::new()
+                    .sink(out, Policy::default())
+                    .sink(Box::new(copy), strict),
+            )
+        }
+        None => out,
+    };
+    let out = if opt.gzip {
+        Output::Gzip(GzEncoder::new(out, Compression::default()))
+    } else {
+        Output::Plain(out)
+    };
+    // Then by using a BufWriter instead of writing directly, we batch many writes together into a
+    // single write syscall.
+    Ok(BufWriter::new(out))
+}
+
+// Where the primes go. The gzip encoder is kept as it is, rather than boxed, so that it can be
+// finished, since dropping it writes the trailer but throws away any error doing so.
+enum Output {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(out) => out.write(buf),
+            Output::Gzip(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(out) => out.flush(),
+            Output::Gzip(out) => out.flush(),
+        }
+    }
+}
+
+// Writes whatever's still buffered, and for gzip the trailer, once there's nothing more to come.
+fn finish(out: BufWriter<Output>) -> io::Result<()> {
+    match out.into_inner().map_err(|err| err.into_error())? {
+        Output::Plain(mut out) => out.flush(),
+        Output::Gzip(out) => out.finish()?.flush(),
+    }
+}
+
+// Where the primes are going, for error messages.
+fn destination(opt: &Opt) -> String {
+    let out = match &opt.output {
+        Some(path) => path.display().to_string(),
+        None => "stdout".to_string(),
+    };
+    match &opt.tee {
+        Some(path) => format!("{} and {}", out, path.display()),
+        None => out,
+    }
+}
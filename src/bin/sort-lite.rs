@@ -0,0 +1,71 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::Report;
+use learning_rust::log;
+use learning_rust::sort::{parse_size, Order, Sorter};
+use learning_rust::span;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::process::exit;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "sort-lite",
+    version,
+    about = "Sort the lines of stdin, spilling to temporary files when they don't fit in memory."
+)]
+struct Opt {
+    /// Compare the numbers the lines start with, rather than the lines as text
+    #[arg(short, long = "numeric-sort")]
+    numeric: bool,
+
+    /// Largest first
+    #[arg(short, long)]
+    reverse: bool,
+
+    /// Only write the first of each set of lines that are the same, or with -n, that start with
+    /// the same number
+    #[arg(short, long)]
+    unique: bool,
+
+    /// How much of the input to sort in memory at once, in bytes or with K, M or G after
+    #[arg(short = 'S', long, default_value = "64M", value_parser = parse_size)]
+    buffer_size: usize,
+
+    /// Where to spill to when the input doesn't fit (default: the system's temporary directory)
+    #[arg(short = 'T', long = "temporary-directory")]
+    temp_dir: Option<PathBuf>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let sorter = Sorter {
+        order: Order {
+            numeric: opt.numeric,
+            reverse: opt.reverse,
+        },
+        unique: opt.unique,
+        budget: opt.buffer_size,
+        temp_dir: opt.temp_dir,
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let _span = span!(Info, "sorting");
+    match sorter.sort(stdin.lock(), &mut out) {
+        // Whoever was reading, e.g. head, has all they wanted.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            eprintln!("error: {}", Report(&err));
+            exit(2);
+        }
+        Ok(()) => {}
+    }
+}
@@ -0,0 +1,487 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::Report;
+use learning_rust::log;
+use learning_rust::table::delimited::{self, Records};
+use learning_rust::table::stream::{Stream, Widths};
+use learning_rust::table::{Align, Border, Compare, Numbers, Overflow, Table};
+use learning_rust::{debug, info, span};
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::process::exit;
+use std::str::FromStr;
+use terminal_size::Width;
+use thiserror::Error;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "table",
+    version,
+    about = "Line up the columns of delimited text from stdin, like column -t."
+)]
+struct Opt {
+    /// What separates the fields: a single character, or tab
+    #[arg(short, long, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: char,
+
+    /// Treat double quotes as ordinary characters rather than as quoting fields
+    #[arg(long)]
+    no_quoting: bool,
+
+    /// The first record names the columns, and is set apart from the rest
+    #[arg(long)]
+    header: bool,
+
+    /// What to draw around the cells when writing text: none, ascii or unicode
+    #[arg(long, default_value = "none", value_parser = parse_border)]
+    border: Border,
+
+    /// What to write: text, lined up with --border; markdown, a GitHub flavored pipe table; or
+    /// html, a table element
+    #[arg(long, default_value = "text")]
+    format: Format,
+
+    /// A class for the table element when writing html, for a stylesheet to pick it out by
+    #[arg(long)]
+    class: Option<String>,
+
+    /// Sort the rows by a column, given by its number from 1 or, with --header, its name. Add
+    /// :numeric to compare numbers rather than text, and :desc for largest first,
+    /// e.g. size:numeric:desc
+    #[arg(long)]
+    sort_by: Option<SortBy>,
+
+    /// Keep a column, by number or name, to at most this wide, e.g. path:40
+    #[arg(long = "max-width")]
+    max_widths: Vec<ForColumn<usize>>,
+
+    /// Align a column, by number or name: left, right, center, or decimal to line up its decimal
+    /// points, e.g. seconds:decimal. Columns of numbers are otherwise on the right, the rest left
+    #[arg(long = "align")]
+    aligns: Vec<ForColumn<Align>>,
+
+    /// Write the numbers in a column with , between thousands and/or to so many decimal places,
+    /// e.g. bytes:, or seconds:.3 or total:,.2
+    #[arg(long)]
+    numbers: Vec<ForColumn<Numbers>>,
+
+    /// Wrap cells that are too wide onto more lines, rather than cut them short
+    #[arg(long)]
+    wrap: bool,
+
+    /// Narrow the widest columns until the table fits in this many columns (default: the
+    /// terminal's width, if writing to one); 0 for no limit
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Write rows as they come in rather than once they're all in, with the columns as wide as
+    /// the first this many rows need. Later rows are cut short, or wrapped, to fit
+    #[arg(long, conflicts_with = "sort_by")]
+    stream: Option<usize>,
+
+    /// Write rows as they come in, with the columns this wide, e.g. 8,30,12
+    #[arg(long, conflicts_with_all = ["sort_by", "stream", "max_widths", "width"])]
+    widths: Option<ColumnWidths>,
+
+    /// Only write these columns, in this order, by number or name, e.g. 3,1,name. Other options
+    /// number the columns as they're written
+    #[arg(long)]
+    columns: Option<Columns>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Columns(Vec<Column>);
+
+impl FromStr for Columns {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Columns)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ColumnWidths(Vec<usize>);
+
+impl FromStr for ColumnWidths {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|width| {
+                width
+                    .trim()
+                    .parse()
+                    .map_err(|err| format!("{}: {}", width, err))
+            })
+            .collect::<Result<_, _>>()
+            .map(ColumnWidths)
+    }
+}
+
+// Something about a column, given as <column>:<value>.
+#[derive(Debug, Clone)]
+struct ForColumn<T> {
+    column: Column,
+    value: T,
+}
+
+impl<T> FromStr for ForColumn<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Names can have colons in them, but values don't.
+        let (column, value) = s
+            .rsplit_once(':')
+            .ok_or_else(|| "expected <column>:<value>".to_string())?;
+        Ok(ForColumn {
+            column: column.parse()?,
+            value: value.parse().map_err(|err| format!("{}", err))?,
+        })
+    }
+}
+
+impl<T> ForColumn<T> {
+    fn index(&self, argument: &'static str, table: &Table) -> Result<usize, Error> {
+        self.column.index(table).ok_or_else(|| Error::NoColumn {
+            argument,
+            column: self.column.clone(),
+        })
+    }
+}
+
+// A column, by where it is or what it's called.
+#[derive(Debug, Clone)]
+enum Column {
+    Number(usize),
+    Name(String),
+}
+
+impl FromStr for Column {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<usize>() {
+            Ok(0) => Err("columns are numbered from 1".to_string()),
+            Ok(n) => Ok(Column::Number(n)),
+            Err(_) if s.is_empty() => Err("expected a column number or name".to_string()),
+            Err(_) => Ok(Column::Name(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Number(n) => write!(f, "{}", n),
+            Column::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Column {
+    // Where it is in the table, from 0. A table with nothing in it yet, as when streaming
+    // without a header, could go on to have any number of columns.
+    fn index(&self, table: &Table) -> Option<usize> {
+        let names: Option<Vec<&str>> = table
+            .head()
+            .map(|head| head.iter().map(|cell| cell.text()).collect());
+        self.position(table.widths().len(), names.as_deref())
+    }
+
+    // Where it is among this many columns with these names, if they have any.
+    fn position(&self, columns: usize, names: Option<&[&str]>) -> Option<usize> {
+        match self {
+            Column::Number(n) => Some(n - 1).filter(|&i| i < columns || columns == 0),
+            Column::Name(name) => names?.iter().position(|n| n == name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SortBy {
+    column: Column,
+    compare: Compare,
+    descending: bool,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Names can have colons in them, so the options come off the end first.
+        let mut sort_by = SortBy {
+            column: Column::Number(1),
+            compare: Compare::Text,
+            descending: false,
+        };
+        let mut rest = s;
+        loop {
+            if let Some(column) = rest.strip_suffix(":numeric") {
+                sort_by.compare = Compare::Numeric;
+                rest = column;
+            } else if let Some(column) = rest.strip_suffix(":desc") {
+                sort_by.descending = true;
+                rest = column;
+            } else {
+                break;
+            }
+        }
+        sort_by.column = rest.parse()?;
+        Ok(sort_by)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Text,
+    Markdown,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "markdown" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            _ => Err(format!(
+                "unknown format {}, expected text, markdown or html",
+                s
+            )),
+        }
+    }
+}
+
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (s, chars.next(), chars.next()) {
+        ("tab", _, _) | ("\\t", _, _) => Ok('\t'),
+        (_, Some('"'), None) => Err("a quote can't be the delimiter".to_string()),
+        (_, Some('\n'), None) | (_, Some('\r'), None) => {
+            Err("a line break can't be the delimiter".to_string())
+        }
+        (_, Some(c), None) => Ok(c),
+        _ => Err("expected a single character, or tab".to_string()),
+    }
+}
+
+// Border knows markdown too, but here that's what --format markdown is for.
+fn parse_border(s: &str) -> Result<Border, String> {
+    match s.parse() {
+        Ok(Border::Markdown) => {
+            Err("markdown isn't a border here, use --format markdown".to_string())
+        }
+        Ok(border) => Ok(border),
+        Err(_) => Err(format!(
+            "unknown border {}, expected none, ascii or unicode",
+            s
+        )),
+    }
+}
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("invalid input")]
+    Input(#[from] delimited::Error),
+
+    #[error("{argument}: there's no column {column}")]
+    NoColumn {
+        argument: &'static str,
+        column: Column,
+    },
+
+    #[error("{argument} can't be used with --format html, which has no widths to keep to")]
+    NotStreamable { argument: &'static str },
+
+    #[error("writing to stdout")]
+    Output(#[source] io::Error),
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    if let Err(err) = run(&opt) {
+        eprintln!("error: {}", Report(&err));
+        exit(2);
+    }
+}
+
+fn run(opt: &Opt) -> Result<(), Error> {
+    let stdin = io::stdin();
+    let mut records = Records::new(stdin.lock(), opt.delimiter, !opt.no_quoting).peekable();
+    let header = if opt.header {
+        records.next().transpose()?
+    } else {
+        None
+    };
+    // Without a header, the first record says how many columns there are to choose from.
+    let first = records.peek().and_then(|record| record.as_ref().ok());
+    let selected = match &opt.columns {
+        Some(Columns(columns)) => Some(select(columns, header.as_deref(), first)?),
+        None => None,
+    };
+    let project = |record: Vec<String>| match &selected {
+        // A record too short to have a column gets an empty cell for it.
+        Some(indices) => indices
+            .iter()
+            .map(|&i| record.get(i).cloned().unwrap_or_default())
+            .collect(),
+        None => record,
+    };
+    let mut table = Table::new();
+    table.border(match opt.format {
+        Format::Text => opt.border,
+        Format::Markdown => Border::Markdown,
+        Format::Html => Border::None,
+    });
+    if let Some(header) = header {
+        table.header(project(header));
+    }
+    let records = records.map(|record| record.map(project));
+    let widths = match (&opt.widths, opt.stream) {
+        (Some(ColumnWidths(widths)), _) => Some(("--widths", Widths::Fixed(widths.clone()))),
+        (None, Some(rows)) => Some(("--stream", Widths::Learn(rows))),
+        (None, None) => None,
+    };
+    let result = match widths {
+        // clap can't tell --format html apart from the default text, so this is left to us.
+        Some((argument, _)) if matches!(opt.format, Format::Html) => {
+            Err(Error::NotStreamable { argument })
+        }
+        Some((_, widths)) => stream(opt, table, records, widths),
+        None => write(opt, table, records),
+    };
+    match result {
+        // Whoever was reading, e.g. head, has all they wanted, so there's nothing more to do.
+        Err(Error::Output(err)) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+// The index of each of the columns --columns asks for.
+fn select(
+    columns: &[Column],
+    header: Option<&[String]>,
+    first: Option<&Vec<String>>,
+) -> Result<Vec<usize>, Error> {
+    let names: Option<Vec<&str>> = header.map(|header| header.iter().map(String::as_str).collect());
+    let count = header.map_or(first.map_or(0, Vec::len), <[String]>::len);
+    columns
+        .iter()
+        .map(|column| {
+            column
+                .position(count, names.as_deref())
+                .ok_or_else(|| Error::NoColumn {
+                    argument: "--columns",
+                    column: column.clone(),
+                })
+        })
+        .collect()
+}
+
+fn overflow(opt: &Opt) -> Overflow {
+    if opt.wrap {
+        Overflow::Wrap
+    } else {
+        Overflow::Truncate
+    }
+}
+
+// Applies the options that are given column by column.
+fn per_column(opt: &Opt, table: &mut Table) -> Result<(), Error> {
+    for max_width in &opt.max_widths {
+        let column = max_width.index("--max-width", table)?;
+        table.max_width(column, max_width.value, overflow(opt));
+    }
+    for align in &opt.aligns {
+        let column = align.index("--align", table)?;
+        table.align(column, align.value);
+    }
+    for numbers in &opt.numbers {
+        let column = numbers.index("--numbers", table)?;
+        table.numbers(column, numbers.value);
+    }
+    Ok(())
+}
+
+fn width(opt: &Opt) -> Option<usize> {
+    opt.width
+        .or_else(|| {
+            // Only a terminal has a width to keep to; a pipe or a file can be as wide as it likes.
+            terminal_size::terminal_size_of(io::stdout())
+                .map(|(Width(width), _)| usize::from(width))
+        })
+        .filter(|&width| width > 0)
+}
+
+// Reads every record, then writes the table once they're all in.
+fn write<R>(opt: &Opt, mut table: Table, records: R) -> Result<(), Error>
+where
+    R: Iterator<Item = Result<Vec<String>, delimited::Error>>,
+{
+    let span = span!(Info, "reading the table");
+    let mut rows = 0;
+    for record in records {
+        table.row(record?);
+        rows += 1;
+    }
+    drop(span);
+    debug!("read {} rows", rows);
+    if let Some(sort_by) = &opt.sort_by {
+        let column = sort_by
+            .column
+            .index(&table)
+            .ok_or_else(|| Error::NoColumn {
+                argument: "--sort-by",
+                column: sort_by.column.clone(),
+            })?;
+        table.sort(column, sort_by.compare, sort_by.descending);
+    }
+    per_column(opt, &mut table)?;
+    if let Some(width) = width(opt) {
+        debug!("fitting the table to {} columns", width);
+        table.fit(width, overflow(opt));
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = BufWriter::new(stdout.lock());
+    match (opt.format, &opt.class) {
+        (Format::Html, Some(class)) => write!(stdout, "{}", table.html().class(class)),
+        (Format::Html, None) => write!(stdout, "{}", table.html()),
+        _ => write!(stdout, "{}", table),
+    }
+    .and_then(|()| stdout.flush())
+    .map_err(Error::Output)
+}
+
+// Writes each record as it's read, once the widths are settled.
+fn stream<R>(opt: &Opt, mut table: Table, records: R, widths: Widths) -> Result<(), Error>
+where
+    R: Iterator<Item = Result<Vec<String>, delimited::Error>>,
+{
+    per_column(opt, &mut table)?;
+    info!("streaming the table, with {:?}", widths);
+    // Stdout is line buffered, unlike a BufWriter, so each row shows up as soon as it's written
+    // rather than once the buffer fills.
+    let stdout = io::stdout();
+    let mut stream = Stream::new(table, widths, stdout.lock()).overflow(overflow(opt));
+    if let Some(width) = width(opt) {
+        stream = stream.fit(width);
+    }
+    for record in records {
+        stream.row(record?).map_err(Error::Output)?;
+    }
+    stream
+        .finish()
+        .and_then(|mut stdout| stdout.flush())
+        .map_err(Error::Output)
+}
@@ -0,0 +1,204 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::error::{IoError, Report};
+use learning_rust::log;
+use learning_rust::pool;
+use learning_rust::table::{Border, Table};
+use learning_rust::wc::{self, Columns, Counts};
+use learning_rust::{debug, span};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::thread;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "wc",
+    version,
+    about = "Count the lines, words and bytes in files, several at once, as GNU wc does."
+)]
+struct Opt {
+    /// Files to count, or - for stdin, which is counted when there are none
+    files: Vec<PathBuf>,
+
+    /// Count newlines
+    #[arg(short, long)]
+    lines: bool,
+
+    /// Count words
+    #[arg(short, long)]
+    words: bool,
+
+    /// Count characters
+    #[arg(short = 'm', long)]
+    chars: bool,
+
+    /// Count bytes
+    #[arg(short = 'c', long)]
+    bytes: bool,
+
+    /// Measure the widest line
+    #[arg(short = 'L', long = "max-line-length")]
+    max_line: bool,
+
+    /// How many files to count at once (default: one per CPU)
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Write a table with a header, drawn with this border, instead of as GNU wc does: none,
+    /// ascii, unicode or markdown
+    #[arg(long)]
+    border: Option<Border>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+impl Opt {
+    // Lines, words and bytes, unless asked for something else.
+    fn columns(&self) -> Columns {
+        let columns = Columns {
+            lines: self.lines,
+            words: self.words,
+            chars: self.chars,
+            bytes: self.bytes,
+            max_line: self.max_line,
+        };
+        if columns.is_empty() {
+            Columns::default()
+        } else {
+            columns
+        }
+    }
+
+    fn threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+// What's being counted: a file, or stdin.
+enum Input<'p> {
+    File(&'p Path),
+    Stdin,
+}
+
+impl Input<'_> {
+    fn count(&self) -> Result<Counts, IoError> {
+        match self {
+            Input::File(path) => File::open(path)
+                .and_then(|file| wc::count(BufReader::new(file)))
+                .map_err(IoError::context(path.display().to_string())),
+            Input::Stdin => wc::count(io::stdin().lock()).map_err(IoError::context("-")),
+        }
+    }
+
+    // How big it is, or None if it isn't a regular file. Those that can't be looked at will fail
+    // to be counted too, so aren't any help one way or the other.
+    fn size(&self) -> Option<Option<u64>> {
+        let path = match self {
+            Input::File(path) => path,
+            Input::Stdin => Path::new("/dev/stdin"),
+        };
+        let metadata = fs::metadata(path).ok()?;
+        Some(metadata.is_file().then_some(metadata.len()))
+    }
+}
+
+fn main() {
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+    let columns = opt.columns();
+    let inputs: Vec<Input<'_>> = if opt.files.is_empty() {
+        vec![Input::Stdin]
+    } else {
+        opt.files
+            .iter()
+            .map(|path| match path.to_str() {
+                Some("-") => Input::Stdin,
+                _ => Input::File(path),
+            })
+            .collect()
+    };
+    // No name is given for stdin unless it was asked for by name.
+    let name = |i: usize| opt.files.get(i).map(|path| path.display().to_string());
+
+    // As GNU wc does, a single count of a single file needs no room to line up with anything.
+    let width = if inputs.len() == 1 && columns.len() == 1 {
+        1
+    } else {
+        wc::number_width(inputs.iter().filter_map(Input::size))
+    };
+    debug!(
+        "counting {} files on {} threads, {} wide",
+        inputs.len(),
+        opt.threads(),
+        width
+    );
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut table = Table::new();
+    if let Some(border) = opt.border {
+        table.border(border);
+        let mut header = columns.names();
+        header.push("file");
+        table.header(header);
+    }
+    let mut total = Counts::default();
+    let mut failed = false;
+    let mut written = Ok(());
+    let span = span!(Info, "counting");
+    pool::map_ordered(&inputs, opt.threads(), Input::count, |i, result| {
+        let counts = match result {
+            Ok(counts) => counts,
+            Err(err) => {
+                // So the error comes after the lines before it, if they're going to the same place.
+                if written.is_ok() {
+                    written = out.flush();
+                }
+                eprintln!("error: {}", Report(&err));
+                failed = true;
+                return;
+            }
+        };
+        total += counts;
+        if opt.border.is_some() {
+            let mut row: Vec<String> = columns.values(&counts).iter().map(u64::to_string).collect();
+            row.push(name(i).unwrap_or_default());
+            table.row(row);
+        } else {
+            let line = wc::line(&columns, &counts, width, name(i).as_deref());
+            if written.is_ok() {
+                written = writeln!(out, "{}", line);
+            }
+        }
+    });
+    drop(span);
+
+    if inputs.len() > 1 {
+        if opt.border.is_some() {
+            let mut row: Vec<String> = columns.values(&total).iter().map(u64::to_string).collect();
+            row.push("total".to_string());
+            table.group().row(row);
+        } else {
+            let line = wc::line(&columns, &total, width, Some("total"));
+            written = written.and_then(|()| writeln!(out, "{}", line));
+        }
+    }
+    if opt.border.is_some() {
+        written = written.and_then(|()| write!(out, "{}", table));
+    }
+    match written.and_then(|()| out.flush()) {
+        // Whoever was reading, e.g. head, has all they wanted.
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("error: {}", Report(&IoError::new("writing to stdout", err)));
+            exit(1);
+        }
+        _ => {}
+    }
+    exit(if failed { 1 } else { 0 });
+}
@@ -0,0 +1,165 @@
+use clap::{ArgAction, Parser};
+use learning_rust::completions;
+use learning_rust::debug;
+use learning_rust::error::{IoError, Report};
+use learning_rust::execute::{self, parse_interval, tee, Job, Outputs, Strategy};
+use learning_rust::locked_write::shared::SharedLineWriter;
+use learning_rust::locked_write::Policy;
+use learning_rust::log;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::process::{exit, Command};
+use std::time::Duration;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "xargs-par",
+    version,
+    about = "Execute a command for each item on stdin, several at once."
+)]
+struct Opt {
+    /// Command to execute, with each item in place of the replace string, or after the last
+    /// argument if there isn't one
+    // OsString because files & arguments are not guaranteed to be utf8.
+    #[arg(required = true, trailing_var_arg = true)]
+    arg: Vec<OsString>,
+    /// What to replace with each item in the command
+    #[arg(short = 'I', long, default_value = "{}")]
+    replace: String,
+    /// Items are separated by NULs, as from find -print0, instead of being one to a line
+    #[arg(short = '0', long)]
+    null: bool,
+    #[arg(short, long = "max-concurrency", default_value = "8")]
+    concurrency: usize,
+    /// Longest to hold on to each command's output before writing it, in seconds, with the
+    /// locked strategy; 0 writes each line as soon as it's complete
+    #[arg(long, default_value = "0.1", value_parser = parse_interval)]
+    flush_interval: Duration,
+    /// How the commands' lines get written without mixing: locked, each command locking stdout
+    /// and stderr for itself; shared, the same but taking turns through a mutex; or channel,
+    /// sending them to a thread that does all the writing
+    #[arg(long, default_value = "locked")]
+    strategy: Strategy,
+    /// Write the commands' output to this file too, as it's written to stdout. It keeps going
+    /// even if whoever's reading stdout stops
+    #[arg(long)]
+    tee: Option<PathBuf>,
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+// The command for one item: the template with the item in place of each replace string, or
+// added on the end if it has none.
+fn command(template: &[OsString], replace: &[u8], item: &[u8]) -> Command {
+    let mut args: Vec<OsString> = template
+        .iter()
+        .map(|arg| substitute(arg, replace, item))
+        .collect();
+    if !template.iter().any(|arg| contains(arg.as_bytes(), replace)) {
+        args.push(OsString::from_vec(item.to_vec()));
+    }
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    command
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+fn substitute(arg: &OsStr, replace: &[u8], item: &[u8]) -> OsString {
+    let arg = arg.as_bytes();
+    if replace.is_empty() {
+        return OsString::from_vec(arg.to_vec());
+    }
+    let mut substituted = Vec::with_capacity(arg.len());
+    let mut rest = arg;
+    while !rest.is_empty() {
+        if rest.starts_with(replace) {
+            substituted.extend_from_slice(item);
+            rest = &rest[replace.len()..];
+        } else {
+            substituted.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    OsString::from_vec(substituted)
+}
+
+fn main() {
+    // Thank you clap.
+    let opt = completions::from_args::<Opt>();
+    log::init(opt.verbose);
+
+    debug!(
+        "running {} for each item, {} at a time, with the {:?} strategy",
+        opt.arg[0].to_string_lossy(),
+        opt.concurrency,
+        opt.strategy
+    );
+    let outputs = Outputs::new(opt.strategy, opt.flush_interval);
+    let transcript = match &opt.tee {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(SharedLineWriter::new(file)),
+            Err(err) => {
+                let err = IoError::new(format!("creating {}", path.display()), err);
+                eprintln!("error: {}", Report(&err));
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Items are read as they're needed, so the first commands can start before stdin ends.
+    // Anything, like a trailing newline, that leaves an item empty isn't one.
+    let separator = if opt.null { b'\0' } else { b'\n' };
+    let replace = opt.replace.as_bytes();
+    let stdin = io::stdin();
+    let mut unread = None;
+    let jobs = stdin
+        .lock()
+        .split(separator)
+        .map_while(|item| match item {
+            Ok(item) => Some(item),
+            Err(err) => {
+                unread = Some(IoError::new("reading stdin", err));
+                None
+            }
+        })
+        .filter(|item| !item.is_empty())
+        .map(|item| Job {
+            command: command(&opt.arg, replace, &item),
+            label: OsString::from_vec(item),
+        });
+    let results = execute::run_all(jobs, opt.concurrency, || {
+        let mut output = outputs.for_command();
+        if let Some(transcript) = &transcript {
+            output.0 = tee(output.0, transcript);
+        }
+        output
+    });
+
+    // Everything the commands wrote has to be out of the way before saying how they went.
+    // As with the commands themselves, nobody reading any more is no reason to complain.
+    let finished = Policy::default().check(outputs.finish());
+    if let Err(err) = finished.map_err(IoError::context("writing output")) {
+        eprintln!("error: {}", Report(&err));
+        exit(1);
+    }
+
+    let code = execute::process_results(results);
+    // The items there were have been run, but there may have been more.
+    if let Some(err) = unread {
+        eprintln!("error: {}", Report(&err));
+        exit(1);
+    }
+    exit(code);
+}
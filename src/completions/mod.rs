@@ -0,0 +1,86 @@
+use crate::man;
+use clap::{value_parser, Arg, ArgAction, Command, Parser};
+use clap_complete::Shell;
+use std::io::{self, Write};
+use std::process::exit;
+
+// Instead of O::parse, for a binary that can write its own shell completions with
+// --completions <shell>, or its man page with --man, e.g.
+//     primes --completions bash > /etc/bash_completion.d/primes
+//     primes --man > /usr/local/share/man/man1/primes.1
+// They're made from O's definition, so they know every option it has, along with these two.
+//
+// Both are looked for in a parse that lets anything else be wrong, since otherwise the arguments a
+// binary can't do without, like execute-in-dirs' directories, would have to be given too. It's
+// still clap's parse, so an argument that only looks like one of them, e.g. in xargs-par's
+// command, is left alone.
+pub fn from_args<O: Parser>() -> O {
+    if let Ok(matches) = with_extras(O::command())
+        .ignore_errors(true)
+        .try_get_matches()
+    {
+        if matches.get_one::<bool>("man") == Some(&true) {
+            man_page::<O>();
+        }
+        if let Some(&shell) = matches.get_one::<Shell>("completions") {
+            completions::<O>(shell);
+        }
+    }
+    let matches = with_extras(O::command())
+        .try_get_matches()
+        .unwrap_or_else(|err| exit_with(err));
+    O::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err.format(&mut O::command())))
+}
+
+// Global, so they can come after a subcommand too, e.g. headtail tail --man.
+fn with_extras(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("completions")
+                .long("completions")
+                .value_name("SHELL")
+                .value_parser(value_parser!(Shell))
+                .global(true)
+                .help("Write completions for this shell to stdout, then exit"),
+        )
+        .arg(
+            Arg::new("man")
+                .long("man")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Write a man page to stdout, then exit"),
+        )
+}
+
+// clap 4 exits 2 for a command line it can't parse, but here that's always been 1, leaving 2 for
+// a value that parsed but can't be used. --help and --version come this way too, and exit 0.
+fn exit_with(err: clap::Error) -> ! {
+    let _ = err.print();
+    exit(if err.use_stderr() { 1 } else { 0 });
+}
+
+fn completions<O: Parser>(shell: Shell) -> ! {
+    let mut command = with_extras(O::command());
+    // Made whole first, since clap_complete panics when it can't write.
+    let mut script = Vec::new();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    write_out(&script);
+}
+
+fn man_page<O: Parser>() -> ! {
+    let page = man::page(with_extras(O::command()));
+    write_out(page.as_bytes());
+}
+
+fn write_out(bytes: &[u8]) -> ! {
+    match io::stdout().lock().write_all(bytes) {
+        // Whoever was reading, e.g. head, has all they wanted.
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("error: writing to stdout: {}", err);
+            exit(1);
+        }
+        _ => {}
+    }
+    exit(0);
+}
@@ -0,0 +1,202 @@
+use crate::error::IoError;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+// How much a directory, and everything under it, takes up. depth is how far below the path it
+// was found under it is, that path itself being 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub size: u64,
+}
+
+// A directory found on the way, with only what's directly in it counted so far. Those under it
+// are always found after it, so come later.
+struct Dir {
+    path: PathBuf,
+    parent: Option<usize>,
+    depth: usize,
+    own: u64,
+}
+
+// What the walkers share: the directories found so far, those still to be read, and how many
+// are being read right now, since any of them might turn up more.
+struct State {
+    dirs: Vec<Dir>,
+    unread: Vec<usize>,
+    reading: usize,
+    // Files with more than one link are only counted the first time, as du does.
+    seen: HashSet<(u64, u64)>,
+    errors: Vec<IoError>,
+}
+
+// Walks each of roots on as many as threads at once, whichever thread is free reading the next
+// directory any of them has found, and says how much each directory takes up, in the order
+// they were found, along with whatever couldn't be read. A root that isn't a directory is there
+// with its own size. Sizes are how much is allocated on disk, or with apparent, how long the
+// files are. Links aren't followed, so nothing is counted twice.
+pub fn walk(roots: &[PathBuf], threads: usize, apparent: bool) -> (Vec<Usage>, Vec<IoError>) {
+    let mut state = State {
+        dirs: Vec::new(),
+        unread: Vec::new(),
+        reading: 0,
+        seen: HashSet::new(),
+        errors: Vec::new(),
+    };
+    for root in roots {
+        match fs::symlink_metadata(root) {
+            Ok(metadata) => {
+                if metadata.is_dir() {
+                    state.unread.push(state.dirs.len());
+                }
+                let own = size(&metadata, apparent, &mut state.seen);
+                state.dirs.push(Dir {
+                    path: root.clone(),
+                    parent: None,
+                    depth: 0,
+                    own,
+                });
+            }
+            Err(err) => state
+                .errors
+                .push(IoError::new(root.display().to_string(), err)),
+        }
+    }
+
+    let state = Mutex::new(state);
+    let more = Condvar::new();
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| walker(&state, &more, apparent));
+        }
+    });
+
+    let state = state.into_inner().expect("a walker panicked");
+    let mut totals: Vec<u64> = state.dirs.iter().map(|dir| dir.own).collect();
+    for (i, dir) in state.dirs.iter().enumerate().rev() {
+        if let Some(parent) = dir.parent {
+            totals[parent] += totals[i];
+        }
+    }
+    let usages = state
+        .dirs
+        .into_iter()
+        .zip(totals)
+        .map(|(dir, size)| Usage {
+            path: dir.path,
+            depth: dir.depth,
+            size,
+        })
+        .collect();
+    (usages, state.errors)
+}
+
+// Reads directories until there are none left, nor any being read that could turn up more.
+fn walker(state: &Mutex<State>, more: &Condvar, apparent: bool) {
+    let mut shared = state.lock().expect("a walker panicked");
+    loop {
+        let i = match shared.unread.pop() {
+            Some(i) => i,
+            None if shared.reading == 0 => {
+                // Wake the others so they see it too.
+                more.notify_all();
+                return;
+            }
+            None => {
+                shared = more.wait(shared).expect("a walker panicked");
+                continue;
+            }
+        };
+        shared.reading += 1;
+        let (path, depth) = (shared.dirs[i].path.clone(), shared.dirs[i].depth);
+        drop(shared);
+
+        let entries = read(&path);
+
+        shared = state.lock().expect("a walker panicked");
+        shared.reading -= 1;
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(err) => {
+                shared.errors.push(err);
+                more.notify_all();
+                continue;
+            }
+        };
+        for (path, metadata) in entries {
+            let own = size(&metadata, apparent, &mut shared.seen);
+            if metadata.is_dir() {
+                let child = shared.dirs.len();
+                shared.dirs.push(Dir {
+                    path,
+                    parent: Some(i),
+                    depth: depth + 1,
+                    own,
+                });
+                shared.unread.push(child);
+            } else {
+                shared.dirs[i].own += own;
+            }
+        }
+        more.notify_all();
+    }
+}
+
+fn read(dir: &Path) -> Result<Vec<(PathBuf, Metadata)>, IoError> {
+    let context = || format!("reading {}", dir.display());
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|err| IoError::new(context(), err))? {
+        let entry = entry.map_err(|err| IoError::new(context(), err))?;
+        let metadata = entry
+            .metadata()
+            .map_err(|err| IoError::new(context(), err))?;
+        entries.push((entry.path(), metadata));
+    }
+    Ok(entries)
+}
+
+fn size(metadata: &Metadata, apparent: bool, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    if metadata.nlink() > 1 && !metadata.is_dir() && !seen.insert((metadata.dev(), metadata.ino()))
+    {
+        return 0;
+    }
+    if apparent {
+        metadata.len()
+    } else {
+        // st_blocks is always in 512 byte units, whatever the filesystem's block size.
+        metadata.blocks() * 512
+    }
+}
+
+// A size as du -h writes it: in bytes below 1K, else in the largest unit it's at least one of,
+// to one decimal place below 10, and always rounded up, so nothing looks smaller than it is.
+pub fn human(size: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64;
+    let mut unit = 0;
+    value /= 1024.0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        let tenths = (value * 10.0).ceil();
+        // Rounding up can take it to 10, which is written without the decimal.
+        if tenths < 100.0 {
+            return format!("{:.1}{}", tenths / 10.0, UNITS[unit]);
+        }
+    }
+    let whole = value.ceil();
+    if whole >= 1024.0 && unit + 1 < UNITS.len() {
+        return format!("1.0{}", UNITS[unit + 1]);
+    }
+    format!("{}{}", whole, UNITS[unit])
+}
@@ -0,0 +1,52 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use thiserror::Error;
+
+// An io::Error along with what we were doing when it happened, e.g. "creating out.txt", which the
+// io::Error itself can't say. The io::Error is its source rather than part of its message, so
+// it's there for whoever wants it, and Report shows both.
+#[derive(Debug, Error)]
+#[error("{context}")]
+pub struct IoError {
+    context: String,
+    #[source]
+    source: io::Error,
+}
+
+impl IoError {
+    pub fn new<C: Into<String>>(context: C, source: io::Error) -> Self {
+        IoError {
+            context: context.into(),
+            source,
+        }
+    }
+
+    // For use with map_err, to say what we were doing when an io::Error happened, e.g.
+    //     File::create(path).map_err(IoError::context(format!("creating {}", path.display())))?
+    pub fn context<C: Into<String>>(context: C) -> impl FnOnce(io::Error) -> IoError {
+        move |source| IoError::new(context, source)
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
+    }
+}
+
+// An error's message followed by those of everything that led to it, as in
+//     writing to stdout: Broken pipe (os error 32)
+// It's how the binaries print what went wrong, so each error only has to say what it knows
+// itself, and leave its source to say the rest.
+pub struct Report<'e>(pub &'e (dyn StdError + 'static));
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(err) = source {
+            write!(f, ": {}", err)?;
+            source = err.source();
+        }
+        Ok(())
+    }
+}
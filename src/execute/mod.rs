@@ -0,0 +1,443 @@
+use crate::error::{IoError, Report};
+use crate::locked_write::buffered::LineBuffer;
+use crate::locked_write::channel::{self, LineSender};
+use crate::locked_write::prefixed::PrefixedWriter;
+use crate::locked_write::shared::SharedLineWriter;
+use crate::locked_write::tee::TeeWriter;
+use crate::locked_write::{Flow, LockWrite, OnError, Policy};
+use crate::{debug, info, span, trace};
+use os_pipe::{pipe, PipeReader};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Stderr, Stdout, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::ExitStatusExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Runs many commands at once, with a line of their output never mixed up with anyone else's,
+// and each line prefixed with a label saying whose it is, like a directory for execute-in-dirs or
+// an item for xargs-par.
+
+// How the commands' lines get written without mixing.
+#[derive(Debug, Clone, Copy)]
+pub enum Strategy {
+    // Each command locking stdout and stderr for itself.
+    Locked,
+    // The same, but taking turns through a mutex.
+    Shared,
+    // Sending them to a thread that does all the writing.
+    Channel,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "locked" => Ok(Strategy::Locked),
+            "shared" => Ok(Strategy::Shared),
+            "channel" => Ok(Strategy::Channel),
+            _ => Err(format!(
+                "unknown strategy {}, expected locked, shared or channel",
+                s
+            )),
+        }
+    }
+}
+
+// For --flush-interval, in seconds.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("{} isn't a length of time", s))
+}
+
+// Where a command writes its output & errors to.
+pub type Out = Box<dyn Write + Send>;
+
+// Sends a command's output to the transcript as well as wherever it was going.
+pub fn tee(stdout: Out, transcript: &SharedLineWriter<File>) -> Out {
+    let strict = Policy {
+        broken_pipe: OnError::Propagate,
+        other: OnError::Propagate,
+    };
+    Box::new(
+        TeeWriter::new()
+            .sink(stdout, Policy::default())
+            .sink(Box::new(transcript.clone()), strict),
+    )
+}
+
+// What every command's output goes through on its way to stdout & stderr, by strategy.
+pub struct Outputs {
+    kind: Kind,
+}
+
+enum Kind {
+    Locked(Duration),
+    Shared(SharedLineWriter<Stdout>, SharedLineWriter<Stderr>),
+    Channel {
+        stdout: LineSender,
+        stderr: LineSender,
+        writers: (
+            JoinHandle<io::Result<Stdout>>,
+            JoinHandle<io::Result<Stderr>>,
+        ),
+    },
+}
+
+impl Outputs {
+    // interval is how long the locked strategy holds on to output before writing it.
+    pub fn new(strategy: Strategy, interval: Duration) -> Self {
+        let kind = match strategy {
+            Strategy::Locked => Kind::Locked(interval),
+            Strategy::Shared => Kind::Shared(
+                SharedLineWriter::new(io::stdout()),
+                SharedLineWriter::new(io::stderr()),
+            ),
+            Strategy::Channel => {
+                let (stdout, out_writer) = channel::channel(io::stdout());
+                let (stderr, err_writer) = channel::channel(io::stderr());
+                Kind::Channel {
+                    stdout,
+                    stderr,
+                    writers: (out_writer, err_writer),
+                }
+            }
+        };
+        Outputs { kind }
+    }
+
+    // Writers of a command's own for its output & errors.
+    pub fn for_command(&self) -> (Out, Out) {
+        match &self.kind {
+            Kind::Locked(interval) => (
+                Box::new(LineBuffer::new(io::stdout()).interval(*interval)),
+                Box::new(LineBuffer::new(io::stderr()).interval(*interval)),
+            ),
+            Kind::Shared(stdout, stderr) => (Box::new(stdout.clone()), Box::new(stderr.clone())),
+            Kind::Channel { stdout, stderr, .. } => {
+                (Box::new(stdout.clone()), Box::new(stderr.clone()))
+            }
+        }
+    }
+
+    // Waits for everything to be written, for the strategies that don't write it themselves.
+    pub fn finish(self) -> io::Result<()> {
+        if let Kind::Channel {
+            stdout,
+            stderr,
+            writers: (out_writer, err_writer),
+        } = self.kind
+        {
+            // The writers finish once there's nothing left to send them anything.
+            drop((stdout, stderr));
+            out_writer.join().expect("stdout writer paniced")?;
+            err_writer.join().expect("stderr writer paniced")?;
+        }
+        Ok(())
+    }
+}
+
+// A command to run, with the label to put before each line of its output.
+pub struct Job {
+    pub label: OsString,
+    pub command: Command,
+}
+
+pub enum ProcessExitResult {
+    IOError(IoError),
+    Code(i32),
+    Signal(i32),
+    Panic,
+}
+
+pub struct ProcessResult {
+    pub label: OsString,
+    pub exit: ProcessExitResult,
+}
+
+impl From<Result<ExitStatus, io::Error>> for ProcessExitResult {
+    fn from(result: Result<ExitStatus, io::Error>) -> Self {
+        match result {
+            Err(err) => ProcessExitResult::IOError(IoError::new("waiting for the command", err)),
+            Ok(estatus) => {
+                if let Some(code) = estatus.code() {
+                    ProcessExitResult::Code(code)
+                } else {
+                    // We expect here because we know that the command executed (no Err), that it
+                    // finished (or there would be no ExitStatus) and that it didn't exit with an
+                    // exit code (estatus.code() -> None). While the compiler can't prove it,
+                    // there's no other possibility.
+                    ProcessExitResult::Signal(
+                        estatus
+                            .signal()
+                            .expect("process exited with no exit code or signal!"),
+                    )
+                }
+            }
+        }
+    }
+}
+
+// Runs every job, at most concurrency of them at once, each writing through the writers output
+// hands it, and says how each went, in the order they came in. Jobs are only started once
+// there's room for them, so they can keep coming, e.g. from stdin, while the first ones run.
+pub fn run_all<I, F>(jobs: I, concurrency: usize, mut output: F) -> Vec<ProcessResult>
+where
+    I: IntoIterator<Item = Job>,
+    F: FnMut() -> (Out, Out),
+{
+    // A fixed pool of workers takes the jobs in turn, so however many jobs there are, there are
+    // never more than concurrency threads. The queue has no room in it, so a job is only handed
+    // over once a worker is free to start it.
+    let (queue, queued) = mpsc::sync_channel::<(usize, Job, (Out, Out))>(0);
+    let queued = Arc::new(Mutex::new(queued));
+    let (finished, results) = mpsc::channel();
+    let workers: Vec<JoinHandle<()>> = (0..concurrency.max(1))
+        .map(|_| {
+            let queued = Arc::clone(&queued);
+            let finished = finished.clone();
+            thread::spawn(move || loop {
+                // The lock's only held while waiting for a job, not while running it.
+                let next = queued.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                let (i, job, output) = match next {
+                    Ok(next) => next,
+                    Err(_) => break,
+                };
+                let label = job.label.clone();
+                // A command that panics still has a result, and leaves its worker to carry on.
+                let exit = panic::catch_unwind(AssertUnwindSafe(|| execute_command(job, output)))
+                    .unwrap_or(ProcessExitResult::Panic);
+                let _ = finished.send((i, ProcessResult { label, exit }));
+            })
+        })
+        .collect();
+    drop(finished);
+
+    // Each result is kept as its command finishes, in the place its job came in.
+    let mut done: Vec<Option<ProcessResult>> = Vec::new();
+    let mut keep = |(i, result): (usize, ProcessResult)| {
+        if done.len() <= i {
+            done.resize_with(i + 1, || None);
+        }
+        done[i] = Some(result);
+    };
+    for (i, job) in jobs.into_iter().enumerate() {
+        trace!("waiting for a turn for {}", label(&job.label));
+        let output = output();
+        // The workers only stop once the queue is closed, below.
+        let _ = queue.send((i, job, output));
+        results.try_iter().for_each(&mut keep);
+    }
+    drop(queue);
+    results.iter().for_each(&mut keep);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    done.into_iter().flatten().collect()
+}
+
+fn label(label: &OsStr) -> String {
+    Path::new(label).display().to_string()
+}
+
+fn execute_command(mut job: Job, (stdout, stderr): (Out, Out)) -> ProcessExitResult {
+    let label = label(&job.label);
+    let program = Path::new(job.command.get_program()).display().to_string();
+    let _span = span!(Debug, "running {} for {}", program, label);
+
+    // Setup our pipes for the command
+    let (o_reader, o_writer) = match pipe() {
+        Ok((o_reader, o_writer)) => (o_reader, o_writer),
+        // Couldn't create our pipes. I suspect a ulimit issue, but there's nothing we can do but
+        // note the failure and return.
+        Err(err) => return ProcessExitResult::IOError(IoError::new("creating a pipe", err)),
+    };
+    let (e_reader, e_writer) = match pipe() {
+        Ok((e_reader, e_writer)) => (e_reader, e_writer),
+        // Couldn't create our pipes. I suspect a ulimit issue, but there's nothing we can do but
+        // note the failure and return.
+        Err(err) => return ProcessExitResult::IOError(IoError::new("creating a pipe", err)),
+    };
+
+    // Spawn our command
+    let child = job.command.stdout(o_writer).stderr(e_writer).spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        // The child couldn't spawn, nothing left to do but note the failure and return.
+        Err(err) => {
+            let context = format!("running {}", program);
+            return ProcessExitResult::IOError(IoError::new(context, err));
+        }
+    };
+    // The command holds on to the write side of our pipes too, and they need to be closed for
+    // our io threads to get an EOF.
+    drop(job.command);
+    debug!("started {} for {} as pid {}", program, label, child.id());
+
+    // We're spawning threads to process stdout/stderr from our commands. Track them to join.
+    let io_threads = vec![
+        spawn_stream(stdout, o_reader, job.label.clone()),
+        spawn_stream(stderr, e_reader, job.label),
+    ];
+
+    // Wait for the child to finish
+    let mut result: ProcessExitResult = child.wait().into();
+
+    // Drop the child since it owns the write side of our pipes, and it needs to be dropped to
+    // close them so our io threads can get an EOF. This is what the docs say to do so I'm
+    // including it to be complete, but in practice, I've still never seen the EOF happen.
+    drop(child);
+
+    match &result {
+        ProcessExitResult::Code(code) => info!("{} for {} exited {}", program, label, code),
+        ProcessExitResult::Signal(signal) => {
+            info!("{} for {} signaled {}", program, label, signal)
+        }
+        _ => {}
+    }
+
+    // Join our io threads so that we block until all of our commands output has been handled.
+    // Output that couldn't be written is lost, which matters more than how the command went.
+    for thread in io_threads {
+        if let Err(err) = thread.join().expect("io thread paniced") {
+            result = ProcessExitResult::IOError(IoError::new("writing its output", err));
+        }
+    }
+    result
+}
+
+// Copies the command's output to target a line at a time. Once writing stops, whether because
+// whoever's reading us has gone or because of an error, the rest of the output is still read,
+// and thrown away, so the command isn't left stuck writing to a pipe that's filled up.
+// Output is written a batch of lines at a time, to save a syscall per line, but never left waiting
+// while we wait for more.
+fn stream_output(out: Out, reader: PipeReader, prefix: &OsStr) -> io::Result<()> {
+    let policy = Policy::default();
+    let prefix = [trim_end(prefix.as_bytes(), b'/'), b": "].concat();
+    let mut out = PrefixedWriter::new(out, prefix);
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut writing = true;
+    let mut failed = None;
+    loop {
+        let result = reader.read_until(b'\n', &mut buf);
+        match result {
+            // If we got 0 bytes or an error, we're done. Return.
+            Err(_) | Ok(0) => {
+                return failed.map_or(Ok(()), Err);
+            }
+            Ok(_) => {
+                if writing {
+                    let mut result = out.write_all(&buf);
+                    // Having read everything there was, the next read could be a long wait.
+                    if reader.buffer().is_empty() {
+                        result = result.and_then(|()| out.flush());
+                    }
+                    match policy.check(result) {
+                        Ok(Flow::Continue) => {}
+                        Ok(Flow::Stop) => writing = false,
+                        Err(err) => {
+                            writing = false;
+                            failed = Some(err);
+                        }
+                    }
+                }
+                buf.clear();
+            }
+        }
+    }
+}
+
+fn trim_end(s: &[u8], v: u8) -> &[u8] {
+    let end_idx = s.len() - s.iter().rev().take_while(|&&x| x == v).count();
+    &s[..end_idx]
+}
+
+// Locks target once for the whole line, so that lines from different commands don't get mixed
+// up with each other.
+fn write_with_prefix<T: LockWrite>(target: &T, prefix: &OsStr, message: &[u8]) -> io::Result<()> {
+    let prefix = [trim_end(prefix.as_bytes(), b'/'), b": "].concat();
+    PrefixedWriter::new(target.lock_write(), prefix).write_all(message)
+}
+
+fn spawn_stream(out: Out, reader: PipeReader, label: OsString) -> JoinHandle<io::Result<()>> {
+    thread::spawn(move || stream_output(out, reader, &label))
+}
+
+// Says on stderr how each command that didn't succeed went, returning the exit code for them
+// all: 1 if any of them failed, else 0.
+pub fn process_results(results: Vec<ProcessResult>) -> i32 {
+    report_results(results, &io::stderr())
+}
+
+fn report_results<T: LockWrite>(results: Vec<ProcessResult>, target: &T) -> i32 {
+    let mut e_code = 0;
+    // If stderr can't be written to, there's nowhere left to say what went wrong, so the exit
+    // code will have to say it instead.
+    let report = |prefix: &OsStr, message: &[u8]| {
+        let _ = write_with_prefix(target, prefix, message);
+    };
+    for result in results {
+        // Handle the results.
+        match result.exit {
+            ProcessExitResult::Code(0) => {}
+            ProcessExitResult::Code(code) => {
+                report(&result.label, format!("exited {:}\n", code).as_bytes());
+                e_code = 1;
+            }
+            ProcessExitResult::Signal(signal) => {
+                report(&result.label, format!("signaled {:}\n", signal).as_bytes());
+                e_code = 1;
+            }
+            ProcessExitResult::IOError(err) => {
+                report(&result.label, format!("{}\n", Report(&err)).as_bytes());
+                e_code = 1;
+            }
+            ProcessExitResult::Panic => {
+                report(&result.label, b"panicked\n");
+                e_code = 1;
+            }
+        };
+    }
+    e_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn result(label: &str, exit: ProcessExitResult) -> ProcessResult {
+        ProcessResult {
+            label: label.into(),
+            exit,
+        }
+    }
+
+    #[test]
+    fn a_panic_is_a_failure() {
+        let stderr = Mutex::new(Vec::new());
+        let results = vec![
+            result("a", ProcessExitResult::Panic),
+            result("b", ProcessExitResult::Code(3)),
+        ];
+        assert_eq!(report_results(results, &stderr), 1);
+        assert_eq!(stderr.into_inner().unwrap(), b"a: panicked\nb: exited 3\n");
+    }
+
+    #[test]
+    fn success() {
+        let stderr = Mutex::new(Vec::new());
+        let results = vec![result("a", ProcessExitResult::Code(0))];
+        assert_eq!(report_results(results, &stderr), 0);
+        assert!(stderr.into_inner().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,732 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+// What a matching rule does to the words from the rules before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    // Adds its word on the end.
+    #[default]
+    Combine,
+    // Replaces them with its word.
+    Override,
+}
+
+// How the words of several matching rules come together: concat joins them, letting a rule in
+// override mode replace those before it; override keeps only the last rule's; and priority only
+// the first's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Combination {
+    #[default]
+    Concat,
+    Override,
+    Priority,
+}
+
+impl FromStr for Combination {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "concat" => Ok(Combination::Concat),
+            "override" => Ok(Combination::Override),
+            "priority" => Ok(Combination::Priority),
+            _ => Err(format!(
+                "unknown combination {}, expected concat, override or priority",
+                s
+            )),
+        }
+    }
+}
+
+// A divisor and the word it contributes to the label of each of its multiples.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Rule {
+    pub divisor: u64,
+    pub word: String,
+    #[serde(default)]
+    pub mode: Mode,
+}
+
+impl Rule {
+    pub fn new(divisor: u64, word: &str, mode: Mode) -> Result<Self, String> {
+        Rule {
+            divisor,
+            word: word.to_string(),
+            mode,
+        }
+        .validate()
+    }
+
+    // Every number is a multiple of nothing, and nothing is a multiple of zero.
+    fn validate(self) -> Result<Self, String> {
+        if self.divisor == 0 {
+            return Err("the divisor must not be 0".to_string());
+        }
+        if self.word.is_empty() {
+            return Err("the word must not be empty".to_string());
+        }
+        Ok(self)
+    }
+}
+
+// <divisor>=<word>, e.g. 7=bazz, which combines.
+impl FromStr for Rule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (divisor, word) = s
+            .split_once('=')
+            .ok_or_else(|| "expected <divisor>=<word>".to_string())?;
+        let divisor = divisor.parse().map_err(|err| format!("{}", err))?;
+        Rule::new(divisor, word, Mode::Combine)
+    }
+}
+
+// The layout of a rules file, an ordered list of rules like
+//
+//     [[rule]]
+//     divisor = 3
+//     word = "fizz"
+//
+//     [[rule]]
+//     divisor = 15
+//     word = "FizzBuzz!"
+//     mode = "override"
+//
+// where mode is combine unless it says otherwise.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+// Dividing a u128 takes a good deal longer than a u64, so it's only done for numbers that need it.
+fn divides(divisor: u64, n: u128) -> bool {
+    match u64::try_from(n) {
+        Ok(n) => n.is_multiple_of(divisor),
+        Err(_) => n.is_multiple_of(u128::from(divisor)),
+    }
+}
+
+// Labels each number by applying every rule it matches, in order. A number no rule matches has
+// no label, and is written as itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    combination: Combination,
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Rules {
+            rules,
+            combination: Combination::default(),
+        }
+    }
+
+    pub fn combination(mut self, combination: Combination) -> Self {
+        self.combination = combination;
+        self
+    }
+
+    // Classic fizzbuzz, as rules.
+    pub fn classic() -> Self {
+        let rule = |divisor, word: &str, mode| Rule {
+            divisor,
+            word: word.to_string(),
+            mode,
+        };
+        Rules::new(vec![
+            rule(3, "fizz", Mode::Combine),
+            rule(5, "buzz", Mode::Combine),
+            rule(15, "FizzBuzz!", Mode::Override),
+        ])
+    }
+
+    // The same rules, saying their words in another vocabulary. It's done once here rather than
+    // on every label, so it costs nothing per line.
+    pub fn translate(mut self, vocabulary: &Vocabulary) -> Self {
+        for rule in &mut self.rules {
+            rule.word = vocabulary.translate(&rule.word).to_string();
+        }
+        self
+    }
+
+    // The rules in a rules file's contents, in the order they're listed.
+    pub fn parse_toml(contents: &str) -> Result<Vec<Rule>, String> {
+        let file: RulesFile = toml::from_str(contents).map_err(|err| err.message().to_string())?;
+        file.rule.into_iter().map(Rule::validate).collect()
+    }
+
+    pub fn label(&self, n: u128) -> Option<String> {
+        let mut matching = self.rules.iter().filter(|rule| divides(rule.divisor, n));
+        match self.combination {
+            Combination::Override => matching.next_back().map(|rule| rule.word.clone()),
+            Combination::Priority => matching.next().map(|rule| rule.word.clone()),
+            Combination::Concat => {
+                let mut label: Option<String> = None;
+                for rule in matching {
+                    match (rule.mode, label.as_mut()) {
+                        (Mode::Combine, Some(label)) => label.push_str(&rule.word),
+                        _ => label = Some(rule.word.clone()),
+                    }
+                }
+                label
+            }
+        }
+    }
+}
+
+// The translations --lang knows without a file, by language code. English is the words as they
+// are.
+const LANGUAGES: &[(&str, &[(&str, &str)])] = &[
+    ("en", &[]),
+    (
+        "de",
+        &[
+            ("fizz", "zisch"),
+            ("buzz", "summ"),
+            ("FizzBuzz!", "ZischSumm!"),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("fizz", "pétille"),
+            ("buzz", "bourdonne"),
+            ("FizzBuzz!", "PétilleBourdonne!"),
+        ],
+    ),
+];
+
+// Words to say in place of the rules' own. Any word it doesn't know is said as it is, so a
+// vocabulary for the classic words still works alongside rules of our own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    words: HashMap<String, String>,
+}
+
+impl Vocabulary {
+    // One of the built in languages.
+    pub fn language(code: &str) -> Result<Self, String> {
+        let (_, words) = LANGUAGES
+            .iter()
+            .find(|(known, _)| *known == code)
+            .ok_or_else(|| {
+                let known: Vec<&str> = LANGUAGES.iter().map(|(code, _)| *code).collect();
+                format!(
+                    "unknown language {}, expected one of {}",
+                    code,
+                    known.join(", ")
+                )
+            })?;
+        let words = words
+            .iter()
+            .map(|(word, translation)| (word.to_string(), translation.to_string()))
+            .collect();
+        Ok(Vocabulary { words })
+    }
+
+    // A words file's contents, a table of each word and what to say instead, like
+    //
+    //     fizz = "zisch"
+    //     buzz = "summ"
+    pub fn parse_toml(contents: &str) -> Result<Self, String> {
+        let words: HashMap<String, String> =
+            toml::from_str(contents).map_err(|err| err.message().to_string())?;
+        if let Some((word, _)) = words.iter().find(|(_, translation)| translation.is_empty()) {
+            return Err(format!("the translation of {} must not be empty", word));
+        }
+        Ok(Vocabulary { words })
+    }
+
+    pub fn translate<'a>(&'a self, word: &'a str) -> &'a str {
+        self.words.get(word).map_or(word, String::as_str)
+    }
+}
+
+// The numbers from start to end by step, where a negative step counts down. Counting up stops
+// after end, and counting down stops before it. Either way it stops rather than wrap if the next
+// step would go past the ends of a u128. Without an end, it never stops, and wraps around instead.
+#[derive(Debug, Clone)]
+pub struct Range {
+    next: Option<u128>,
+    end: Option<u128>,
+    step: i128,
+}
+
+impl Range {
+    // None for a step of zero, which would never get anywhere.
+    pub fn new(start: u128, end: u128, step: i128) -> Option<Self> {
+        Range::from(start, Some(end), step)
+    }
+
+    // The numbers from start by step, forever.
+    pub fn forever(start: u128, step: i128) -> Option<Self> {
+        Range::from(start, None, step)
+    }
+
+    fn from(start: u128, end: Option<u128>, step: i128) -> Option<Self> {
+        if step == 0 {
+            return None;
+        }
+        Some(Range {
+            next: Some(start),
+            end,
+            step,
+        })
+    }
+}
+
+impl Iterator for Range {
+    type Item = u128;
+    fn next(&mut self) -> Option<u128> {
+        let step = self.step;
+        let n = self.next?;
+        self.next = match self.end {
+            Some(end) if step > 0 && n > end => return None,
+            Some(end) if step < 0 && n < end => return None,
+            Some(_) => n.checked_add_signed(step),
+            None => Some(n.wrapping_add_signed(step)),
+        };
+        Some(n)
+    }
+}
+
+// One line of fizzbuzz: a number no rule matched, or the words of the ones that did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Number(u128),
+    Words(u128, String),
+}
+
+impl Line {
+    pub fn number(&self) -> u128 {
+        match self {
+            Line::Number(n) | Line::Words(n, _) => *n,
+        }
+    }
+
+    // The words, unless no rule matched.
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Line::Number(_) => None,
+            Line::Words(_, words) => Some(words),
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Number(n) => write!(f, "{}", n),
+            Line::Words(_, words) => write!(f, "{}", words),
+        }
+    }
+}
+
+// Which lines to keep: those of plain numbers, or those saying certain words. Words are compared
+// ignoring case and anything that isn't a letter or digit, so fizzbuzz matches FizzBuzz!, and
+// they work just as well for rules of our own or words in another language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Only {
+    Number,
+    Words(String),
+}
+
+fn normalize(words: &str) -> String {
+    words
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl FromStr for Only {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "" => Err("expected number or the words of the lines to keep".to_string()),
+            "number" => Ok(Only::Number),
+            words => Ok(Only::Words(words.to_string())),
+        }
+    }
+}
+
+impl Only {
+    pub fn keeps(&self, line: &Line) -> bool {
+        match (self, line.label()) {
+            (Only::Number, label) => label.is_none(),
+            (Only::Words(_), None) => false,
+            (Only::Words(words), Some(label)) => normalize(label) == *words,
+        }
+    }
+}
+
+// How often each rule matched over a range, rather than what it said each time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub numbers: u64,
+    // Each rule, with how many numbers it matched, in the rules' order.
+    pub matched: Vec<(Rule, u64)>,
+    // How many numbers matched more than one rule.
+    pub multiple: u64,
+    // How many matched none, and were written as themselves.
+    pub plain: u64,
+}
+
+impl Summary {
+    // Counts matches without building any labels, so it's as quick as fizzbuzz gets.
+    pub fn collect(rules: &Rules, range: Range) -> Self {
+        let mut summary = Summary {
+            numbers: 0,
+            matched: rules.rules.iter().map(|rule| (rule.clone(), 0)).collect(),
+            multiple: 0,
+            plain: 0,
+        };
+        for n in range {
+            summary.numbers += 1;
+            let mut matches = 0;
+            for (rule, count) in &mut summary.matched {
+                if divides(rule.divisor, n) {
+                    *count += 1;
+                    matches += 1;
+                }
+            }
+            match matches {
+                0 => summary.plain += 1,
+                1 => {}
+                _ => summary.multiple += 1,
+            }
+        }
+        summary
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "numbers: {}", self.numbers)?;
+        writeln!(f, "matched:")?;
+        for (rule, count) in &self.matched {
+            writeln!(f, "  {}={}: {}", rule.divisor, rule.word, count)?;
+        }
+        writeln!(f, "multiple rules: {}", self.multiple)?;
+        write!(f, "plain: {}", self.plain)
+    }
+}
+
+// A line shaped by placeholders: {n} or {number} for the number, and {label} for the words, or
+// nothing if there aren't any. {{ and }} are literal braces, and \t, \n and \\ are a tab, a
+// newline and a backslash, since they're awkward to type on a command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Number,
+    Label,
+}
+
+impl FromStr for Template {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(c) => return Err(format!("unknown escape \\{}", c)),
+                    None => return Err("a trailing \\ escapes nothing".to_string()),
+                },
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err("unclosed {".to_string()),
+                        }
+                    }
+                    let piece = match name.as_str() {
+                        "n" | "number" => Piece::Number,
+                        "label" => Piece::Label,
+                        _ => {
+                            return Err(format!(
+                                "unknown placeholder {{{}}}, expected {{n}} or {{label}}",
+                                name
+                            ))
+                        }
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(piece);
+                }
+                '}' => return Err("unmatched }, use }} for a literal one".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template { pieces })
+    }
+}
+
+// How to write each line: text, as the number or the words; json, as one object per line with
+// the number and its label, null if it has none; csv, as number,label rows under a header; or
+// through a template of our own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Template(Template),
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {}, expected text, json or csv", s)),
+        }
+    }
+}
+
+impl Format {
+    // What comes before the first line, if anything.
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            Format::Csv => Some("number,label"),
+            Format::Text | Format::Json | Format::Template(_) => None,
+        }
+    }
+
+    // A line as it should be written in this format, without the newline, so the same thing can
+    // be written to a file or formatted into a String.
+    pub fn record<'a>(&'a self, line: &'a Line) -> Record<'a> {
+        Record { format: self, line }
+    }
+}
+
+pub struct Record<'a> {
+    format: &'a Format,
+    line: &'a Line,
+}
+
+impl fmt::Display for Record<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.line.number();
+        match (self.format, self.line.label()) {
+            (Format::Text, _) => write!(f, "{}", self.line),
+            (Format::Json, None) => write!(f, r#"{{"number":{},"label":null}}"#, n),
+            (Format::Json, Some(label)) => {
+                write!(f, r#"{{"number":{},"label":""#, n)?;
+                for c in label.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                        c => f.write_char(c)?,
+                    }
+                }
+                f.write_str("\"}")
+            }
+            (Format::Csv, None) => write!(f, "{},", n),
+            // Quoted only if it has to be, with any quotes in it doubled.
+            (Format::Csv, Some(label)) if label.contains(&[',', '"', '\n', '\r'][..]) => {
+                write!(f, "{},\"{}\"", n, label.replace('"', "\"\""))
+            }
+            (Format::Csv, Some(label)) => write!(f, "{},{}", n, label),
+            (Format::Template(template), label) => {
+                for piece in &template.pieces {
+                    match piece {
+                        Piece::Literal(literal) => f.write_str(literal)?,
+                        Piece::Number => write!(f, "{}", n)?,
+                        Piece::Label => f.write_str(label.unwrap_or(""))?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Plays fizzbuzz over a range, a line at a time.
+pub struct FizzBuzz {
+    rules: Rules,
+    range: Range,
+}
+
+impl FizzBuzz {
+    pub fn new(rules: Rules, range: Range) -> Self {
+        FizzBuzz { rules, range }
+    }
+}
+
+impl Iterator for FizzBuzz {
+    type Item = Line;
+    fn next(&mut self) -> Option<Line> {
+        let n = self.range.next()?;
+        Some(match self.rules.label(n) {
+            Some(words) => Line::Words(n, words),
+            None => Line::Number(n),
+        })
+    }
+}
+
+// How many numbers each parallel job formats. Big enough that sending them around costs little
+// next to formatting them.
+const CHUNK: usize = 1 << 14;
+
+// How many jobs each worker may have outstanding, so workers can stay busy while we write
+// without the whole range piling up in memory.
+const DEPTH: usize = 2;
+
+// Writes what FizzBuzz would produce, in the given format but without its header, formatted on
+// several threads at once. The range is cut into chunks, dealt out to the workers in turn, and
+// collected from them in the same turn, which is all it takes to write them in order: chunk i
+// always comes from worker i % threads.
+pub fn write_parallel<W: Write>(
+    rules: &Rules,
+    range: Range,
+    format: &Format,
+    threads: usize,
+    out: &mut W,
+) -> io::Result<()> {
+    let threads = threads.max(1);
+    thread::scope(|scope| {
+        let workers: Vec<(SyncSender<Vec<u128>>, Receiver<String>)> = (0..threads)
+            .map(|_| {
+                let (tx, jobs) = sync_channel::<Vec<u128>>(DEPTH);
+                let (done, rx) = sync_channel(DEPTH);
+                // Each worker stops once we hang up on it, either by dropping its sender when
+                // there's nothing left, or its receiver when writing fails.
+                scope.spawn(move || {
+                    for chunk in jobs {
+                        let mut text = String::new();
+                        for n in chunk {
+                            let line = match rules.label(n) {
+                                Some(words) => Line::Words(n, words),
+                                None => Line::Number(n),
+                            };
+                            // Writing to a String can't fail.
+                            let _ = writeln!(text, "{}", format.record(&line));
+                        }
+                        if done.send(text).is_err() {
+                            return;
+                        }
+                    }
+                });
+                (tx, rx)
+            })
+            .collect();
+
+        let mut range = range.peekable();
+        let mut chunks = std::iter::from_fn(move || {
+            range.peek()?;
+            Some(range.by_ref().take(CHUNK).collect::<Vec<u128>>())
+        });
+        // Fill every worker's queue, then hand each one a new chunk as we collect one from it.
+        // Nobody ever has more than DEPTH outstanding, so neither side of a channel blocks for
+        // long.
+        let mut sent = 0;
+        for _ in 0..DEPTH {
+            for (tx, _) in &workers {
+                match chunks.next() {
+                    Some(chunk) => tx.send(chunk).expect("fizzbuzz worker exited"),
+                    None => break,
+                }
+                sent += 1;
+            }
+        }
+        let mut received = 0;
+        while received < sent {
+            let (tx, rx) = &workers[received % threads];
+            let text = rx.recv().expect("fizzbuzz worker exited");
+            received += 1;
+            out.write_all(text.as_bytes())?;
+            if let Some(chunk) = chunks.next() {
+                tx.send(chunk).expect("fizzbuzz worker exited");
+                sent += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(range: Option<Range>) -> Vec<u128> {
+        range.unwrap().collect()
+    }
+
+    #[test]
+    fn counting_down_stops_at_zero() {
+        assert_eq!(numbers(Range::new(2, 0, -1)), [2, 1, 0]);
+        assert_eq!(numbers(Range::new(3, 0, -2)), [3, 1]);
+        assert_eq!(numbers(Range::new(0, 0, -1)), [0]);
+    }
+
+    #[test]
+    fn counting_down_from_the_top() {
+        let max = u128::MAX;
+        assert_eq!(
+            numbers(Range::new(max, max - 2, -1)),
+            [max, max - 1, max - 2]
+        );
+        assert_eq!(numbers(Range::new(max, max - 3, -2)), [max, max - 2]);
+        assert_eq!(numbers(Range::new(max, max, i128::MIN)), [max]);
+    }
+
+    #[test]
+    fn forever_wraps() {
+        let range = Range::forever(1, -1).unwrap();
+        assert_eq!(range.take(3).collect::<Vec<_>>(), [1, 0, u128::MAX]);
+    }
+
+    // Six chunks on four threads, so the workers don't all get the same number of them, and the
+    // last chunk is a short one.
+    #[test]
+    fn parallel_matches_sequential() {
+        let range = Range::new(1, 5 * CHUNK as u128 + 7, 1).unwrap();
+        for format in [Format::Text, Format::Csv] {
+            let mut sequential = Vec::new();
+            for line in FizzBuzz::new(Rules::classic(), range.clone()) {
+                writeln!(sequential, "{}", format.record(&line)).unwrap();
+            }
+            let mut parallel = Vec::new();
+            write_parallel(&Rules::classic(), range.clone(), &format, 4, &mut parallel).unwrap();
+            assert!(parallel == sequential, "{:?}", format);
+        }
+    }
+}
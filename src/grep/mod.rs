@@ -0,0 +1,94 @@
+use crate::error::IoError;
+use regex::bytes::Regex;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// How to search, and what to say about what's found, like grep's -v, -n and -l.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub regex: Regex,
+    // Lines that don't match, instead of those that do.
+    pub invert: bool,
+    pub line_numbers: bool,
+    // Only the names of the files with a line in them.
+    pub files_only: bool,
+}
+
+impl Search {
+    // Writes the lines of contents that are wanted to out, each after its file's name when
+    // named, and says whether there were any. Contents with a NUL in them are taken to be
+    // binary, and only said to match rather than written, so as not to mess up a terminal.
+    pub fn search(&self, name: &str, named: bool, contents: &[u8], out: &mut Vec<u8>) -> bool {
+        let mut lines = contents.split(|&b| b == b'\n');
+        // A newline at the end ends the last line rather than starting another.
+        if contents.ends_with(b"\n") || contents.is_empty() {
+            lines.next_back();
+        }
+        let binary = contents.contains(&0);
+        let mut found = false;
+        for (n, line) in lines.enumerate() {
+            if self.regex.is_match(line) == self.invert {
+                continue;
+            }
+            found = true;
+            if self.files_only || binary {
+                break;
+            }
+            // Writing to a Vec can't fail.
+            if named {
+                let _ = write!(out, "{}:", name);
+            }
+            if self.line_numbers {
+                let _ = write!(out, "{}:", n + 1);
+            }
+            out.extend_from_slice(line);
+            out.push(b'\n');
+        }
+        if found && self.files_only {
+            let _ = writeln!(out, "{}", name);
+        } else if found && binary {
+            let _ = writeln!(out, "Binary file {} matches", name);
+        }
+        found
+    }
+}
+
+// The files to search in paths: each of them, and with recursive, everything in each directory
+// among them, and in theirs, in name order so the results are the same every time. Links to
+// directories aren't followed, so a link back up can't send us round in circles, unless they
+// were asked for by name. A directory that couldn't be read is there as its error, in place of
+// its files.
+pub fn files(paths: &[PathBuf], recursive: bool) -> Vec<Result<PathBuf, IoError>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if recursive && path.is_dir() {
+            walk(path, &mut files);
+        } else {
+            files.push(Ok(path.clone()));
+        }
+    }
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<Result<PathBuf, IoError>>) {
+    let context = || format!("reading {}", dir.display());
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => return files.push(Err(IoError::new(context(), err))),
+    };
+    let mut entries: Vec<_> = match entries.collect() {
+        Ok(entries) => entries,
+        Err(err) => return files.push(Err(IoError::new(context(), err))),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => walk(&path, files),
+            Ok(kind) if kind.is_symlink() && path.is_dir() => {}
+            Ok(_) => files.push(Ok(path)),
+            Err(err) => files.push(Err(IoError::new(context(), err))),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::error::IoError;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// Files being followed, like tail -f: whatever's added to one is written as soon as it's
+// noticed, under a header saying which file it's from whenever that's a different one from last
+// time and there are headers.
+pub struct Follower {
+    files: Vec<Followed>,
+    headers: bool,
+    // Which file was written from last, so its header needn't be written again.
+    last: Option<usize>,
+    watch: Watch,
+    interval: Duration,
+}
+
+struct Followed {
+    name: String,
+    file: File,
+    position: u64,
+}
+
+// How to find out that a file might have changed: by being told, with inotify, or by looking
+// every so often.
+enum Watch {
+    #[cfg(target_os = "linux")]
+    Notify(inotify::Inotify),
+    Poll(Duration),
+}
+
+impl Follower {
+    // Polls every interval, unless poll is false and it can be told about changes instead.
+    // last is which of the files their tails were written from last, if any.
+    pub fn new(headers: bool, last: Option<usize>, poll: bool, interval: Duration) -> Self {
+        Follower {
+            files: Vec::new(),
+            headers,
+            last,
+            watch: Watch::new(poll, interval),
+            interval,
+        }
+    }
+
+    // Follows file, already read up to where it is now, from there on. If it can't be watched,
+    // it's still polled along with the rest, as often as they're looked at.
+    pub fn add(&mut self, name: String, path: &Path, mut file: File) -> Result<(), IoError> {
+        let position = file
+            .stream_position()
+            .map_err(IoError::context(name.clone()))?;
+        if let Err(err) = self.watch.add(path) {
+            self.watch = Watch::Poll(self.interval);
+            crate::debug!("polling, as {} can't be watched: {}", name, err);
+        }
+        self.files.push(Followed {
+            name,
+            file,
+            position,
+        });
+        Ok(())
+    }
+
+    // Writes whatever's added to the files, for as long as out can be written to. A file that's
+    // got shorter has been truncated, so is followed from its start again.
+    pub fn run<W: Write>(mut self, out: &mut W) -> io::Result<()> {
+        loop {
+            for i in 0..self.files.len() {
+                self.catch_up(i, out)?;
+            }
+            out.flush()?;
+            self.watch.wait()?;
+        }
+    }
+
+    fn catch_up<W: Write>(&mut self, i: usize, out: &mut W) -> io::Result<()> {
+        let followed = &mut self.files[i];
+        let len = match followed.file.metadata() {
+            Ok(metadata) => metadata.len(),
+            // It's still there to read from, whatever's become of its name.
+            Err(_) => return Ok(()),
+        };
+        if len < followed.position {
+            eprintln!("{}: file truncated", followed.name);
+            followed.position = followed.file.seek(SeekFrom::Start(0))?;
+        }
+        if len == followed.position {
+            return Ok(());
+        }
+        if self.headers && self.last != Some(i) {
+            let blank = if self.last.is_some() { "\n" } else { "" };
+            writeln!(out, "{}==> {} <==", blank, followed.name)?;
+        }
+        self.last = Some(i);
+        followed.position += io::copy(&mut (&followed.file).take(len - followed.position), out)?;
+        Ok(())
+    }
+}
+
+impl Watch {
+    fn new(poll: bool, interval: Duration) -> Self {
+        #[cfg(target_os = "linux")]
+        if !poll {
+            match inotify::Inotify::new() {
+                Ok(inotify) => return Watch::Notify(inotify),
+                Err(err) => crate::debug!("polling, as there's no inotify: {}", err),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = poll;
+        Watch::Poll(interval)
+    }
+
+    fn add(&mut self, path: &Path) -> io::Result<()> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Watch::Notify(inotify) => inotify.watch(path),
+            Watch::Poll(_) => Ok(()),
+        }
+    }
+
+    fn wait(&self) -> io::Result<()> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Watch::Notify(inotify) => inotify.wait(),
+            Watch::Poll(interval) => {
+                thread::sleep(*interval);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Just enough of inotify(7) to be woken when a file's written to, without a crate for it.
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub struct Inotify(OwnedFd);
+
+    impl Inotify {
+        pub fn new() -> io::Result<Self> {
+            // Safe, as it only makes a new descriptor, which is ours alone once it has.
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Inotify(unsafe { OwnedFd::from_raw_fd(fd) }))
+        }
+
+        // Written to, or truncated, which only changes its attributes.
+        pub fn watch(&self, path: &Path) -> io::Result<()> {
+            let path = CString::new(path.as_os_str().as_bytes())?;
+            let mask = libc::IN_MODIFY | libc::IN_ATTRIB;
+            // Safe, as path is a proper C string for as long as it's needed.
+            let wd = unsafe { libc::inotify_add_watch(self.0.as_raw_fd(), path.as_ptr(), mask) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        // Blocks until something happens to a file being watched. Which file, and what, don't
+        // matter, since they're all looked at again anyway.
+        pub fn wait(&self) -> io::Result<()> {
+            let mut events = [0u8; 4096];
+            loop {
+                // Safe, as events is as long as it's said to be.
+                let read = unsafe {
+                    libc::read(self.0.as_raw_fd(), events.as_mut_ptr().cast(), events.len())
+                };
+                if read >= 0 {
+                    return Ok(());
+                }
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
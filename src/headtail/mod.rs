@@ -0,0 +1,137 @@
+pub mod follow;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::mem;
+
+// How much of the start or end of a file: so many lines, or so many bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Lines(u64),
+    Bytes(u64),
+}
+
+// Copies the first count of reader to out, reading no further than it has to.
+pub fn head<R: BufRead, W: Write>(mut reader: R, count: Count, out: &mut W) -> io::Result<()> {
+    match count {
+        Count::Bytes(n) => {
+            io::copy(&mut reader.take(n), out)?;
+        }
+        Count::Lines(n) => {
+            let mut line = Vec::new();
+            for _ in 0..n {
+                line.clear();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+                out.write_all(&line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Copies the last count of reader to out. There's no knowing where the end is until it's
+// reached, so everything that might be in it is kept until then, which is no more than count.
+pub fn tail<R: BufRead, W: Write>(mut reader: R, count: Count, out: &mut W) -> io::Result<()> {
+    match count {
+        Count::Bytes(n) => {
+            let mut last = VecDeque::new();
+            let mut buf = [0; 8192];
+            loop {
+                let read = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                last.extend(&buf[..read]);
+                let over = last.len().saturating_sub(n as usize);
+                last.drain(..over);
+            }
+            let (front, back) = last.as_slices();
+            out.write_all(front)?;
+            out.write_all(back)?;
+        }
+        Count::Lines(n) => {
+            let mut last: VecDeque<Vec<u8>> = VecDeque::new();
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+                if n == 0 {
+                    continue;
+                }
+                // A line that's fallen out of the end can hold the next one.
+                let spare = if last.len() as u64 == n {
+                    last.pop_front()
+                } else {
+                    None
+                };
+                last.push_back(mem::replace(&mut line, spare.unwrap_or_default()));
+            }
+            for line in last {
+                out.write_all(&line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Reads past everything before the count'th line or byte from the start, like tail -n +count, so
+// that the rest of reader is the tail that starts there. The first is 1, and 0 means it too.
+pub fn skip_to<R: BufRead>(reader: &mut R, count: Count) -> io::Result<()> {
+    match count {
+        Count::Bytes(n) => {
+            io::copy(&mut reader.take(n.saturating_sub(1)), &mut io::sink())?;
+        }
+        Count::Lines(n) => {
+            let mut line = Vec::new();
+            for _ in 1..n {
+                line.clear();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// How much of a file to read back at a time, looking for where its last lines start.
+const CHUNK: u64 = 64 * 1024;
+
+// Where the last count of file starts, found by reading back from its end rather than through
+// all of it. A newline at the very end ends the last line, rather than starting another.
+pub fn tail_start(file: &mut File, count: Count) -> io::Result<u64> {
+    let len = file.metadata()?.len();
+    let n = match count {
+        Count::Bytes(n) => return Ok(len.saturating_sub(n)),
+        Count::Lines(0) => return Ok(len),
+        Count::Lines(n) => n,
+    };
+    let mut newlines = 0;
+    let mut end = len;
+    let mut buf = vec![0; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for (i, &b) in chunk.iter().enumerate().rev() {
+            let at = start + i as u64;
+            if b != b'\n' || at == len - 1 {
+                continue;
+            }
+            newlines += 1;
+            if newlines == n {
+                return Ok(at + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
@@ -1 +1,36 @@
+// Without the std feature, only the portable algorithms are built, on core and alloc alone.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod completions;
+#[cfg(feature = "std")]
+pub mod du;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod execute;
+#[cfg(feature = "std")]
+pub mod fizzbuzz;
+#[cfg(feature = "std")]
+pub mod grep;
+#[cfg(feature = "std")]
+pub mod headtail;
+#[cfg(feature = "std")]
+pub mod locked_write;
+#[cfg(feature = "std")]
+pub mod log;
+#[cfg(feature = "std")]
+pub mod man;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod portable;
+#[cfg(feature = "std")]
 pub mod primes;
+#[cfg(feature = "std")]
+pub mod sort;
+#[cfg(feature = "std")]
+pub mod table;
+#[cfg(feature = "std")]
+pub mod wc;
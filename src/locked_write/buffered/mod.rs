@@ -0,0 +1,94 @@
+use super::LockWrite;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+const CAPACITY: usize = 8 * 1024;
+
+// Buffers what's written to target, then writes whole lines of it at once: every so often, or
+// once there's enough of them, whichever comes first, and whenever it's flushed. Each write
+// takes target's lock once for all the lines it has, so they come out together and with a single
+// syscall, rather than one per line.
+//
+// It only holds the lock while writing, not all the time. If it did, anything else with lines
+// for the same target, e.g. another thread's LineBuffer, would have to wait until it was done
+// altogether.
+//
+// There's no timer of its own, so lines only go out on time when there are more writes to notice
+// the time, or a flush. Whoever's writing is the one who knows when there won't be more for a
+// while, e.g. before blocking on a read, and that's when to flush.
+pub struct LineBuffer<T: LockWrite> {
+    target: T,
+    buf: Vec<u8>,
+    interval: Duration,
+    capacity: usize,
+    written: Instant,
+}
+
+impl<T: LockWrite> LineBuffer<T> {
+    pub fn new(target: T) -> Self {
+        LineBuffer {
+            target,
+            buf: Vec::with_capacity(CAPACITY),
+            interval: INTERVAL,
+            capacity: CAPACITY,
+            written: Instant::now(),
+        }
+    }
+
+    // How long lines may wait to be written, 100ms unless told otherwise. Zero writes each line
+    // as soon as it's complete.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // How much may pile up before it's written regardless, 8K unless told otherwise.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    // Writes everything up to the end of the last whole line, leaving any line still being
+    // written for later.
+    fn write_lines(&mut self) -> io::Result<()> {
+        if let Some(newline) = self.buf.iter().rposition(|&b| b == b'\n') {
+            self.target.lock_write().write_all(&self.buf[..=newline])?;
+            self.buf.drain(..=newline);
+        }
+        self.written = Instant::now();
+        Ok(())
+    }
+}
+
+impl<T: LockWrite> Write for LineBuffer<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let before = self.buf.len();
+        self.buf.extend_from_slice(buf);
+        if self.written.elapsed() >= self.interval || self.buf.len() >= self.capacity {
+            if let Err(err) = self.write_lines() {
+                // Nothing of buf was written, then, so it shouldn't be kept as though it had been.
+                self.buf.truncate(before);
+                return Err(err);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    // Writes everything, even a line that hasn't ended yet.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut target = self.target.lock_write();
+        target.write_all(&self.buf)?;
+        self.buf.clear();
+        self.written = Instant::now();
+        target.flush()
+    }
+}
+
+// Like a BufWriter, whatever's left is written on the way out, but there's nobody to tell if
+// that fails. Flush first to find out.
+impl<T: LockWrite> Drop for LineBuffer<T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+// A writer that sends each line it's given to a thread of its own to write to out, so no line
+// is ever split by another, and none of the threads with lines to write ever waits for out.
+// Cloning the sender gives another thread somewhere to write to. The thread finishes once every
+// sender has gone, with out itself, or with the first error out gave, after which sending
+// fails as a broken pipe would.
+//
+// shared::SharedLineWriter is the alternative, without the thread.
+pub fn channel<W: Write + Send + 'static>(mut out: W) -> (LineSender, JoinHandle<io::Result<W>>) {
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    let writer = thread::spawn(move || {
+        for lines in rx {
+            out.write_all(&lines)?;
+        }
+        out.flush()?;
+        Ok(out)
+    });
+    let sender = LineSender {
+        tx,
+        pending: Vec::new(),
+    };
+    (sender, writer)
+}
+
+pub struct LineSender {
+    tx: Sender<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+impl LineSender {
+    fn send(&self, lines: Vec<u8>) -> io::Result<()> {
+        self.tx.send(lines).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the thread doing the writing has stopped",
+            )
+        })
+    }
+}
+
+// A clone sends to the same thread, with its own lines.
+impl Clone for LineSender {
+    fn clone(&self) -> Self {
+        LineSender {
+            tx: self.tx.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl Write for LineSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        if let Some(newline) = self.pending.iter().rposition(|&b| b == b'\n') {
+            let rest = self.pending.split_off(newline + 1);
+            let lines = std::mem::replace(&mut self.pending, rest);
+            // With the thread gone, there's no writing anything ever again, so no point keeping
+            // hold of what couldn't be sent.
+            self.send(lines)?;
+        }
+        Ok(buf.len())
+    }
+
+    // Sends the line that's still being written too, as it is. It's written once the thread
+    // gets to it, not necessarily by the time this returns.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let line = std::mem::take(&mut self.pending);
+        self.send(line)
+    }
+}
+
+impl Drop for LineSender {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
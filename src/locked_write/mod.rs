@@ -0,0 +1,184 @@
+pub mod buffered;
+pub mod channel;
+pub mod prefixed;
+pub mod shared;
+pub mod tee;
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Something shared, like stdout, that hands whoever locks it a writer of their own for as long as
+// they hold the lock. Code generic over it can take the lock once for several writes, so they
+// come out together, without caring whether it's writing to stdout or stderr.
+//
+// The lock borrows what it locks, so naming its type takes a lifetime the trait can only have
+// with a generic associated type. Before those, it had to be faked with a family trait per
+// lifetime, like WriteFamilyLt, or avoided altogether with an enum of the handles there were.
+pub trait LockWrite {
+    type Locked<'a>: Write
+    where
+        Self: 'a;
+
+    fn lock_write(&self) -> Self::Locked<'_>;
+}
+
+// Locks target once to write all of parts, one after the other, so nothing else written to it
+// comes between them. Unlike a bare write, which may write only some of what it's given, each
+// part is written in full or not at all.
+pub fn write_locked<T: LockWrite + ?Sized>(target: &T, parts: &[&[u8]]) -> io::Result<()> {
+    let mut locked = target.lock_write();
+    for part in parts {
+        locked.write_all(part)?;
+    }
+    Ok(())
+}
+
+// What to do about an error writing: stop writing, but without calling it a failure; pass it on
+// to the caller; or carry on as though it never happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    Stop,
+    Propagate,
+    Ignore,
+}
+
+// Whether to keep writing after a write, or stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+// How to take the errors from writing, telling a broken pipe, e.g. from head having read all it
+// wanted, apart from the rest. By default a broken pipe just means there's nobody left to write
+// for, so nothing more to do, while anything else is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub broken_pipe: OnError,
+    pub other: OnError,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            broken_pipe: OnError::Stop,
+            other: OnError::Propagate,
+        }
+    }
+}
+
+impl Policy {
+    // What to do after a write, going by how it went.
+    pub fn check(self, result: io::Result<()>) -> io::Result<Flow> {
+        let err = match result {
+            Ok(()) => return Ok(Flow::Continue),
+            Err(err) => err,
+        };
+        let on_error = if err.kind() == io::ErrorKind::BrokenPipe {
+            self.broken_pipe
+        } else {
+            self.other
+        };
+        match on_error {
+            OnError::Stop => Ok(Flow::Stop),
+            OnError::Propagate => Err(err),
+            OnError::Ignore => Ok(Flow::Continue),
+        }
+    }
+}
+
+impl LockWrite for io::Stdout {
+    type Locked<'a> = io::StdoutLock<'static>;
+
+    fn lock_write(&self) -> Self::Locked<'_> {
+        self.lock()
+    }
+}
+
+impl LockWrite for io::Stderr {
+    type Locked<'a> = io::StderrLock<'static>;
+
+    fn lock_write(&self) -> Self::Locked<'_> {
+        self.lock()
+    }
+}
+
+impl<T: LockWrite + ?Sized> LockWrite for &T {
+    type Locked<'a>
+        = T::Locked<'a>
+    where
+        Self: 'a;
+
+    fn lock_write(&self) -> Self::Locked<'_> {
+        (**self).lock_write()
+    }
+}
+
+impl<T: LockWrite + ?Sized> LockWrite for Arc<T> {
+    type Locked<'a>
+        = T::Locked<'a>
+    where
+        Self: 'a;
+
+    fn lock_write(&self) -> Self::Locked<'_> {
+        (**self).lock_write()
+    }
+}
+
+// Any writer at all, e.g. a Mutex<File> for a log file, or a Mutex<Vec<u8>> to keep everything
+// written in memory, where a test can look at it afterwards with into_inner. A writer whose last
+// holder panicked is still a writer, if maybe with half a line in it, so poisoning is ignored.
+impl<W: Write> LockWrite for Mutex<W> {
+    type Locked<'a>
+        = Guard<'a, W>
+    where
+        Self: 'a;
+
+    fn lock_write(&self) -> Self::Locked<'_> {
+        Guard(self.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+// What a Mutex locks to: the guard of its writer, which is a writer itself.
+pub struct Guard<'a, W: Write>(MutexGuard<'a, W>);
+
+impl<W: Write> Write for Guard<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutex_of_vec() {
+        let target = Mutex::new(Vec::new());
+        write_locked(&target, &[b"one ", b"line\n"]).unwrap();
+        write_locked(&target, &[b"another\n"]).unwrap();
+        assert_eq!(target.into_inner().unwrap(), b"one line\nanother\n");
+    }
+
+    #[test]
+    fn broken_pipe_stops() {
+        let broken = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert_eq!(Policy::default().check(Err(broken)).unwrap(), Flow::Stop);
+        assert_eq!(Policy::default().check(Ok(())).unwrap(), Flow::Continue);
+    }
+
+    #[test]
+    fn other_errors_propagate() {
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        let err = Policy::default().check(Err(denied)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
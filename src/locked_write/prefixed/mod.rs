@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+
+// A writer that starts every line written to it with a prefix, e.g. the directory some output
+// came from. Lines can arrive in as many pieces as they like, or several to a write, and each
+// still gets the prefix once, at its start; an empty write doesn't start a line.
+pub struct PrefixedWriter<W: Write> {
+    inner: W,
+    prefix: Vec<u8>,
+    // Whether the next byte written starts a line, and so needs the prefix first.
+    line_start: bool,
+}
+
+impl<W: Write> PrefixedWriter<W> {
+    pub fn new<P: Into<Vec<u8>>>(inner: W, prefix: P) -> Self {
+        PrefixedWriter {
+            inner,
+            prefix: prefix.into(),
+            line_start: true,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for PrefixedWriter<W> {
+    // Writes no further than the end of the first line in buf, so that the next write knows to
+    // start with the prefix. The prefix isn't part of buf, so isn't counted in what was written.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.line_start {
+            self.inner.write_all(&self.prefix)?;
+            self.line_start = false;
+        }
+        let end = buf
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buf.len(), |newline| newline + 1);
+        let written = self.inner.write(&buf[..end])?;
+        if written > 0 && buf[written - 1] == b'\n' {
+            self.line_start = true;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Takes at most a few bytes at a time, like a pipe that's nearly full.
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn line_in_pieces() {
+        let mut out = PrefixedWriter::new(Vec::new(), "a: ");
+        out.write_all(b"one ").unwrap();
+        out.write_all(b"").unwrap();
+        out.write_all(b"line\n").unwrap();
+        assert_eq!(out.into_inner(), b"a: one line\n");
+    }
+
+    #[test]
+    fn partial_writes() {
+        let mut out = PrefixedWriter::new(Trickle(Vec::new()), "a: ");
+        out.write_all(b"first line\nsecond\n").unwrap();
+        assert_eq!(out.into_inner().0, b"a: first line\na: second\n");
+    }
+
+    #[test]
+    fn several_lines() {
+        let mut out = PrefixedWriter::new(Vec::new(), "a: ");
+        out.write_all(b"one\ntwo\n\nfour\n").unwrap();
+        assert_eq!(out.into_inner(), b"a: one\na: two\na: \na: four\n");
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let mut out = PrefixedWriter::new(Vec::new(), "a: ");
+        out.write_all(b"one\ntwo").unwrap();
+        assert_eq!(out.into_inner(), b"a: one\na: two");
+    }
+}
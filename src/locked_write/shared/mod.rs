@@ -0,0 +1,75 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+// A writer that any number of threads can share, each with a clone of its own, where every line
+// comes out whole, however the threads' writes happen to interleave. Each clone holds on to a
+// line until it's complete, then writes it, along with any others it has, under the lock, so it
+// can't be split by another thread's.
+//
+// This is the alternative to sending lines to a thread that does all the writing, as
+// channel::LineSender does: the threads take turns writing themselves, which saves a thread and
+// a copy of each line, at the cost of waiting for each other when they write at once.
+pub struct SharedLineWriter<W: Write> {
+    shared: Arc<Mutex<W>>,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> SharedLineWriter<W> {
+    pub fn new(inner: W) -> Self {
+        SharedLineWriter {
+            shared: Arc::new(Mutex::new(inner)),
+            pending: Vec::new(),
+        }
+    }
+}
+
+// A clone writes to the same writer, with its own lines.
+impl<W: Write> Clone for SharedLineWriter<W> {
+    fn clone(&self) -> Self {
+        SharedLineWriter {
+            shared: Arc::clone(&self.shared),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for SharedLineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let before = self.pending.len();
+        self.pending.extend_from_slice(buf);
+        if let Some(newline) = self.pending.iter().rposition(|&b| b == b'\n') {
+            // A thread that panicked mid-line only spoils its own line, which is the next
+            // line's problem at worst, so poisoning is ignored.
+            let mut inner = self
+                .shared
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Err(err) = inner.write_all(&self.pending[..=newline]) {
+                // Nothing of buf was written, then, so it shouldn't be kept as though it had been.
+                self.pending.truncate(before);
+                return Err(err);
+            }
+            self.pending.drain(..=newline);
+        }
+        Ok(buf.len())
+    }
+
+    // Writes the line that's still being written too, as it is.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut inner = self
+            .shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.write_all(&self.pending)?;
+        self.pending.clear();
+        inner.flush()
+    }
+}
+
+impl<W: Write> Drop for SharedLineWriter<W> {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            let _ = self.flush();
+        }
+    }
+}
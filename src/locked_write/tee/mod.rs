@@ -0,0 +1,70 @@
+use super::{Flow, Policy};
+use std::io::{self, Write};
+
+// A writer that writes everything it's given to each of its sinks, like tee, e.g. to stdout and
+// a transcript file. Each sink has a Policy of its own, so that e.g. whoever's reading stdout
+// going away can stop the writing there without stopping the transcript. Once every sink has
+// stopped, there's nothing left to write to, and writing fails as with a broken pipe.
+//
+// Every sink gets all of each write, or an error, so a failed write may have reached the sinks
+// before the one that failed.
+pub struct TeeWriter<W: Write> {
+    sinks: Vec<Sink<W>>,
+}
+
+struct Sink<W> {
+    writer: W,
+    policy: Policy,
+    stopped: bool,
+}
+
+impl<W: Write> TeeWriter<W> {
+    pub fn new() -> Self {
+        TeeWriter { sinks: Vec::new() }
+    }
+
+    pub fn sink(mut self, writer: W, policy: Policy) -> Self {
+        self.sinks.push(Sink {
+            writer,
+            policy,
+            stopped: false,
+        });
+        self
+    }
+
+    // Does f to each sink that hasn't stopped, as its policy says.
+    fn each<F>(&mut self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&mut W) -> io::Result<()>,
+    {
+        for sink in self.sinks.iter_mut().filter(|sink| !sink.stopped) {
+            if sink.policy.check(f(&mut sink.writer))? == Flow::Stop {
+                sink.stopped = true;
+            }
+        }
+        if self.sinks.iter().all(|sink| sink.stopped) {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "every sink has stopped",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Default for TeeWriter<W> {
+    fn default() -> Self {
+        TeeWriter::new()
+    }
+}
+
+impl<W: Write> Write for TeeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.each(|writer| writer.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.each(|writer| writer.flush())
+    }
+}
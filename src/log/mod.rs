@@ -0,0 +1,154 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+// Diagnostics on stderr, for seeing what a binary is up to without adding eprintln!s to find out,
+// e.g.
+//     [   0.004s debug execute_in_dirs] > running make in src/
+//     [   1.210s debug execute_in_dirs] < running make in src/ (1.206s)
+// Nothing is written unless it's asked for, with -v for the main steps, -vv for more detail and
+// -vvv for everything, so it costs no more than checking the level when it's off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info = 1,
+    Debug = 2,
+    Trace = 3,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        })
+    }
+}
+
+static VERBOSITY: AtomicUsize = AtomicUsize::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    // How many spans this thread is in, to indent by.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+// Turns logging on for levels up to verbosity, the number of -v flags there were. Timestamps
+// count from here.
+pub fn init(verbosity: u8) {
+    START.get_or_init(Instant::now);
+    let verbosity = verbosity.min(Level::Trace as u8) as usize;
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as usize <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+// Writes a line for the macros below, which have already checked it's enabled. A line that can't
+// be written is dropped, since there's nowhere else to say so.
+pub fn write(level: Level, target: &str, message: fmt::Arguments<'_>) {
+    let elapsed = START.get_or_init(Instant::now).elapsed().as_secs_f64();
+    let indent = DEPTH.with(Cell::get) * 2;
+    let _ = writeln!(
+        io::stderr().lock(),
+        "[{:>8.3}s {:<5} {}] {:indent$}{}",
+        elapsed,
+        level,
+        target,
+        "",
+        message,
+        indent = indent
+    );
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log_at!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log_at!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log_at!($crate::log::Level::Trace, $($arg)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::log::enabled($level) {
+            $crate::log::write($level, module_path!(), format_args!($($arg)+));
+        }
+    };
+}
+
+// A stretch of work, logged when it starts and again, with how long it took, when the Span is
+// dropped. Whatever's logged on the same thread in between is indented under it, e.g.
+//     let _span = span!(Debug, "sieving up to {}", max);
+// The name is only worked out when the level is enabled.
+#[macro_export]
+macro_rules! span {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::log::Span::enter($crate::log::Level::$level, module_path!(), || {
+            format!($($arg)+)
+        })
+    };
+}
+
+#[must_use = "the span ends as soon as it's dropped"]
+pub struct Span {
+    entered: Option<Entered>,
+}
+
+struct Entered {
+    level: Level,
+    target: &'static str,
+    name: String,
+    start: Instant,
+}
+
+impl Span {
+    pub fn enter<F: FnOnce() -> String>(level: Level, target: &'static str, name: F) -> Self {
+        if !enabled(level) {
+            return Span { entered: None };
+        }
+        let name = name();
+        write(level, target, format_args!("> {}", name));
+        DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Span {
+            entered: Some(Entered {
+                level,
+                target,
+                name,
+                start: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(entered) = self.entered.take() {
+            DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+            write(
+                entered.level,
+                entered.target,
+                format_args!("< {} ({:.3?})", entered.name, entered.start.elapsed()),
+            );
+        }
+    }
+}
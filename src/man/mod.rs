@@ -0,0 +1,273 @@
+use clap::{Arg, Command};
+
+// A man page for command, in roff, made from the same arguments and descriptions as its --help.
+// Its arguments, options and subcommands each get a section, as they do in the help, and so does
+// each section of an after_help in the same form, like EXIT CODES, with an entry for each item.
+// Each subcommand's own arguments and options go under COMMANDS, as its help would have them.
+pub fn page(mut command: Command) -> String {
+    // So that what clap adds itself, like --help, is there too.
+    command.build();
+    let name = command.get_name().to_string();
+    let title = match command.get_version() {
+        Some(version) => format!("{} {}", name, version),
+        None => name.clone(),
+    };
+    let about = command
+        .get_long_about()
+        .or_else(|| command.get_about())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let about: Vec<&str> = about
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let usage = command.render_usage().to_string();
+    let usage = usage
+        .trim_start()
+        .strip_prefix("Usage:")
+        .unwrap_or(usage.as_str());
+
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .partition(|arg| arg.is_positional());
+    let subcommands: Vec<(String, String)> = command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .map(|subcommand| {
+            let about = subcommand.get_about().map(ToString::to_string);
+            (subcommand.get_name().to_string(), about.unwrap_or_default())
+        })
+        .collect();
+    let mut sections = vec![
+        ("ARGUMENTS", entries(positionals)),
+        ("OPTIONS", entries(options)),
+        ("COMMANDS", subcommands),
+    ];
+    sections.retain(|(_, entries)| !entries.is_empty());
+    let mut subsections = Vec::new();
+    subcommand_sections(&command, &mut subsections);
+    let after_help = command
+        .get_after_long_help()
+        .or_else(|| command.get_after_help())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+
+    let mut page = String::new();
+    let mut line = |text: &str| {
+        page.push_str(text);
+        page.push('\n');
+    };
+    line(&format!(
+        ".TH \"{}\" 1 \"\" \"{}\"",
+        name.to_uppercase(),
+        escape(&title)
+    ));
+    line(".SH NAME");
+    line(&format!(
+        "{} \\- {}",
+        escape(&name),
+        escape(about.first().copied().unwrap_or_default())
+    ));
+    line(".SH SYNOPSIS");
+    let usages = usage
+        .lines()
+        .map(str::trim)
+        .filter(|usage| !usage.is_empty());
+    for (n, usage) in usages.enumerate() {
+        if n > 0 {
+            line(".br");
+        }
+        line(&escape(usage));
+    }
+    // The rest of what it's for goes after how it's used, as is usual.
+    if about.len() > 1 {
+        line(".SH DESCRIPTION");
+        for paragraph in &about {
+            line(&escape(paragraph));
+        }
+    }
+    for (heading, entries) in &sections {
+        line(&format!(".SH {}", heading));
+        for (term, description) in entries {
+            for text in entry(term, description) {
+                line(&text);
+            }
+        }
+    }
+    for subsection in &subsections {
+        line(&format!(".SS \"{}\"", escape(&subsection.name)));
+        line(&escape(&subsection.usage));
+        for (term, description) in &subsection.entries {
+            for text in entry(term, description) {
+                line(&text);
+            }
+        }
+    }
+    for item in after_help.lines().map(str::trim) {
+        match item.strip_suffix(':') {
+            Some(heading) if is_heading(heading) => line(&format!(".SH {}", heading)),
+            _ if item.is_empty() => {}
+            _ => {
+                // What's being described, then two or more spaces, then what it means.
+                let (term, description) = match item.find("  ") {
+                    Some(i) => (&item[..i], item[i..].trim_start()),
+                    None => (item, ""),
+                };
+                line(".TP");
+                line(&format!("\\fB{}\\fR", escape(term)));
+                if !description.is_empty() {
+                    line(&escape(description));
+                }
+            }
+        }
+    }
+    page
+}
+
+// An entry in a section: what's being described, in bold, then a paragraph for each line of its
+// description.
+fn entry(term: &str, description: &str) -> Vec<String> {
+    let mut lines = vec![".TP".to_string(), format!("\\fB{}\\fR", escape(term))];
+    lines.extend(
+        description
+            .lines()
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(escape),
+    );
+    lines
+}
+
+// Arguments as entries of what each is called and does.
+fn entries(args: Vec<&Arg>) -> Vec<(String, String)> {
+    args.into_iter()
+        .map(|arg| (term(arg), description(arg)))
+        .collect()
+}
+
+// The part of the page for a subcommand that takes arguments of its own.
+struct Subsection {
+    // What it's run as, e.g. headtail tail.
+    name: String,
+    usage: String,
+    entries: Vec<(String, String)>,
+}
+
+// Adds a subsection for each of command's subcommands that has arguments or options of its own,
+// and then for theirs. Global ones, and --help, which every subcommand has, are left to the
+// command's own OPTIONS.
+fn subcommand_sections(command: &Command, subsections: &mut Vec<Subsection>) {
+    for subcommand in command.get_subcommands() {
+        if subcommand.is_hide_set() {
+            continue;
+        }
+        let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = subcommand
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set() && !arg.is_global_set() && arg.get_id() != "help")
+            .partition(|arg| arg.is_positional());
+        let described = [entries(positionals), entries(options)].concat();
+        if !described.is_empty() {
+            let usage = subcommand.clone().render_usage().to_string();
+            let usage = usage.trim_start();
+            subsections.push(Subsection {
+                name: subcommand
+                    .get_bin_name()
+                    .unwrap_or(subcommand.get_name())
+                    .to_string(),
+                usage: usage
+                    .strip_prefix("Usage:")
+                    .unwrap_or(usage)
+                    .trim()
+                    .to_string(),
+                entries: described,
+            });
+        }
+        subcommand_sections(subcommand, subsections);
+    }
+}
+
+// How the help names an argument, e.g. <FILES>... or -c, --max-concurrency <CONCURRENCY>.
+fn term(arg: &Arg) -> String {
+    let mut values = match arg.get_value_names() {
+        Some(names) => names
+            .iter()
+            .map(|name| format!("<{}>", name))
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => format!("<{}>", arg.get_id()),
+    };
+    if arg.get_num_args().is_some_and(|n| n.max_values() > 1) {
+        values.push_str("...");
+    }
+    if arg.is_positional() {
+        return values;
+    }
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("--{}", long));
+    }
+    let mut term = names.join(", ");
+    if arg.get_action().takes_values() {
+        term.push(' ');
+        term.push_str(&values);
+    }
+    term
+}
+
+// What an argument does, then what it can be and what it is if it isn't given, as the help has
+// them.
+fn description(arg: &Arg) -> String {
+    let mut description = arg
+        .get_long_help()
+        .or_else(|| arg.get_help())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    if !arg.get_action().takes_values() {
+        return description;
+    }
+    let possible: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !possible.is_empty() {
+        description.push_str(&format!(" [possible values: {}]", possible.join(", ")));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect();
+    if !defaults.is_empty() && !arg.is_hide_default_value_set() {
+        description.push_str(&format!(" [default: {}]", defaults.join(" ")));
+    }
+    description
+}
+
+// Like EXIT CODES.
+fn is_heading(heading: &str) -> bool {
+    !heading.is_empty() && heading.chars().all(|c| c.is_ascii_uppercase() || c == ' ')
+}
+
+// Text as roff would have it: with backslashes and hyphens escaped, and a line that starts with
+// a control character kept from being taken for a request.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    if text.starts_with('.') || text.starts_with('\'') {
+        escaped.push_str("\\&");
+    }
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\e"),
+            '-' => escaped.push_str("\\-"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
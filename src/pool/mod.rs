@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+// Does work on each of items, on as many as threads of them at once, handing each result to
+// done in the order of the items, as soon as it and all those before it are done. Whichever
+// thread is free takes the next item, so one slow item holds up the output, but not the work.
+pub fn map_ordered<T, R, W, D>(items: &[T], threads: usize, work: W, mut done: D)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    D: FnMut(usize, R),
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = channel();
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            let tx = tx.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    return;
+                };
+                // Nobody's left to tell only if the calling thread has panicked.
+                if tx.send((i, work(item))).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        let mut finished = 0;
+        for (i, result) in rx {
+            pending[i] = Some(result);
+            while let Some(result) = pending.get_mut(finished).and_then(Option::take) {
+                done(finished, result);
+                finished += 1;
+            }
+        }
+    });
+}
@@ -0,0 +1,7 @@
+// The algorithms themselves, with nothing but core and alloc underneath, so they can go anywhere
+// Rust does, embedded and wasm included. Everything that reads, writes or parses lives under
+// primes, which needs std and is built on top of these. Building without the std feature leaves
+// just this.
+pub mod naive;
+pub mod primality;
+pub mod sieve;
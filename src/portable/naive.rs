@@ -0,0 +1,47 @@
+use alloc::vec::Vec;
+
+// Trial division by every prime found so far.
+#[derive(Debug)]
+pub struct Primes {
+    max: u64,
+    next: u64,
+    seen: Vec<u64>,
+}
+
+pub fn primes(max: u64) -> Primes {
+    Primes {
+        max,
+        next: 1,
+        seen: Vec::new(),
+    }
+}
+
+impl Primes {
+    // The next candidate it will consider.
+    pub fn next_candidate(&self) -> u64 {
+        self.next
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        'outer: for i in self.next..=self.max {
+            for j in &self.seen {
+                if i % j == 0 {
+                    continue 'outer;
+                }
+            }
+            if i != 1 {
+                self.seen.push(i);
+                self.next = i + 1;
+                return Some(i);
+            }
+        }
+        None
+    }
+}
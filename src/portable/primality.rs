@@ -0,0 +1,118 @@
+use crate::portable::naive;
+
+/// Every u64 can be proven prime or composite with these bases, so they're always tried first.
+pub const DETERMINISTIC_BASES: [u128; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Primality {
+    Prime,
+    ProbablePrime,
+    Composite,
+}
+
+// u128 multiplication overflows as soon as the operands pass 2^64, so above that we fall back to
+// shifting and adding, reducing as we go. It's slow, but it's correct for every modulus.
+pub fn mul_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    if a <= u128::from(u64::MAX) && b <= u128::from(u64::MAX) {
+        return (a * b) % modulus;
+    }
+    let mut a = a % modulus;
+    let mut b = b % modulus;
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, modulus);
+        }
+        a = add_mod(a, a, modulus);
+        b >>= 1;
+    }
+    result
+}
+
+pub fn add_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    // a and b are both already less than modulus, so a + b only overflows when the true sum is
+    // larger than modulus, and wrapping then subtracting gets us the right answer.
+    if a >= modulus - b {
+        a.wrapping_add(b).wrapping_sub(modulus)
+    } else {
+        a + b
+    }
+}
+
+pub fn pow_mod(base: u128, exp: u128, modulus: u128) -> u128 {
+    if modulus == 1 {
+        return 0;
+    }
+    let mut base = base % modulus;
+    let mut exp = exp;
+    let mut result = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+// Returns true if base proves n composite.
+fn is_witness(n: u128, d: u128, s: u32, base: u128) -> bool {
+    let mut x = pow_mod(base, d, n);
+    if x == 1 || x == n - 1 {
+        return false;
+    }
+    for _ in 1..s {
+        x = mul_mod(x, x, n);
+        if x == n - 1 {
+            return false;
+        }
+    }
+    true
+}
+
+// Miller-Rabin. Anything that fits in a u64 gets a definitive answer from the deterministic bases.
+// Beyond that we test against the first `rounds` primes, each of which lets through at most a
+// quarter of composites, so the answer is only ever "probably".
+pub fn is_prime(n: u128, rounds: usize) -> Primality {
+    let extra = naive::primes(u64::MAX)
+        .skip(DETERMINISTIC_BASES.len())
+        .take(rounds.saturating_sub(DETERMINISTIC_BASES.len()))
+        .map(u128::from);
+    miller_rabin(n, extra)
+}
+
+// Miller-Rabin with the deterministic bases, and then, for anything too large for them to settle,
+// the bases from extra. They're only drawn from when they're needed, so they can be as costly to
+// come up with as they like.
+pub fn miller_rabin<I: IntoIterator<Item = u128>>(n: u128, extra: I) -> Primality {
+    if n < 2 {
+        return Primality::Composite;
+    }
+    for &p in &DETERMINISTIC_BASES {
+        if n == p {
+            return Primality::Prime;
+        }
+        if n.is_multiple_of(p) {
+            return Primality::Composite;
+        }
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    if DETERMINISTIC_BASES
+        .iter()
+        .any(|&base| is_witness(n, d, s, base))
+    {
+        return Primality::Composite;
+    }
+    if n <= u128::from(u64::MAX) {
+        return Primality::Prime;
+    }
+    if extra.into_iter().any(|base| is_witness(n, d, s, base)) {
+        Primality::Composite
+    } else {
+        Primality::ProbablePrime
+    }
+}
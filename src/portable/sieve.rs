@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+
+// Records which numbers have been eliminated, however they're stored.
+pub trait Eliminated {
+    fn is_eliminated(&self, n: u64) -> bool;
+    fn eliminate(&mut self, n: u64);
+}
+
+impl Eliminated for Vec<bool> {
+    fn is_eliminated(&self, n: u64) -> bool {
+        self[n as usize]
+    }
+
+    fn eliminate(&mut self, n: u64) {
+        self[n as usize] = true;
+    }
+}
+
+#[derive(Debug)]
+pub struct Primes<E = Vec<bool>> {
+    max: u64,
+    next: u64,
+    eliminated: E,
+}
+
+impl<E: Eliminated> Primes<E> {
+    // Sieve with a table that covers 0..=max and has nothing eliminated yet.
+    pub fn with(max: u64, mut eliminated: E) -> Self {
+        eliminated.eliminate(0);
+        eliminated.eliminate(1);
+        Primes {
+            max,
+            next: 1,
+            eliminated,
+        }
+    }
+
+    // The next candidate it will consider.
+    pub fn next_candidate(&self) -> u64 {
+        self.next
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+}
+
+impl<E: Eliminated> Iterator for Primes<E> {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        for n in self.next..=self.max {
+            if !self.eliminated.is_eliminated(n) {
+                let mut current = n + n;
+                while current <= self.max {
+                    self.eliminated.eliminate(current);
+                    current += n;
+                }
+                self.next = n + 1;
+                return Some(n);
+            }
+        }
+        None
+    }
+}
+
+// Unlike Primes, which eliminates lazily as it's iterated, a Table is fully sieved up front so it
+// can answer whether any n <= max is prime in constant time.
+#[derive(Debug)]
+pub struct Table {
+    eliminated: Vec<bool>,
+}
+
+impl Table {
+    pub fn new(max: u64) -> Self {
+        let mut eliminated = alloc::vec![false; (max + 1) as usize];
+        for n in eliminated.iter_mut().take(2) {
+            *n = true;
+        }
+        let mut n = 2;
+        while n * n <= max {
+            if !eliminated[n as usize] {
+                let mut current = n * n;
+                while current <= max {
+                    eliminated[current as usize] = true;
+                    current += n;
+                }
+            }
+            n += 1;
+        }
+        Table { eliminated }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.eliminated.len() as u64 - 1
+    }
+
+    // Whether n is prime. Anything past max isn't in the table, so we can't say, and say no.
+    pub fn contains(&self, n: u64) -> bool {
+        self.eliminated.get(n as usize).is_some_and(|&e| !e)
+    }
+}
@@ -0,0 +1,44 @@
+use crate::primes::factor::{factor, powers};
+
+// The classic multiplicative functions of n. Each one is a product over n's prime powers, so they
+// all fall straight out of the factorization. The sum of divisors can outgrow a u128 for n near
+// the top of the range, in which case it's None.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Arith {
+    // Euler's totient: how many of 1..=n are coprime to n.
+    pub phi: u128,
+    // The sum of n's divisors.
+    pub sigma: Option<u128>,
+    // How many divisors n has.
+    pub divisors: u128,
+}
+
+// None for 0, which has no factorization.
+pub fn arith(n: u128) -> Option<Arith> {
+    if n == 0 {
+        return None;
+    }
+    let mut result = Arith {
+        phi: 1,
+        sigma: Some(1),
+        divisors: 1,
+    };
+    for (p, e) in powers(&factor(n)) {
+        // p^(e-1) always divides n, so this can't overflow.
+        let lower = p.pow(e - 1);
+        result.phi *= lower * (p - 1);
+        // 1 + p + ... + p^e, built up term by term since p^(e+1) may not fit even when the sum
+        // of lower powers does.
+        let sum = (0..=e).try_fold((0u128, 1u128), |(sum, term), i| {
+            let sum = sum.checked_add(term)?;
+            let term = if i < e { term.checked_mul(p)? } else { term };
+            Some((sum, term))
+        });
+        result.sigma = match (result.sigma, sum) {
+            (Some(sigma), Some((sum, _))) => sigma.checked_mul(sum),
+            _ => None,
+        };
+        result.divisors *= u128::from(e) + 1;
+    }
+    Some(result)
+}
@@ -0,0 +1,158 @@
+use crate::primes::factor::{self, Method};
+use crate::primes::primality::{self, Primality, Witnesses};
+use crate::primes::sieve;
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use std::convert::TryFrom;
+
+// Small primes are divided out by trial division before Pollard's rho takes over, since rho is at
+// its worst finding them.
+const TRIAL_DIVISION_LIMIT: u64 = 10_000;
+
+// Pollard's rho multiplies this many differences together between gcds, trading an occasional
+// wasted batch for far fewer gcds.
+const BATCH: u64 = 128;
+
+// Miller-Rabin rounds used to spot when a cofactor is prime.
+const ROUNDS: usize = 20;
+
+// Like primality::is_prime_with, but for numbers of any size. Anything that fits in a u128 is
+// passed straight through, and anything bigger is tested against the first `rounds` primes, or
+// against `rounds` random bases.
+pub fn is_prime(n: &BigUint, rounds: usize, witnesses: Witnesses) -> Primality {
+    if let Ok(n) = u128::try_from(n) {
+        return primality::is_prime_with(n, rounds, witnesses);
+    }
+    if n.is_even() {
+        return Primality::Composite;
+    }
+    let one = BigUint::one();
+    let minus_one = n - &one;
+    let s = minus_one.trailing_zeros().unwrap_or(0);
+    let d = &minus_one >> s;
+
+    let is_witness = |base: &BigUint| {
+        let mut x = base.modpow(&d, n);
+        if x == one || x == minus_one {
+            return false;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == minus_one {
+                return false;
+            }
+        }
+        true
+    };
+    let composite = match witnesses {
+        // There's no deterministic answer this large, but primality::is_prime always tries its
+        // 12 deterministic bases, so we try at least as many.
+        Witnesses::Fixed => sieve::primes(1 << 16)
+            .take(rounds.max(12))
+            .any(|base| is_witness(&BigUint::from(base))),
+        Witnesses::Random => {
+            let mut rng = rand::thread_rng();
+            let low = BigUint::from(2u32);
+            (0..rounds).any(|_| is_witness(&rng.gen_biguint_range(&low, &minus_one)))
+        }
+    };
+    if composite {
+        return Primality::Composite;
+    }
+    Primality::ProbablePrime
+}
+
+// Like factor::factor, but for numbers of any size, in ascending order. Small factors come out by
+// trial division, and the rest by Pollard's rho, which takes time on the order of the square root
+// of the smallest factor left. That's fine for numbers with hundreds of digits as long as only one
+// of their factors is big.
+//
+// Beyond a u128, rho is the only method there is, so asking for any other gives None.
+pub fn factor(n: &BigUint, method: Method) -> Option<Vec<BigUint>> {
+    if let Ok(n) = u128::try_from(n) {
+        let factors = factor::factor_with(n, method);
+        return Some(factors.into_iter().map(BigUint::from).collect());
+    }
+    if !matches!(method, Method::Auto | Method::Rho) {
+        return None;
+    }
+    let mut factors = Vec::new();
+    let mut n = n.clone();
+    for p in sieve::primes(TRIAL_DIVISION_LIMIT) {
+        let p = BigUint::from(p);
+        while (&n % &p).is_zero() {
+            n /= &p;
+            factors.push(p.clone());
+        }
+    }
+    split(n, &mut factors);
+    factors.sort();
+    Some(factors)
+}
+
+// Pushes the prime factors of n, which has no factors below TRIAL_DIVISION_LIMIT, onto factors.
+fn split(n: BigUint, factors: &mut Vec<BigUint>) {
+    if n.is_one() {
+        return;
+    }
+    if is_prime(&n, ROUNDS, Witnesses::Fixed) != Primality::Composite {
+        factors.push(n);
+        return;
+    }
+    // Each polynomial x^2 + c either finds a factor or cycles without one, in which case the next
+    // one gets a go.
+    let divisor = (1u32..)
+        .find_map(|c| rho(&n, &BigUint::from(c)))
+        .expect("every composite has a factor");
+    let cofactor = &n / &divisor;
+    split(divisor, factors);
+    split(cofactor, factors);
+}
+
+// Brent's variant of Pollard's rho: a proper factor of the composite n, or None if x^2 + c cycled
+// around without finding one.
+fn rho(n: &BigUint, c: &BigUint) -> Option<BigUint> {
+    let f = |x: &BigUint| (x * x + c) % n;
+    let distance = |a: &BigUint, b: &BigUint| if a > b { a - b } else { b - a };
+
+    let mut y = BigUint::from(2u32);
+    let mut x = y.clone();
+    let mut saved = y.clone();
+    let mut product = BigUint::one();
+    let mut g = BigUint::one();
+    let mut r: u64 = 1;
+    while g.is_one() {
+        x = y.clone();
+        for _ in 0..r {
+            y = f(&y);
+        }
+        let mut k = 0;
+        while k < r && g.is_one() {
+            saved = y.clone();
+            for _ in 0..BATCH.min(r - k) {
+                y = f(&y);
+                product = product * distance(&x, &y) % n;
+            }
+            g = product.gcd(n);
+            k += BATCH;
+        }
+        r *= 2;
+    }
+    // The batch overshot and multiplied in every factor at once, so step back through it one at a
+    // time.
+    if &g == n {
+        loop {
+            saved = f(&saved);
+            g = distance(&x, &saved).gcd(n);
+            if !g.is_one() {
+                break;
+            }
+        }
+    }
+    if &g == n {
+        None
+    } else {
+        Some(g)
+    }
+}
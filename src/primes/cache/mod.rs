@@ -0,0 +1,123 @@
+use crate::primes::memory::Bytes;
+use crate::primes::{segmented, Generator};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Identifies a cache file, and its version, so that anything else that happens to be there is
+// ignored rather than trusted.
+const MAGIC: &[u8; 8] = b"PRIMEBM1";
+
+// Which numbers up to max are prime, a bit each. It's what --cache-dir keeps between runs, since
+// loading it is far quicker than sieving again.
+pub struct Bitmap {
+    max: u64,
+    bits: Vec<u8>,
+}
+
+// How many bytes a bitmap covering max takes, in memory or on disk.
+pub fn footprint(max: u64) -> Bytes {
+    Bytes(max / 8 + 1)
+}
+
+// Where the cache lives in a cache directory. There's only ever one, for the largest max so far,
+// since it answers for every smaller max too.
+pub fn path(dir: &Path) -> PathBuf {
+    dir.join("primes.bitmap")
+}
+
+impl Bitmap {
+    // Builds a bitmap with the segmented sieve, so the only big allocation is the bitmap itself.
+    pub fn sieve(max: u64, segment_size: u64) -> Self {
+        let mut bits = vec![0; (max / 8 + 1) as usize];
+        for p in segmented::primes(max, segment_size) {
+            bits[(p / 8) as usize] |= 1 << (p % 8);
+        }
+        Bitmap { max, bits }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn contains(&self, n: u64) -> bool {
+        n <= self.max && self.bits[(n / 8) as usize] & (1 << (n % 8)) != 0
+    }
+
+    // A cache that isn't there, is from something else, or is cut short is no use, but it's not
+    // an error either since we can always sieve again, so all of those are None.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = match File::open(path) {
+            Ok(file) => BufReader::new(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut header = [0; 16];
+        if file.read_exact(&mut header).is_err() || &header[..8] != MAGIC {
+            return Ok(None);
+        }
+        let mut max = [0; 8];
+        max.copy_from_slice(&header[8..]);
+        let max = u64::from_le_bytes(max);
+        let mut bits = Vec::new();
+        file.read_to_end(&mut bits)?;
+        if bits.len() as u64 != max / 8 + 1 {
+            return Ok(None);
+        }
+        Ok(Some(Bitmap { max, bits }))
+    }
+
+    // Like checkpoints, written to a temporary file and renamed into place, so an interrupted save
+    // never leaves a truncated cache for the next run to find.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&self.max.to_le_bytes())?;
+        file.write_all(&self.bits)?;
+        file.flush()?;
+        drop(file);
+        fs::rename(&tmp, path)
+    }
+
+    // The primes up to max, which may be less than what the bitmap covers.
+    pub fn primes(self, max: u64) -> Box<dyn Generator> {
+        Box::new(Primes {
+            max: max.min(self.max),
+            next: 0,
+            bitmap: self,
+        })
+    }
+}
+
+pub struct Primes {
+    max: u64,
+    next: u64,
+    bitmap: Bitmap,
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        while self.next <= self.max {
+            let n = self.next;
+            self.next = n + 1;
+            if self.bitmap.contains(n) {
+                return Some(n);
+            }
+        }
+        None
+    }
+}
+
+impl Generator for Primes {
+    fn cursor(&self) -> u64 {
+        self.next
+    }
+
+    fn limit(&self) -> u64 {
+        self.max
+    }
+}
@@ -0,0 +1,92 @@
+use crate::primes::error::{Error, Result};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Everything needed to pick a segmented sieve back up where it left off. The file is plain text,
+// one "key value..." line per field, so it's easy to inspect by hand:
+//
+//     max 1000000
+//     next 524288
+//     written 3059184
+//     base 2 3 5 7 ...
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub max: u64,
+    pub next: u64,
+    // How many bytes of primes had been written to the output file when it was saved, so the
+    // file can be cut back to that on resuming. There's none when they were going to stdout.
+    pub written: Option<u64>,
+    pub base: Vec<u64>,
+}
+
+impl Checkpoint {
+    // We write to a temporary file and rename it into place, so being interrupted mid-save can
+    // never leave a truncated checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        self.write(&tmp)
+            .map_err(Error::io(format!("writing checkpoint {}", tmp.display())))?;
+        fs::rename(&tmp, path).map_err(Error::io(format!(
+            "renaming checkpoint into place at {}",
+            path.display()
+        )))
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "max {}", self.max)?;
+        writeln!(file, "next {}", self.next)?;
+        if let Some(written) = self.written {
+            writeln!(file, "written {}", written)?;
+        }
+        write!(file, "base")?;
+        for p in &self.base {
+            write!(file, " {}", p)?;
+        }
+        writeln!(file)?;
+        file.flush()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let context = || format!("reading checkpoint {}", path.display());
+        let invalid = |message: String| Error::Checkpoint {
+            path: path.to_owned(),
+            message,
+        };
+        let parse = |value: Option<&str>, name: &str| {
+            value
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| invalid(format!("invalid {} field", name)))
+        };
+
+        let file = BufReader::new(File::open(path).map_err(Error::io(context()))?);
+        let (mut max, mut next, mut written, mut base) = (None, None, None, None);
+        for line in file.lines() {
+            let line = line.map_err(Error::io(context()))?;
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("max") => max = Some(parse(fields.next(), "max")?),
+                Some("next") => next = Some(parse(fields.next(), "next")?),
+                Some("written") => written = Some(parse(fields.next(), "written")?),
+                Some("base") => {
+                    base = Some(
+                        fields
+                            .map(|p| parse(Some(p), "base"))
+                            .collect::<Result<Vec<_>>>()?,
+                    )
+                }
+                Some(key) => return Err(invalid(format!("unknown field {}", key))),
+                None => {}
+            }
+        }
+        Ok(Checkpoint {
+            max: max.ok_or_else(|| invalid("missing max".to_string()))?,
+            next: next.ok_or_else(|| invalid("missing next".to_string()))?,
+            written,
+            base: base.ok_or_else(|| invalid("missing base".to_string()))?,
+        })
+    }
+}
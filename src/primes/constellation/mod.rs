@@ -0,0 +1,119 @@
+use crate::primes::error::Error;
+use crate::primes::Generator;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+// The offsets of a prime constellation from its first prime, e.g. 0,2 for twin primes, 0,4 for
+// cousins, 0,6 for sexy primes and 0,2,6,8 for quadruplets. They're kept sorted and always start
+// at 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    offsets: Vec<u64>,
+}
+
+impl Pattern {
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    // How far the last prime of a match is past the first.
+    pub fn span(&self) -> u64 {
+        self.offsets.last().copied().unwrap_or(0)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Parse {
+            what: "pattern",
+            value: s.to_string(),
+        };
+        let mut offsets = s
+            .split(',')
+            .map(|o| o.trim().parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<u64>, Error>>()?;
+        offsets.sort_unstable();
+        offsets.dedup();
+        // Without a 0 there's no prime for the match to start at.
+        if offsets.first() != Some(&0) {
+            return Err(invalid());
+        }
+        Ok(Pattern { offsets })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offsets: Vec<String> = self.offsets.iter().map(|o| o.to_string()).collect();
+        write!(f, "{}", offsets.join(","))
+    }
+}
+
+// Passes on each prime p for which p + offset is also prime for every offset in the pattern. It
+// keeps a window of the primes from p to p + span, so it only ever looks at each prime once however
+// long the pattern is, and like Filter it's still a generator. A match has to fit below the inner
+// generator's limit, since the primes past it are never seen.
+pub struct Constellations {
+    inner: Box<dyn Generator>,
+    pattern: Pattern,
+    window: VecDeque<u64>,
+    exhausted: bool,
+}
+
+impl Constellations {
+    pub fn new(inner: Box<dyn Generator>, pattern: Pattern) -> Self {
+        Constellations {
+            inner,
+            pattern,
+            window: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    // Pulls primes into the window until it reaches past its first prime plus the span.
+    fn fill(&mut self) {
+        while !self.exhausted {
+            let reach = match (self.window.front(), self.window.back()) {
+                (Some(&first), Some(&last)) => last.saturating_sub(first) > self.pattern.span(),
+                _ => false,
+            };
+            if reach {
+                return;
+            }
+            match self.inner.next() {
+                Some(p) => self.window.push_back(p),
+                None => self.exhausted = true,
+            }
+        }
+    }
+}
+
+impl Iterator for Constellations {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            self.fill();
+            let p = self.window.front().copied()?;
+            let matches = self.pattern.offsets()[1..].iter().all(|&offset| {
+                p.checked_add(offset)
+                    .is_some_and(|q| self.window.binary_search(&q).is_ok())
+            });
+            self.window.pop_front();
+            if matches {
+                return Some(p);
+            }
+        }
+    }
+}
+
+impl Generator for Constellations {
+    fn cursor(&self) -> u64 {
+        self.inner.cursor()
+    }
+
+    fn limit(&self) -> u64 {
+        self.inner.limit()
+    }
+}
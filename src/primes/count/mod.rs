@@ -0,0 +1,110 @@
+use crate::primes::memory::Bytes;
+use std::mem;
+
+// PI_POWERS_OF_TEN[k] is pi(10^k), the number of primes up to 10^k, for every power of 10 that
+// fits in a u64. They're long established, so anything that disagrees with them is wrong.
+pub const PI_POWERS_OF_TEN: [u64; 20] = [
+    0,
+    4,
+    25,
+    168,
+    1_229,
+    9_592,
+    78_498,
+    664_579,
+    5_761_455,
+    50_847_534,
+    455_052_511,
+    4_118_054_813,
+    37_607_912_018,
+    346_065_536_839,
+    3_204_941_750_802,
+    29_844_570_422_669,
+    279_238_341_033_925,
+    2_623_557_157_654_233,
+    24_739_954_287_740_860,
+    234_057_667_276_344_607,
+];
+
+// Roughly how much memory counting up to x needs: S for the small and the large values, each
+// isqrt(x) + 1 of them.
+pub fn footprint(x: u64) -> Bytes {
+    let values = (x.isqrt() + 1).saturating_mul(2);
+    Bytes(values.saturating_mul(mem::size_of::<u64>() as u64))
+}
+
+// Counts the primes up to x without enumerating them, using Lucy_Hedgehog's method. S(v) starts
+// out as the count of every number in 2..=v, and for each prime p in turn we subtract the numbers
+// whose smallest prime factor is p. Only values of the form x / i are ever needed, and there are
+// only about 2 * sqrt(x) of them, so we keep S for the small values (v <= sqrt(x)) indexed by v,
+// and S for the large ones (x / i) indexed by i. That gets us to roughly x^(3/4) time and sqrt(x)
+// space, which makes x around 10^12 a matter of seconds.
+pub fn count(x: u64) -> u64 {
+    if x < 2 {
+        return 0;
+    }
+    let r = x.isqrt() as usize;
+
+    let mut small: Vec<u64> = (0..=r as u64).map(|v| v.saturating_sub(1)).collect();
+    // large[0] would be S(x / 0), which we never use, so it's left as 0.
+    let mut large: Vec<u64> = (0..=r as u64)
+        .map(|i| x.checked_div(i).map_or(0, |v| v - 1))
+        .collect();
+
+    for p in 2..=r {
+        // S only drops at p if p survived every smaller prime, i.e. if p is prime.
+        if small[p] == small[p - 1] {
+            continue;
+        }
+        let primes_below = small[p - 1];
+        let p2 = (p as u64) * (p as u64);
+
+        for i in 1..=r {
+            let v = x / i as u64;
+            if v < p2 {
+                break;
+            }
+            let ip = i * p;
+            let s_v_over_p = if ip <= r {
+                large[ip]
+            } else {
+                small[(x / ip as u64) as usize]
+            };
+            large[i] -= s_v_over_p - primes_below;
+        }
+
+        for v in (p2 as usize..=r).rev() {
+            small[v] -= small[v / p] - primes_below;
+        }
+    }
+    large[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn powers_of_ten() {
+        for (k, &expected) in PI_POWERS_OF_TEN.iter().enumerate().take(9) {
+            assert_eq!(count(10u64.pow(k as u32)), expected, "10^{}", k);
+        }
+    }
+
+    // Either side of a prime, and on it.
+    #[test]
+    fn around_primes() {
+        assert_eq!([count(0), count(1), count(2), count(3)], [0, 0, 1, 2]);
+        assert_eq!([count(96), count(97), count(98)], [24, 25, 25]);
+        let pi = PI_POWERS_OF_TEN[6];
+        assert_eq!(
+            [count(1_000_002), count(1_000_003), count(1_000_004)],
+            [pi, pi + 1, pi + 1]
+        );
+        let pi = PI_POWERS_OF_TEN[8];
+        assert_eq!(
+            [count(99_999_988), count(99_999_989), count(99_999_990)],
+            [pi - 1, pi, pi]
+        );
+    }
+}
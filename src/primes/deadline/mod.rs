@@ -0,0 +1,79 @@
+use crate::primes::Generator;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// How far a generator had got when its time ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reached {
+    // How many primes it had handed out.
+    pub count: u64,
+    // The last of them, unless there weren't any.
+    pub largest: Option<u64>,
+}
+
+// Like Limit, but for time rather than primes: once the budget has passed, it stops as though it
+// had run out of range. That looks just like finishing to whatever's downstream, so the Expiry
+// that comes with it is how to tell the difference afterwards.
+pub struct Deadline {
+    inner: Box<dyn Generator>,
+    until: Instant,
+    count: u64,
+    largest: Option<u64>,
+    expired: Rc<Cell<Option<Reached>>>,
+}
+
+// Whether a Deadline stopped early, and if so how far it got.
+pub struct Expiry(Rc<Cell<Option<Reached>>>);
+
+impl Expiry {
+    pub fn reached(&self) -> Option<Reached> {
+        self.0.get()
+    }
+}
+
+impl Deadline {
+    pub fn new(inner: Box<dyn Generator>, budget: Duration) -> (Self, Expiry) {
+        let expired = Rc::new(Cell::new(None));
+        let deadline = Deadline {
+            inner,
+            until: Instant::now() + budget,
+            count: 0,
+            largest: None,
+            expired: expired.clone(),
+        };
+        (deadline, Expiry(expired))
+    }
+}
+
+impl Iterator for Deadline {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        if self.expired.get().is_some() {
+            return None;
+        }
+        // Checked before asking for the next prime rather than after, so the one that took us
+        // over the budget isn't thrown away.
+        if Instant::now() >= self.until {
+            self.expired.set(Some(Reached {
+                count: self.count,
+                largest: self.largest,
+            }));
+            return None;
+        }
+        let p = self.inner.next()?;
+        self.count += 1;
+        self.largest = Some(p);
+        Some(p)
+    }
+}
+
+impl Generator for Deadline {
+    fn cursor(&self) -> u64 {
+        self.inner.cursor()
+    }
+
+    fn limit(&self) -> u64 {
+        self.inner.limit()
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::IoError;
+use crate::primes::memory::Bytes;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+// Every way primes can fail. Each kind of failure exits with its own code, so scripts can tell a
+// bad command line from a full disk without scraping stderr.
+#[derive(Debug, Error)]
+pub enum Error {
+    // An argument parsed, but its value can't be used.
+    #[error("{argument} {message}")]
+    Argument {
+        argument: &'static str,
+        message: String,
+    },
+
+    // A value that doesn't parse at all, e.g. an unknown algorithm name.
+    #[error("invalid {what}: {value}")]
+    Parse { what: &'static str, value: String },
+
+    // hint is appended to the message, to suggest a way to need less memory, if there is one.
+    #[error(
+        "{argument} needs {needed} of memory, more than the --max-memory limit of {limit}{hint}"
+    )]
+    MemoryLimit {
+        argument: &'static str,
+        needed: Bytes,
+        limit: Bytes,
+        hint: &'static str,
+    },
+
+    #[error("{argument} needs {needed} of memory, but only {available} is available{hint}")]
+    MemoryAvailable {
+        argument: &'static str,
+        needed: Bytes,
+        available: Bytes,
+        hint: &'static str,
+    },
+
+    #[error(transparent)]
+    Io(#[from] IoError),
+
+    #[error("invalid checkpoint {}: {message}", path.display())]
+    Checkpoint { path: PathBuf, message: String },
+
+    #[error("prime #{index} differs: {reference} gave {expected}, {algorithm} gave {actual}")]
+    Verify {
+        index: u64,
+        reference: String,
+        expected: String,
+        algorithm: String,
+        actual: String,
+    },
+
+    #[error("{algorithm} counted {actual} primes up to 10^{power}, but there are {expected}")]
+    SelfTest {
+        algorithm: String,
+        power: u32,
+        expected: u64,
+        actual: u64,
+    },
+
+    // --max-seconds ran out before the range did. Whatever was found by then has been written.
+    #[error(
+        "--max-seconds {seconds} ran out after {count} primes{}",
+        largest.map_or(String::new(), |p| format!(", the largest {}", p))
+    )]
+    OutOfTime {
+        seconds: f64,
+        count: u64,
+        largest: Option<u64>,
+    },
+
+    // The question was fine, it just has no answer.
+    #[error("{0}")]
+    NoAnswer(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // For use with map_err, to say what we were doing when an io::Error happened.
+    pub fn io<C: Into<String>>(context: C) -> impl FnOnce(io::Error) -> Error {
+        move |source| Error::Io(IoError::new(context, source))
+    }
+
+    // Adds a hint to a memory error, e.g. " (try ...)". Other errors are left alone.
+    pub fn hint(mut self, hint: &'static str) -> Self {
+        if let Error::MemoryLimit { hint: h, .. } | Error::MemoryAvailable { hint: h, .. } =
+            &mut self
+        {
+            *h = hint;
+        }
+        self
+    }
+
+    // clap already uses 1 for the errors it catches itself, so ours start at 2.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Argument { .. } | Error::Parse { .. } => 2,
+            Error::MemoryLimit { .. } | Error::MemoryAvailable { .. } => 3,
+            Error::Io(_) => 4,
+            Error::Checkpoint { .. } => 5,
+            Error::Verify { .. } | Error::SelfTest { .. } => 6,
+            Error::NoAnswer(_) => 7,
+            Error::OutOfTime { .. } => 8,
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use crate::primes::error::Error;
+use crate::primes::primality::{add_mod, is_prime, mul_mod, Primality};
+use crate::primes::sieve;
+use std::fmt;
+use std::str::FromStr;
+
+// Miller-Rabin rounds used to spot when what's left is prime.
+const ROUNDS: usize = 20;
+
+// Before rho or ECM get a go, primes below this are divided out by trial division, since they're
+// cheaper to find that way.
+const SMALL_PRIMES: u64 = 1 << 12;
+
+// Pollard's rho multiplies this many differences together between gcds, trading an occasional
+// wasted batch for far fewer gcds.
+const BATCH: u64 = 128;
+
+// How long auto lets rho run before trying ECM. Rho needs around the square root of the smallest
+// factor in steps, so this finds factors of up to about 10 digits, which is most of them. Past
+// that, ECM usually gets there first.
+const RHO_STEPS: u64 = 1 << 16;
+
+// ECM stage 1 bounds and how many curves to try at each, from the standard tables for factors of
+// roughly 15, 20 and 25 digits. The last is as far as anything fitting in a u128 needs.
+const ECM_SCHEDULE: [(u64, u32); 3] = [(2_000, 25), (11_000, 90), (50_000, 300)];
+
+// How factor finds factors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    // Rho, after dividing out the small primes, for up to RHO_STEPS. Then ECM, and if that
+    // can't split what's left either, rho again for as long as it takes.
+    Auto,
+    // Dividing by every odd number in turn. Hopeless once there are two large factors.
+    Trial,
+    // Brent's variant of Pollard's rho, which takes time on the order of the square root of the
+    // smallest factor.
+    Rho,
+    // The elliptic curve method's first stage, falling back on rho for whatever it can't split.
+    // Its running time depends on the size of the smallest factor rather than of n, so it's the
+    // one to use when n's factors are very lopsided.
+    Ecm,
+}
+
+impl FromStr for Method {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Method::Auto),
+            "trial" => Ok(Method::Trial),
+            "rho" => Ok(Method::Rho),
+            "ecm" => Ok(Method::Ecm),
+            _ => Err(Error::Parse {
+                what: "factoring algorithm",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Auto => write!(f, "auto"),
+            Method::Trial => write!(f, "trial"),
+            Method::Rho => write!(f, "rho"),
+            Method::Ecm => write!(f, "ecm"),
+        }
+    }
+}
+
+// The prime factors of n in ascending order, with repeats, e.g. 360 gives 2 2 2 3 3 5.
+pub fn factor(n: u128) -> Vec<u128> {
+    factor_with(n, Method::Auto)
+}
+
+pub fn factor_with(n: u128, method: Method) -> Vec<u128> {
+    if method == Method::Trial {
+        return trial(n);
+    }
+    let mut factors = Vec::new();
+    let mut n = n;
+    if n < 2 {
+        return factors;
+    }
+    for p in sieve::primes(SMALL_PRIMES) {
+        let p = u128::from(p);
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    split(n, method, &mut factors);
+    factors.sort_unstable();
+    factors
+}
+
+// Trial division. We pull out 2 and then only try odd divisors, stopping once the divisor squared
+// passes what's left, since whatever remains at that point has to be prime. We also stop as soon
+// as Miller-Rabin says the remainder is prime, otherwise a large prime factor would have us
+// dividing until the heat death of the universe.
+fn trial(n: u128) -> Vec<u128> {
+    let mut factors = Vec::new();
+    let mut n = n;
+    if n < 2 {
+        return factors;
+    }
+    while n.is_multiple_of(2) {
+        factors.push(2);
+        n /= 2;
+    }
+    let mut divisor: u128 = 3;
+    // checked_mul guards the square against overflow for inputs near u128::MAX.
+    let mut remainder_prime = n == 1 || is_prime(n, ROUNDS) != Primality::Composite;
+    while !remainder_prime && divisor.checked_mul(divisor).is_some_and(|sq| sq <= n) {
+        if n.is_multiple_of(divisor) {
+            while n.is_multiple_of(divisor) {
+                factors.push(divisor);
+                n /= divisor;
+            }
+            remainder_prime = n == 1 || is_prime(n, ROUNDS) != Primality::Composite;
+        }
+        divisor += 2;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+// Pushes the prime factors of n, which has none below SMALL_PRIMES, onto factors.
+fn split(n: u128, method: Method, factors: &mut Vec<u128>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n, ROUNDS) != Primality::Composite {
+        factors.push(n);
+        return;
+    }
+    let divisor = match method {
+        Method::Auto => rho_with(n, 1, RHO_STEPS)
+            .or_else(|| ecm(n))
+            .or_else(|| rho(n)),
+        Method::Ecm => ecm(n).or_else(|| rho(n)),
+        _ => rho(n),
+    };
+    // rho tries polynomial after polynomial, and one of them always finds a factor.
+    let divisor = divisor.expect("every composite has a factor");
+    split(divisor, method, factors);
+    split(n / divisor, method, factors);
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn distance(a: u128, b: u128) -> u128 {
+    a.abs_diff(b)
+}
+
+fn sub_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    if a >= b {
+        a - b
+    } else {
+        modulus - (b - a)
+    }
+}
+
+// A proper factor of the composite n by Brent's variant of Pollard's rho, trying x^2 + c for one c
+// after another until one doesn't cycle without finding anything.
+fn rho(n: u128) -> Option<u128> {
+    (1..n).find_map(|c| rho_with(n, c, u64::MAX))
+}
+
+// Rho with x^2 + c, giving up, with None, once it's gone about steps without finding anything.
+fn rho_with(n: u128, c: u128, steps: u64) -> Option<u128> {
+    let f = |x: u128| add_mod(mul_mod(x, x, n), c % n, n);
+
+    let mut y = 2 % n;
+    let mut x = y;
+    let mut saved = y;
+    let mut product = 1;
+    let mut g = 1;
+    let mut r: u64 = 1;
+    while g == 1 {
+        if r > steps {
+            return None;
+        }
+        x = y;
+        for _ in 0..r {
+            y = f(y);
+        }
+        let mut k = 0;
+        while k < r && g == 1 {
+            saved = y;
+            for _ in 0..BATCH.min(r - k) {
+                y = f(y);
+                product = mul_mod(product, distance(x, y), n);
+            }
+            g = gcd(product, n);
+            k += BATCH;
+        }
+        r *= 2;
+    }
+    // The batch overshot and multiplied in every factor at once, so step back through it one at a
+    // time.
+    if g == n {
+        loop {
+            saved = f(saved);
+            g = gcd(distance(x, saved), n);
+            if g != 1 {
+                break;
+            }
+        }
+    }
+    if g == n {
+        None
+    } else {
+        Some(g)
+    }
+}
+
+// The modular inverse of a mod n, or the factor of n it shares with a when there isn't one, which
+// for ECM is just as good.
+fn inverse(a: u128, n: u128) -> Result<u128, u128> {
+    // Extended Euclid, with the coefficients kept reduced mod n so they never go negative.
+    let (mut r0, mut r1) = (n, a % n);
+    let (mut t0, mut t1) = (0, 1);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, sub_mod(t0, mul_mod(q % n, t1, n), n));
+    }
+    if r0 == 1 {
+        Ok(t0)
+    } else {
+        Err(r0)
+    }
+}
+
+// A point on a Montgomery curve, in projective X:Z coordinates, which let us multiply points
+// without ever dividing.
+#[derive(Clone, Copy)]
+struct Point {
+    x: u128,
+    z: u128,
+}
+
+struct Curve {
+    n: u128,
+    // (A + 2) / 4, for the curve B y^2 = x^3 + A x^2 + x.
+    a24: u128,
+}
+
+impl Curve {
+    fn double(&self, p: Point) -> Point {
+        let n = self.n;
+        let sum = add_mod(p.x, p.z, n);
+        let diff = sub_mod(p.x, p.z, n);
+        let sum2 = mul_mod(sum, sum, n);
+        let diff2 = mul_mod(diff, diff, n);
+        let cross = sub_mod(sum2, diff2, n);
+        Point {
+            x: mul_mod(sum2, diff2, n),
+            z: mul_mod(cross, add_mod(diff2, mul_mod(self.a24, cross, n), n), n),
+        }
+    }
+
+    // p + q, given p - q.
+    fn add(&self, p: Point, q: Point, difference: Point) -> Point {
+        let n = self.n;
+        let u = mul_mod(sub_mod(p.x, p.z, n), add_mod(q.x, q.z, n), n);
+        let v = mul_mod(add_mod(p.x, p.z, n), sub_mod(q.x, q.z, n), n);
+        let plus = add_mod(u, v, n);
+        let minus = sub_mod(u, v, n);
+        Point {
+            x: mul_mod(difference.z, mul_mod(plus, plus, n), n),
+            z: mul_mod(difference.x, mul_mod(minus, minus, n), n),
+        }
+    }
+
+    // k p, by the Montgomery ladder.
+    fn multiply(&self, k: u64, p: Point) -> Point {
+        let mut low = p;
+        let mut high = self.double(p);
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if k & (1 << bit) != 0 {
+                low = self.add(high, low, p);
+                high = self.double(high);
+            } else {
+                high = self.add(high, low, p);
+                low = self.double(low);
+            }
+        }
+        low
+    }
+}
+
+// A proper factor of the composite n by ECM stage 1, or None if no curve in the schedule finds one.
+fn ecm(n: u128) -> Option<u128> {
+    let mut sigma: u128 = 6;
+    for &(bound, curves) in &ECM_SCHEDULE {
+        let primes: Vec<u64> = sieve::primes(bound).collect();
+        for _ in 0..curves {
+            sigma += 1;
+            match ecm_curve(n, sigma, bound, &primes) {
+                Some(g) if g != n => return Some(g),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+// One curve, chosen from sigma by Suyama's parametrisation, which guarantees its order has 12 as a
+// factor. Multiplying the point by every prime power up to bound finds p whenever the curve's
+// order mod p has no prime factors beyond bound.
+fn ecm_curve(n: u128, sigma: u128, bound: u64, primes: &[u64]) -> Option<u128> {
+    let m = |a, b| mul_mod(a, b, n);
+    let u = sub_mod(m(sigma, sigma), 5 % n, n);
+    let v = m(4, sigma);
+    let u3 = m(m(u, u), u);
+    let v_u = sub_mod(v, u, n);
+    let numerator = m(m(m(v_u, v_u), v_u), add_mod(m(3, u), v, n));
+    let denominator = m(m(16, u3), v);
+    let a24 = match inverse(denominator, n) {
+        Ok(inverse) => m(numerator, inverse),
+        // A curve we can't even set up has found a factor for us.
+        Err(g) => return Some(g),
+    };
+    let curve = Curve { n, a24 };
+    let start = Point {
+        x: u3,
+        z: m(m(v, v), v),
+    };
+    let powers = primes.iter().map(|&p| {
+        let mut power = p;
+        while power <= bound / p {
+            power *= p;
+        }
+        power
+    });
+    let point = powers
+        .clone()
+        .fold(start, |point, power| curve.multiply(power, point));
+    match gcd(point.z, n) {
+        1 => None,
+        // Every factor at once, which happens when n is small next to the bound. Going through
+        // the primes again, a gcd at a time, finds the first one to turn up on its own, if any.
+        g if g == n => {
+            let mut point = start;
+            for power in powers {
+                point = curve.multiply(power, point);
+                match gcd(point.z, n) {
+                    1 => {}
+                    g if g == n => return None,
+                    g => return Some(g),
+                }
+            }
+            None
+        }
+        g => Some(g),
+    }
+}
+
+// Groups a factorization (as returned by factor) into (prime, exponent) pairs.
+pub fn powers(factors: &[u128]) -> Vec<(u128, u32)> {
+    let mut powers: Vec<(u128, u32)> = Vec::new();
+    for &f in factors {
+        match powers.last_mut() {
+            Some((p, e)) if *p == f => *e += 1,
+            _ => powers.push((f, 1)),
+        }
+    }
+    powers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: [Method; 4] = [Method::Auto, Method::Trial, Method::Rho, Method::Ecm];
+
+    // 2^61 - 1 and 2^127 - 1 are Mersenne primes, and 2^128 - 159 is the largest prime below 2^128.
+    const M61: u128 = (1 << 61) - 1;
+    const M127: u128 = (1 << 127) - 1;
+    const LARGEST: u128 = u128::MAX - 158;
+
+    #[test]
+    fn semiprimes() {
+        for method in METHODS {
+            assert_eq!(factor_with(10_007 * 10_009, method), [10_007, 10_009]);
+        }
+        // Too big a factor for trial division to find in a reasonable time.
+        for method in [Method::Auto, Method::Rho, Method::Ecm] {
+            let (p, q) = (998_244_353, 1_000_000_007);
+            assert_eq!(factor_with(p * q, method), [p, q], "{}", method);
+            assert_eq!(factor_with(p * M61, method), [p, M61], "{}", method);
+        }
+    }
+
+    #[test]
+    fn edges() {
+        let all_ones = vec![
+            3,
+            5,
+            17,
+            257,
+            641,
+            65_537,
+            274_177,
+            6_700_417,
+            67_280_421_310_721,
+        ];
+        for method in METHODS {
+            assert_eq!(factor_with(0, method), [], "{}", method);
+            assert_eq!(factor_with(1, method), [], "{}", method);
+            assert_eq!(factor_with(97, method), [97], "{}", method);
+            assert_eq!(factor_with(LARGEST, method), [LARGEST], "{}", method);
+            assert_eq!(factor_with(u128::MAX - 1, method), [2, M127], "{}", method);
+            assert_eq!(factor_with(u128::MAX, method), all_ones, "{}", method);
+        }
+    }
+
+    #[test]
+    fn rho_and_ecm_find_proper_factors() {
+        let n = 998_244_353 * 1_000_000_007;
+        for divisor in [rho(n), ecm(n)] {
+            let divisor = divisor.unwrap();
+            assert!(divisor > 1 && divisor < n && n % divisor == 0);
+        }
+        // One step isn't enough to find anything.
+        assert_eq!(rho_with(n, 1, 1), None);
+    }
+
+    #[test]
+    fn inverses() {
+        assert_eq!(inverse(3, 7), Ok(5));
+        assert_eq!(inverse(10, 7), Ok(5));
+        let a = 123_456_789_123_456_789;
+        let inverse = inverse(a, M127).unwrap();
+        assert_eq!(mul_mod(a, inverse, M127), 1);
+    }
+
+    #[test]
+    fn inverses_of_shared_factors() {
+        assert_eq!(inverse(6, 9), Err(3));
+        assert_eq!(inverse(10, 25), Err(5));
+        assert_eq!(inverse(M61 * 3, M61 * 5), Err(M61));
+        assert_eq!(inverse(0, 35), Err(35));
+    }
+
+    // The same point, whatever its Z, in projective coordinates.
+    fn same(curve: &Curve, p: Point, q: Point) -> bool {
+        mul_mod(p.x, q.z, curve.n) == mul_mod(q.x, p.z, curve.n)
+    }
+
+    #[test]
+    fn multiplying_points() {
+        let curve = Curve {
+            n: M127,
+            a24: 1_234_567,
+        };
+        let p = Point { x: 9, z: 1 };
+        let twice = curve.double(p);
+        let thrice = curve.add(twice, p, p);
+        assert!(same(&curve, curve.multiply(1, p), p));
+        assert!(same(&curve, curve.multiply(2, p), twice));
+        assert!(same(&curve, curve.multiply(3, p), thrice));
+        let six = curve.multiply(6, p);
+        assert!(same(&curve, curve.multiply(2, thrice), six));
+        assert!(same(&curve, curve.multiply(3, twice), six));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::primes::format::Radix;
+use crate::primes::primality::{is_prime, Primality};
+use crate::primes::Generator;
+
+// Miller-Rabin rounds for companions of primes too large for the deterministic bases.
+const ROUNDS: usize = 20;
+
+// Only passes on the primes that keep says to, but is still a generator, so progress, limits and
+// checkpoints carry on working on top of it. Filters stack by wrapping one in another.
+pub struct Filter {
+    inner: Box<dyn Generator>,
+    keep: Box<dyn Fn(u64) -> bool>,
+}
+
+impl Filter {
+    pub fn new<F: Fn(u64) -> bool + 'static>(inner: Box<dyn Generator>, keep: F) -> Self {
+        Filter {
+            inner,
+            keep: Box::new(keep),
+        }
+    }
+}
+
+// Keeps the primes congruent to residue mod modulus, e.g. (10, 7) for the ones ending in 7.
+pub fn residue(inner: Box<dyn Generator>, modulus: u64, residue: u64) -> Filter {
+    Filter::new(inner, move |p| p % modulus == residue)
+}
+
+// Keeps the primes that read the same backwards as forwards when written in radix.
+pub fn palindromic(inner: Box<dyn Generator>, radix: Radix) -> Filter {
+    let base = radix.base();
+    Filter::new(inner, move |p| {
+        let mut reversed: u128 = 0;
+        let mut rest = p;
+        while rest > 0 {
+            // Reversing can carry past a u64, e.g. for 18446744073709551557, so work in a u128.
+            reversed = reversed * u128::from(base) + u128::from(rest % base);
+            rest /= base;
+        }
+        reversed == u128::from(p)
+    })
+}
+
+// Keeps the Sophie Germain primes, the p for which 2p + 1 is prime too. That's usually past the
+// range being sieved, so it's checked with Miller-Rabin, which only needs a few modular
+// exponentiations per prime, and is deterministic unless 2p + 1 overflows a u64.
+pub fn sophie_germain(inner: Box<dyn Generator>) -> Filter {
+    Filter::new(inner, |p| {
+        is_prime(2 * u128::from(p) + 1, ROUNDS) != Primality::Composite
+    })
+}
+
+// Keeps the safe primes, the p for which (p - 1) / 2 is prime too, i.e. the 2q + 1 of a Sophie
+// Germain prime q.
+pub fn safe(inner: Box<dyn Generator>) -> Filter {
+    Filter::new(inner, |p| {
+        p > 2 && is_prime(u128::from((p - 1) / 2), ROUNDS) != Primality::Composite
+    })
+}
+
+impl Iterator for Filter {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        let keep = &self.keep;
+        self.inner.find(|&p| keep(p))
+    }
+}
+
+impl Generator for Filter {
+    fn cursor(&self) -> u64 {
+        self.inner.cursor()
+    }
+
+    fn limit(&self) -> u64 {
+        self.inner.limit()
+    }
+}
@@ -0,0 +1,261 @@
+use crate::primes::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+// The base primes get written out in. Decimal unless asked otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radix {
+    Binary,
+    Octal,
+    #[default]
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    pub fn base(self) -> u64 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hex => 16,
+        }
+    }
+
+    // Wraps n so that displaying it writes it in this radix.
+    pub fn display(self, n: u64) -> InRadix {
+        InRadix { radix: self, n }
+    }
+}
+
+impl FromStr for Radix {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2" => Ok(Radix::Binary),
+            "8" => Ok(Radix::Octal),
+            "10" => Ok(Radix::Decimal),
+            "16" => Ok(Radix::Hex),
+            _ => Err(Error::Parse {
+                what: "radix",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base())
+    }
+}
+
+// A number along with the radix to write it in. There's no prefix like 0x, since whatever reads
+// the output already knows which radix it asked for.
+pub struct InRadix {
+    radix: Radix,
+    n: u64,
+}
+
+impl fmt::Display for InRadix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.radix {
+            Radix::Binary => write!(f, "{:b}", self.n),
+            Radix::Octal => write!(f, "{:o}", self.n),
+            Radix::Decimal => write!(f, "{}", self.n),
+            Radix::Hex => write!(f, "{:x}", self.n),
+        }
+    }
+}
+
+// How primes are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    // One per line, in --radix.
+    #[default]
+    Text,
+    // Each prime as 8 little-endian bytes.
+    Binary,
+    // Each prime as the LEB128 varint of its difference from the one before, which for most gaps
+    // takes a single byte.
+    Varint,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "binary" => Ok(Format::Binary),
+            "varint" => Ok(Format::Varint),
+            _ => Err(Error::Parse {
+                what: "format",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Binary => write!(f, "binary"),
+            Format::Varint => write!(f, "varint"),
+        }
+    }
+}
+
+// One piece of a --template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    // Where the prime comes in the output, from 0.
+    Index,
+    Prime,
+    // How far the prime is past the one before it, or 0 for the first.
+    Gap,
+    // How many primes have been written, including this one.
+    Count,
+}
+
+// The shape of each line of text output, like "{index}: {prime} (gap {gap})". The prime and gap
+// are written in --radix, and {{ and }} stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl FromStr for Template {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Parse {
+            what: "template",
+            value: s.to_string(),
+        };
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(invalid()),
+                        }
+                    }
+                    let piece = match name.as_str() {
+                        "index" => Piece::Index,
+                        "prime" => Piece::Prime,
+                        "gap" => Piece::Gap,
+                        "count" => Piece::Count,
+                        _ => return Err(invalid()),
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(piece);
+                }
+                '}' => return Err(invalid()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template { pieces })
+    }
+}
+
+// Writes a stream of primes in a format. Varints are relative to the previous prime, so one
+// encoder has to see the whole stream, in order, and so do templates. Primes that aren't in order
+// can't be written as varints, and their gaps in templates are 0.
+pub struct Encoder {
+    format: Format,
+    radix: Radix,
+    template: Option<Template>,
+    previous: u64,
+    count: u64,
+}
+
+impl Encoder {
+    pub fn new(format: Format, radix: Radix) -> Self {
+        Encoder {
+            format,
+            radix,
+            template: None,
+            previous: 0,
+            count: 0,
+        }
+    }
+
+    // Writes text lines in the shape of template rather than just the prime.
+    pub fn template(mut self, template: Option<Template>) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn write<W: Write + ?Sized>(&mut self, out: &mut W, p: u64) -> io::Result<()> {
+        let previous = std::mem::replace(&mut self.previous, p);
+        let first = self.count == 0;
+        self.count += 1;
+        match self.format {
+            Format::Text => match self.template {
+                Some(ref template) => {
+                    for piece in &template.pieces {
+                        match piece {
+                            Piece::Literal(s) => write!(out, "{}", s)?,
+                            Piece::Index => write!(out, "{}", self.count - 1)?,
+                            Piece::Prime => write!(out, "{}", self.radix.display(p))?,
+                            Piece::Gap => {
+                                let gap = if first { 0 } else { p.saturating_sub(previous) };
+                                write!(out, "{}", self.radix.display(gap))?
+                            }
+                            Piece::Count => write!(out, "{}", self.count)?,
+                        }
+                    }
+                    writeln!(out)
+                }
+                None => writeln!(out, "{}", self.radix.display(p)),
+            },
+            Format::Binary => out.write_all(&p.to_le_bytes()),
+            Format::Varint => {
+                // The first delta is from 0 rather than nothing, so the prime itself.
+                let mut delta = p.checked_sub(previous).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{} comes after {}, so can't be a varint", p, previous),
+                    )
+                })?;
+                // Seven bits at a time, low bits first, with the top bit set on every byte but
+                // the last.
+                let mut bytes = [0; 10];
+                let mut len = 0;
+                loop {
+                    let byte = (delta & 0x7f) as u8;
+                    delta >>= 7;
+                    if delta == 0 {
+                        bytes[len] = byte;
+                        len += 1;
+                        break;
+                    }
+                    bytes[len] = byte | 0x80;
+                    len += 1;
+                }
+                out.write_all(&bytes[..len])
+            }
+        }
+    }
+}
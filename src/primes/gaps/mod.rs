@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+// The widest a histogram bar gets. Every other bar is scaled relative to the most common gap.
+const BAR_WIDTH: u128 = 50;
+
+// Turns a stream of primes into the gaps between consecutive ones, as (gap, the prime before it,
+// the prime after it). A range with n primes has n - 1 gaps.
+pub struct Gaps<I> {
+    primes: I,
+    previous: Option<u64>,
+}
+
+pub fn gaps<I: Iterator<Item = u64>>(primes: I) -> Gaps<I> {
+    Gaps {
+        primes,
+        previous: None,
+    }
+}
+
+impl<I: Iterator<Item = u64>> Iterator for Gaps<I> {
+    type Item = (u64, u64, u64);
+    fn next(&mut self) -> Option<(u64, u64, u64)> {
+        loop {
+            let p = self.primes.next()?;
+            if let Some(previous) = self.previous.replace(p) {
+                return Some((p - previous, previous, p));
+            }
+        }
+    }
+}
+
+// How often each gap size turns up in a range of primes.
+#[derive(Debug, Default)]
+pub struct Histogram {
+    pub frequencies: BTreeMap<u64, u64>,
+}
+
+impl Histogram {
+    pub fn collect<I: Iterator<Item = u64>>(primes: I) -> Self {
+        let mut histogram = Histogram::default();
+        for (gap, _, _) in gaps(primes) {
+            *histogram.frequencies.entry(gap).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let most = match self.frequencies.values().max() {
+            Some(&most) => most,
+            None => return write!(f, "no gaps"),
+        };
+        let gap_width = self
+            .frequencies
+            .keys()
+            .last()
+            .map_or(1, |g| g.to_string().len());
+        let count_width = most.to_string().len();
+        let mut first = true;
+        for (gap, &count) in &self.frequencies {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            // Round up, so a gap that turns up at all always gets at least one #.
+            let bar = (u128::from(count) * BAR_WIDTH).div_ceil(u128::from(most)) as usize;
+            write!(
+                f,
+                "{:>gw$} | {:>cw$} {}",
+                gap,
+                count,
+                "#".repeat(bar),
+                gw = gap_width,
+                cw = count_width
+            )?;
+        }
+        Ok(())
+    }
+}
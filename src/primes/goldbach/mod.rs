@@ -0,0 +1,9 @@
+use crate::primes::sieve::Table;
+
+// Pairs of primes (p, q) with p <= q and p + q == n, smallest p first. Every candidate is checked
+// against the sieve table, which must cover n.
+pub fn pairs(table: &Table, n: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+    (2..=n / 2)
+        .filter(move |&p| table.contains(p) && table.contains(n - p))
+        .map(move |p| (p, n - p))
+}
@@ -0,0 +1,38 @@
+use crate::primes::Generator;
+
+// Like Iterator::take, but still a generator, so whatever's downstream can keep asking how far
+// along we are. Once it's handed out its quota of primes, the cursor stays just past the last one.
+pub struct Limit {
+    inner: Box<dyn Generator>,
+    remaining: u64,
+}
+
+impl Limit {
+    pub fn new(inner: Box<dyn Generator>, limit: u64) -> Self {
+        Limit {
+            inner,
+            remaining: limit,
+        }
+    }
+}
+
+impl Iterator for Limit {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.inner.next()
+    }
+}
+
+impl Generator for Limit {
+    fn cursor(&self) -> u64 {
+        self.inner.cursor()
+    }
+
+    fn limit(&self) -> u64 {
+        self.inner.limit()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::primes::error::{Error, Result};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+// A size in bytes, which can be given on the command line with a K, M, G or T suffix (powers of
+// 1024), e.g. 512M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bytes(pub u64);
+
+impl FromStr for Bytes {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let (digits, shift) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&s[..s.len() - 1], 10),
+            Some('M') => (&s[..s.len() - 1], 20),
+            Some('G') => (&s[..s.len() - 1], 30),
+            Some('T') => (&s[..s.len() - 1], 40),
+            _ => (s, 0),
+        };
+        let invalid = || Error::Parse {
+            what: "size",
+            value: s.to_string(),
+        };
+        let n: u64 = digits.parse().map_err(|_| invalid())?;
+        n.checked_mul(1 << shift).map(Bytes).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < units.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} {}", self.0, units[unit])
+        } else {
+            write!(f, "{:.1} {}", size, units[unit])
+        }
+    }
+}
+
+// How much memory the kernel thinks we could allocate without swapping. This is only known on
+// Linux, everywhere else we can't tell and return None.
+pub fn available() -> Option<Bytes> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    // The line looks like "MemAvailable:   12345678 kB".
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(Bytes(kib * 1024))
+}
+
+// Refuse an allocation of `needed` bytes when it's over the caller's limit or more than the system
+// has available, rather than letting the allocator abort or the OOM killer step in.
+// argument names whichever argument decided how much to allocate, so the error can point at it.
+pub fn guard(argument: &'static str, needed: Bytes, limit: Option<Bytes>) -> Result<()> {
+    if let Some(limit) = limit {
+        if needed > limit {
+            return Err(Error::MemoryLimit {
+                argument,
+                needed,
+                limit,
+                hint: "",
+            });
+        }
+    }
+    if let Some(available) = available() {
+        if needed > available {
+            return Err(Error::MemoryAvailable {
+                argument,
+                needed,
+                available,
+                hint: "",
+            });
+        }
+    }
+    Ok(())
+}
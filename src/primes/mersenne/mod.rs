@@ -0,0 +1,36 @@
+use crate::primes::primality::{is_prime, Primality};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+// Whether 2^p - 1 is prime, by Lucas-Lehmer: with s = 4, squaring and subtracting 2 p - 2 times
+// leaves s divisible by 2^p - 1 exactly when it's prime.
+pub fn lucas_lehmer(p: u32) -> bool {
+    if p == 2 {
+        return true;
+    }
+    // 2^p - 1 can only be prime if p is, and the test itself only works for odd primes, so there's
+    // no point running it otherwise.
+    if is_prime(u128::from(p), 0) == Primality::Composite {
+        return false;
+    }
+    let mersenne = (BigUint::one() << p as usize) - BigUint::one();
+    let mut s = BigUint::from(4u32);
+    for _ in 0..p - 2 {
+        // Adding the modulus before subtracting keeps us from going negative.
+        s = reduce(&s * &s + &mersenne - 2u32, p, &mersenne);
+    }
+    s.is_zero()
+}
+
+// Division is slow, but 2^p = 1 (mod 2^p - 1), so n mod 2^p - 1 is the sum of n's p-bit chunks,
+// which only takes shifts, masks and adds.
+fn reduce(mut n: BigUint, p: u32, mersenne: &BigUint) -> BigUint {
+    while n.bits() > u64::from(p) {
+        n = (&n & mersenne) + (&n >> p as usize);
+    }
+    if &n == mersenne {
+        BigUint::zero()
+    } else {
+        n
+    }
+}
@@ -1,3 +1,43 @@
+pub mod arith;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod cache;
+pub mod checkpoint;
+pub mod constellation;
+pub mod count;
+pub mod deadline;
+pub mod error;
+pub mod estimate;
+pub mod factor;
+pub mod filter;
+pub mod format;
+pub mod gaps;
+pub mod goldbach;
+pub mod limit;
+pub mod memory;
+pub mod mersenne;
 pub mod naive;
 pub mod options;
+pub mod plot;
+pub mod primality;
+pub mod primorial;
+pub mod progress;
+pub mod pseudoprime;
+pub mod query;
+pub mod random;
+pub mod ranges;
+pub mod registry;
+pub mod segmented;
+pub mod serve;
 pub mod sieve;
+pub mod stats;
+
+// Every algorithm is an iterator of primes in ascending order, but long runs want to know how far
+// along they are too, so each one also reports where it is in its range.
+pub trait Generator: Iterator<Item = u64> {
+    /// The next candidate the generator will consider.
+    fn cursor(&self) -> u64;
+
+    /// The largest candidate the generator will consider.
+    fn limit(&self) -> u64;
+}
@@ -1,36 +1,21 @@
-#[derive(Debug)]
-pub struct Primes {
-    max: u64,
-    next: u64,
-    seen: Vec<u64>,
-}
+use crate::primes::Generator;
+
+pub mod parallel;
+
+pub use crate::portable::naive::Primes;
 
-// By boxing it up and returning a trait object, we can use it anywhere an iterator of u64's is
+// By boxing it up and returning a trait object, we can use it anywhere a generator of u64's is
 // needed, so that all of our different implementations can have compatible types.
-pub fn primes(max: u64) -> Box<dyn Iterator<Item = u64>> {
-    let iter = Primes {
-        max,
-        next: 1,
-        seen: Vec::new(),
-    };
-    Box::new(iter)
+pub fn primes(max: u64) -> Box<dyn Generator> {
+    Box::new(crate::portable::naive::primes(max))
 }
 
-impl Iterator for Primes {
-    type Item = u64;
-    fn next(&mut self) -> Option<u64> {
-        'outer: for i in self.next..=self.max {
-            for j in &self.seen {
-                if i % j == 0 {
-                    continue 'outer;
-                }
-            }
-            if i != 1 {
-                self.seen.push(i);
-                self.next = i + 1;
-                return Some(i);
-            }
-        }
-        None
+impl Generator for Primes {
+    fn cursor(&self) -> u64 {
+        self.next_candidate()
+    }
+
+    fn limit(&self) -> u64 {
+        self.max()
     }
 }
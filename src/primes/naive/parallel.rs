@@ -0,0 +1,130 @@
+use crate::primes::Generator;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+// Most composites have a small factor, and checking those is quicker than asking the workers, so
+// the first primes found stay on the calling thread and are always tried first.
+const LOCAL: usize = 64;
+
+// The same trial division as naive, but with the primes seen so far dealt out between worker
+// threads. Each candidate is sent to every worker, which checks it against its own share, so the
+// work per candidate is divided by the number of workers. Only the final answer needs collecting.
+pub struct Primes {
+    max: u64,
+    next: u64,
+    local: Vec<u64>,
+    // How many primes have been dealt out to the workers.
+    dealt: u64,
+    workers: Vec<Worker>,
+    answers: Receiver<bool>,
+}
+
+struct Worker {
+    tx: Sender<Message>,
+}
+
+enum Message {
+    // Does any of your primes divide this?
+    Test(u64),
+    // Add this prime to your share.
+    Add(u64),
+}
+
+pub fn primes(max: u64, workers: usize) -> Box<dyn Generator> {
+    Box::new(Primes::new(max, workers))
+}
+
+impl Primes {
+    pub fn new(max: u64, workers: usize) -> Self {
+        let (answer_tx, answers) = channel();
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let (tx, rx) = channel();
+                let answer_tx = answer_tx.clone();
+                // The workers exit once we hang up on them by dropping their senders.
+                thread::spawn(move || work(rx, answer_tx));
+                Worker { tx }
+            })
+            .collect();
+        Primes {
+            max,
+            next: 1,
+            local: Vec::new(),
+            dealt: 0,
+            workers,
+            answers,
+        }
+    }
+
+    fn divisible(&self, i: u64) -> bool {
+        if self.local.iter().any(|&j| i.is_multiple_of(j)) {
+            return true;
+        }
+        // Until the local primes fill up, the workers have nothing to check.
+        if self.dealt == 0 {
+            return false;
+        }
+        for worker in &self.workers {
+            worker
+                .tx
+                .send(Message::Test(i))
+                .expect("naive-parallel worker exited");
+        }
+        // Every worker answers every test, so the answers can't get mixed up between candidates,
+        // but we do have to wait for all of them.
+        let mut divisible = false;
+        for _ in &self.workers {
+            divisible |= self.answers.recv().expect("naive-parallel worker exited");
+        }
+        divisible
+    }
+}
+
+fn work(rx: Receiver<Message>, answers: Sender<bool>) {
+    let mut seen = Vec::new();
+    for message in rx {
+        match message {
+            Message::Test(i) => {
+                let divisible = seen.iter().any(|&j| i.is_multiple_of(j));
+                if answers.send(divisible).is_err() {
+                    return;
+                }
+            }
+            Message::Add(p) => seen.push(p),
+        }
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        for i in self.next..=self.max {
+            if i != 1 && !self.divisible(i) {
+                if self.local.len() < LOCAL {
+                    self.local.push(i);
+                } else {
+                    // Deal the rest out in turn, so every worker's share stays about the same size.
+                    let worker = &self.workers[(self.dealt % self.workers.len() as u64) as usize];
+                    worker
+                        .tx
+                        .send(Message::Add(i))
+                        .expect("naive-parallel worker exited");
+                    self.dealt += 1;
+                }
+                self.next = i + 1;
+                return Some(i);
+            }
+        }
+        None
+    }
+}
+
+impl Generator for Primes {
+    fn cursor(&self) -> u64 {
+        self.next
+    }
+
+    fn limit(&self) -> u64 {
+        self.max
+    }
+}
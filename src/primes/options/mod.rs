@@ -1,35 +1,420 @@
-use failure::{err_msg, Error};
-use std::str::FromStr;
-use structopt::StructOpt;
+use crate::primes::constellation::Pattern;
+use crate::primes::factor::Method;
+use crate::primes::format::{Format, Radix, Template};
+use crate::primes::memory::Bytes;
+use crate::primes::primality::Witnesses;
+use crate::primes::registry;
+use crate::primes::sieve::Backend;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use std::fmt;
+use std::path::PathBuf;
+use std::thread;
 
-#[derive(Debug, StructOpt)]
-#[structopt(
+// What is-prime and factor accept. The bigint feature lifts the u128 limit.
+#[cfg(not(feature = "bigint"))]
+pub type Number = u128;
+#[cfg(feature = "bigint")]
+pub type Number = num_bigint::BigUint;
+
+#[derive(Debug, Parser)]
+#[command(
     name = "primes",
-    author = "\n",
-    about = "    Calculate primes with multiple algorithms.",
-    raw(setting = "structopt::clap::AppSettings::AllowNegativeNumbers")
+    version,
+    about = "Calculate primes with multiple algorithms.",
+    subcommand_negates_reqs = true,
+    after_help = "EXIT CODES:
+    1    the command line couldn't be parsed
+    2    an argument's value can't be used
+    3    not enough memory
+    4    reading or writing a file failed
+    5    the checkpoint is invalid
+    6    --verify or --self-test found a difference
+    7    there's no answer (e.g. no prime <= 1)
+    8    --max-seconds ran out before max was reached"
 )]
 pub struct Opt {
-    /// Valid choices are sieve & naive
-    pub algorithm: Algorithm,
+    // These are Options so that clap doesn't unwrap them when a subcommand or --ranges is
+    // given instead, but they're still required otherwise so that clap complains when neither is
+    // present.
+    /// One of the algorithms listed by `primes algorithms`, or auto
+    #[arg(value_enum, required_unless_present = "ranges")]
+    pub algorithm: Option<Algorithm>,
 
     /// Find all primes less than this
-    pub max: u64,
+    #[arg(required_unless_present = "ranges", allow_negative_numbers = true)]
+    pub max: Option<u64>,
+
+    /// Instead of a single range, list the primes in every range in this file, one per line
+    /// like 100..200 or 100..=199, with the segmented sieve
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "algorithm",
+            "max",
+            "checkpoint",
+            "verify",
+            "self_test",
+            "stats",
+            "gap_histogram",
+            "plot",
+            "max_seconds",
+        ]
+    )]
+    pub ranges: Option<PathBuf>,
+
+    /// Merge overlapping --ranges into a single list, rather than a section per range
+    #[arg(long, requires = "ranges")]
+    pub merge: bool,
+
+    /// Stop after this many primes, even if there are more below max
+    #[arg(long)]
+    pub limit: Option<u64>,
+
+    /// Only output primes congruent to --residue modulo this
+    #[arg(long = "mod", requires = "residue")]
+    pub modulus: Option<u64>,
+
+    /// Only output primes congruent to this modulo --mod (e.g. --mod 4 --residue 1)
+    #[arg(long, requires = "modulus")]
+    pub residue: Option<u64>,
+
+    /// Only output primes that are palindromes when written in --radix
+    #[arg(long)]
+    pub palindromic: bool,
+
+    /// Only output primes p where p + offset is also prime for every offset in this list, e.g. 0,2
+    /// for twin primes or 0,2,6,8 for prime quadruplets
+    #[arg(long)]
+    pub pattern: Option<Pattern>,
+
+    /// Only output Sophie Germain primes, the p where 2p + 1 is prime too
+    #[arg(long)]
+    pub sophie_germain: bool,
+
+    /// Only output safe primes, the p where (p - 1) / 2 is prime too
+    #[arg(long)]
+    pub safe: bool,
+
+    /// Stop early once this many seconds have passed, keeping whatever was found by then
+    #[arg(long, conflicts_with_all = ["verify", "self_test"])]
+    pub max_seconds: Option<f64>,
+
+    /// Render a progress bar on stderr while the primes are computed
+    #[arg(long)]
+    pub progress: bool,
+
+    /// How many worker threads the parallel algorithms use (default: one per CPU)
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Refuse to run if the algorithm would need more memory than this (e.g. 512M, 4G)
+    #[arg(long)]
+    pub max_memory: Option<Bytes>,
+
+    /// How many numbers the segmented sieve works on at a time (e.g. 32K, 1M)
+    #[arg(long, default_value = "32K")]
+    pub segment_size: Bytes,
+
+    /// Where the sieve keeps its table: memory, or mmap to page it to a temporary file when it
+    /// doesn't fit
+    #[arg(long, default_value = "memory")]
+    pub backend: Backend,
+
+    /// Keep a bitmap of the primes found in this directory, and reuse it for any max it covers
+    /// (sieve and segmented only)
+    #[arg(long, conflicts_with_all = ["checkpoint", "backend"])]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Write a bitmap of which numbers up to max are prime to this file: "PRIMEBM1", max as a
+    /// little-endian u64, then a bit per number, least significant bit first (sieve and segmented
+    /// only)
+    #[arg(long, conflicts_with_all = ["checkpoint", "backend", "cache_dir"])]
+    pub dump_bitmap: Option<PathBuf>,
+
+    /// List the primes from a bitmap written by --dump-bitmap or --cache-dir, rather than sieving
+    #[arg(long, conflicts_with_all = ["checkpoint", "backend", "cache_dir", "dump_bitmap"])]
+    pub load_bitmap: Option<PathBuf>,
+
+    /// Periodically save progress to this file (segmented only)
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Continue from --checkpoint instead of starting over. --output and --tee are cut back to
+    /// what they held at its last save, but primes written to stdout since then are written again
+    #[arg(long, requires = "checkpoint", conflicts_with = "gzip")]
+    pub resume: bool,
+
+    /// Write the primes to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Write the primes to this file as well, e.g. to keep a copy of what's piped on elsewhere
+    #[arg(long)]
+    pub tee: Option<PathBuf>,
+
+    /// Say what's going on, on stderr: -v for the main steps, -vv for more detail and -vvv for
+    /// everything
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// How to write the primes: text, one per line; binary, as little-endian u64s; or varint, as
+    /// LEB128 varints of the gaps between them
+    #[arg(long, default_value = "text")]
+    pub format: Format,
+
+    /// Write the primes in this base: 2, 8, 10 or 16
+    #[arg(long, default_value = "10")]
+    pub radix: Radix,
+
+    /// Write each prime on a line shaped like this (text only), with placeholders {index} (from
+    /// 0), {prime}, {gap} (from the prime before) and {count}, e.g. "{index}: {prime} (gap {gap})"
+    #[arg(long)]
+    pub template: Option<Template>,
+
+    /// Gzip compress the primes as they're written
+    #[arg(long)]
+    pub gzip: bool,
+
+    /// Instead of printing the primes, check them against another algorithm
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Instead of printing the primes, check how many there are up to each power of 10 <= max
+    /// against the known counts
+    #[arg(long, conflicts_with = "verify")]
+    pub self_test: bool,
+
+    /// Instead of printing the primes, print summary statistics about them
+    #[arg(long, conflicts_with = "checkpoint")]
+    pub stats: bool,
+
+    /// Instead of printing the primes, print a histogram of the gaps between consecutive primes
+    #[arg(long, conflicts_with_all = ["checkpoint", "stats"])]
+    pub gap_histogram: bool,
+
+    /// Instead of printing the primes, plot how densely they fall in --buckets equal parts of the
+    /// range
+    #[arg(long, conflicts_with_all = ["checkpoint", "stats", "gap_histogram"])]
+    pub plot: bool,
+
+    /// How many parts --plot splits the range into
+    #[arg(long, default_value = "20")]
+    pub buckets: u64,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Opt {
+    // How many threads the parallel algorithms should use: --threads if given, or one per CPU.
+    pub fn threads(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Test whether a single number is prime
+    IsPrime {
+        /// The number to test
+        n: Number,
+
+        #[command(flatten)]
+        mr: MillerRabin,
+    },
+
+    /// Factor a number into its primes
+    Factor {
+        /// The number to factor
+        n: Number,
+
+        /// How to find the factors: trial division, rho (Pollard's, Brent's variant), ecm (elliptic
+        /// curves, then rho), or auto (rho for a while, then ecm)
+        #[arg(long, default_value = "auto")]
+        algorithm: Method,
+    },
+
+    /// Compute Euler's totient, the sum of divisors and the number of divisors of a number
+    Arith {
+        /// The number to compute them for
+        n: u128,
+    },
+
+    /// Find the smallest prime greater than or equal to a number
+    Next {
+        /// Where to start looking
+        n: u128,
+
+        #[command(flatten)]
+        mr: MillerRabin,
+    },
+
+    /// Find the largest prime less than or equal to a number
+    Prev {
+        /// Where to start looking
+        n: u128,
+
+        #[command(flatten)]
+        mr: MillerRabin,
+    },
+
+    /// Generate a random prime of a given size
+    Random {
+        /// How many bits the prime should have (2 to 128)
+        #[arg(long)]
+        bits: u32,
+
+        /// Seed the random number generator to get the same prime every time
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Miller-Rabin rounds to use for numbers too large to test deterministically
+        #[arg(long = "mr-rounds", alias = "rounds", default_value = "20")]
+        rounds: usize,
+    },
+
+    /// Pick primes uniformly at random from a range, with replacement, e.g. to generate test
+    /// fixtures
+    Sample {
+        /// The smallest prime that may be picked
+        low: u128,
+
+        /// The largest prime that may be picked
+        high: u128,
+
+        /// How many primes to pick. Each is picked independently of the others, so the same prime
+        /// can come up more than once, and --count can be more than there are primes in the range
+        #[arg(long, default_value = "1")]
+        count: u64,
+
+        /// Seed the random number generator to get the same primes every time
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Miller-Rabin rounds to use for numbers too large to test deterministically
+        #[arg(long = "mr-rounds", alias = "rounds", default_value = "20")]
+        rounds: usize,
+    },
+
+    /// Write an even number as the sum of two primes
+    Goldbach {
+        /// The even number to decompose
+        n: u64,
+
+        /// Print every pair rather than just the first
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Compute n#, the product of every prime up to n
+    Primorial {
+        /// Multiply all primes less than or equal to this
+        n: u64,
+
+        /// Print how many digits n# has rather than n# itself
+        #[arg(long)]
+        digits: bool,
+    },
+
+    /// List the composites that fool the Fermat test
+    Pseudoprimes {
+        /// List pseudoprimes less than or equal to this
+        max: u64,
+
+        /// The base to run the Fermat test with (default 2)
+        #[arg(long, conflicts_with = "carmichael")]
+        base: Option<u64>,
+
+        /// List the Carmichael numbers, which fool the test for every base coprime to them
+        #[arg(long)]
+        carmichael: bool,
+    },
+
+    /// Compute base^exp mod modulus, the same way Miller-Rabin does
+    Powmod {
+        /// The number to raise to a power
+        base: u128,
+
+        /// The power to raise it to
+        exp: u128,
+
+        /// What to reduce the result modulo
+        modulus: u128,
+    },
+
+    /// Test whether the Mersenne number 2^p - 1 is prime
+    Mersenne {
+        /// The exponent
+        p: u32,
+    },
+
+    /// Answer queries like "isprime 97", "factor 360" or "count 10^6", one per line
+    Query {
+        /// File to read the queries from, or - for stdin
+        input: PathBuf,
+
+        /// Miller-Rabin rounds to use for numbers too large to test deterministically
+        #[arg(long = "mr-rounds", alias = "rounds", default_value = "20")]
+        rounds: usize,
+    },
+
+    /// Sieve once, then answer "contains n", "next n" and "count n" requests on a Unix socket
+    Serve {
+        /// Sieve all primes up to this
+        #[arg(long)]
+        max: u64,
+
+        /// Where to listen for connections
+        #[arg(long, default_value = "primes.sock")]
+        socket: PathBuf,
+    },
+
+    /// List the available algorithms and what they cost
+    Algorithms,
+
+    /// Count the primes up to a number without listing them
+    Count {
+        /// Count all primes less than or equal to this
+        x: u64,
+    },
+
+    /// Bound and estimate the k-th prime without finding it
+    EstimateNth {
+        /// Which prime, counting 2 as the first
+        k: u64,
+    },
+}
+
+// How hard to try when a number is too large to test deterministically.
+#[derive(Debug, Args)]
+pub struct MillerRabin {
+    /// Miller-Rabin rounds to use for numbers too large to test deterministically
+    #[arg(long = "mr-rounds", alias = "rounds", default_value = "20")]
+    pub rounds: usize,
+
+    /// Where Miller-Rabin bases beyond the deterministic ones come from: fixed, the next primes
+    /// in order, or random
+    #[arg(long, default_value = "fixed")]
+    pub witnesses: Witnesses,
 }
 
-#[derive(Debug)]
+// Parsed by name, the same name as the algorithm's row in the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum Algorithm {
+    Auto,
     Naive,
+    NaiveParallel,
     Sieve,
+    Segmented,
 }
 
-impl FromStr for Algorithm {
-    type Err = Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "sieve" => Ok(Algorithm::Sieve),
-            "naive" => Ok(Algorithm::Naive),
-            s => Err(err_msg(format!("invalid algorithm: {}", s))),
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Auto => write!(f, "auto"),
+            // max doesn't matter for anything but auto.
+            algorithm => write!(f, "{}", registry::lookup(*algorithm, 0).name),
         }
     }
 }
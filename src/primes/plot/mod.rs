@@ -0,0 +1,60 @@
+use std::fmt;
+
+// The widest a bar gets. Every other bar is scaled relative to the densest bucket.
+const BAR_WIDTH: u128 = 50;
+
+// How many primes fall in each of a number of equally wide buckets covering 1..=max, to show how
+// they thin out.
+#[derive(Debug)]
+pub struct Plot {
+    pub max: u64,
+    pub width: u64,
+    pub counts: Vec<u64>,
+}
+
+impl Plot {
+    pub fn collect<I: Iterator<Item = u64>>(primes: I, max: u64, buckets: u64) -> Self {
+        // Round the width up, so that buckets of it always reach max. That can leave fewer buckets
+        // than asked for, but never more.
+        let width = max.div_ceil(buckets.max(1)).max(1);
+        let mut counts = vec![0; max.div_ceil(width) as usize];
+        for p in primes {
+            counts[((p - 1) / width) as usize] += 1;
+        }
+        Plot { max, width, counts }
+    }
+}
+
+impl fmt::Display for Plot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buckets: Vec<(String, u64)> = (0..self.counts.len() as u64)
+            .map(|i| {
+                let low = i * self.width + 1;
+                let high = low.saturating_add(self.width - 1).min(self.max);
+                (format!("{}..={}", low, high), high - low + 1)
+            })
+            .collect();
+        let range_width = buckets.iter().map(|(r, _)| r.len()).max().unwrap_or(0);
+        let densest = match self.counts.iter().copied().max() {
+            Some(densest) => densest.max(1),
+            None => return write!(f, "nothing to plot"),
+        };
+        for (i, ((range, size), &count)) in buckets.iter().zip(&self.counts).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            // The last bucket is cut short by max, so only count the part of it we covered.
+            let density = count as f64 / *size as f64 * 100.0;
+            let bar = (u128::from(count) * BAR_WIDTH).div_ceil(u128::from(densest)) as usize;
+            write!(
+                f,
+                "{:>rw$} | {:>8.4}% {}",
+                range,
+                density,
+                "#".repeat(bar),
+                rw = range_width
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::portable::primality::{miller_rabin, DETERMINISTIC_BASES};
+use crate::primes::error::Error;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+pub use crate::portable::primality::{add_mod, is_prime, mul_mod, pow_mod, Primality};
+
+// Where the bases beyond the deterministic ones come from, for numbers too large for a u64.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Witnesses {
+    // The primes following the deterministic bases. The answer is repeatable, but a composite
+    // built to pass exactly those bases will.
+    Fixed,
+    // Uniformly random bases, which no composite can be built to pass, so each one really does
+    // let through at most a quarter of composites.
+    Random,
+}
+
+impl FromStr for Witnesses {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Witnesses::Fixed),
+            "random" => Ok(Witnesses::Random),
+            _ => Err(Error::Parse {
+                what: "witnesses",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Witnesses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Witnesses::Fixed => write!(f, "fixed"),
+            Witnesses::Random => write!(f, "random"),
+        }
+    }
+}
+
+// How likely is_prime_with is to call a composite probably prime, given how it was called: each
+// round lets through at most a quarter of composites. With fixed witnesses that only holds for
+// numbers that weren't chosen to fool them.
+pub fn error_bound(rounds: usize, witnesses: Witnesses) -> f64 {
+    let rounds = match witnesses {
+        Witnesses::Fixed => rounds.max(DETERMINISTIC_BASES.len()),
+        Witnesses::Random => rounds,
+    };
+    0.25f64.powi(rounds.min(i32::MAX as usize) as i32)
+}
+
+// Like is_prime, but with a choice of witnesses for numbers beyond a u64. Random witnesses are
+// tried on top of the deterministic bases, so `rounds` of them are all random.
+pub fn is_prime_with(n: u128, rounds: usize, witnesses: Witnesses) -> Primality {
+    match witnesses {
+        Witnesses::Fixed => is_prime(n, rounds),
+        Witnesses::Random => {
+            // Only drawn when n is past a u64, so n - 1 is always well above 2.
+            let mut rng = rand::thread_rng();
+            miller_rabin(n, (0..rounds).map(|_| rng.gen_range(2..n - 1)))
+        }
+    }
+}
+
+// Skipping the even numbers and multiples of 3 saves two thirds of the Miller-Rabin tests.
+fn worth_testing(candidate: u128) -> bool {
+    candidate == 3 || !candidate.is_multiple_of(3)
+}
+
+// The smallest prime >= n, or None if that would be larger than a u128.
+pub fn next_prime(n: u128, rounds: usize, witnesses: Witnesses) -> Option<u128> {
+    if n <= 2 {
+        return Some(2);
+    }
+    // The first odd number >= n.
+    let mut candidate = n | 1;
+    loop {
+        if worth_testing(candidate)
+            && is_prime_with(candidate, rounds, witnesses) != Primality::Composite
+        {
+            return Some(candidate);
+        }
+        candidate = candidate.checked_add(2)?;
+    }
+}
+
+// The largest prime <= n, or None if n is smaller than every prime.
+pub fn prev_prime(n: u128, rounds: usize, witnesses: Witnesses) -> Option<u128> {
+    if n < 2 {
+        return None;
+    }
+    if n == 2 {
+        return Some(2);
+    }
+    // The first odd number <= n.
+    let mut candidate = if n.is_multiple_of(2) { n - 1 } else { n };
+    while candidate >= 3 {
+        if worth_testing(candidate)
+            && is_prime_with(candidate, rounds, witnesses) != Primality::Composite
+        {
+            return Some(candidate);
+        }
+        candidate -= 2;
+    }
+    Some(2)
+}
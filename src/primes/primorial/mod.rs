@@ -0,0 +1,50 @@
+use num_bigint::BigUint;
+use num_traits::One;
+
+// The product of every prime primes yields, e.g. of primes up to n for n#. Multiplying one prime at
+// a time into an ever growing product is quadratic, so instead we pack runs of primes into u64s
+// and multiply those together pairwise, keeping the operands about the same size.
+pub fn primorial<I: Iterator<Item = u64>>(primes: I) -> BigUint {
+    let mut packed = Vec::new();
+    let mut word: u64 = 1;
+    for p in primes {
+        match word.checked_mul(p) {
+            Some(w) => word = w,
+            None => {
+                packed.push(BigUint::from(word));
+                word = p;
+            }
+        }
+    }
+    packed.push(BigUint::from(word));
+    product(packed)
+}
+
+fn product(mut factors: Vec<BigUint>) -> BigUint {
+    while factors.len() > 1 {
+        factors = factors
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => a * b,
+                [a] => a.clone(),
+                _ => unreachable!("chunks(2) yields one or two"),
+            })
+            .collect();
+    }
+    factors.pop().unwrap_or_else(BigUint::one)
+}
+
+// How many decimal digits n has, without converting all of it to decimal, which for the huge
+// numbers primorials get to takes far longer than working them out.
+pub fn digits(n: &BigUint) -> u64 {
+    let ten = BigUint::from(10u32);
+    // log10(n) is within a digit of bits * log10(2), so start there and correct.
+    let mut digits = ((n.bits().saturating_sub(1)) as f64 * std::f64::consts::LOG10_2) as u64 + 1;
+    while ten.pow(digits as u32) <= *n {
+        digits += 1;
+    }
+    while digits > 1 && ten.pow(digits as u32 - 1) > *n {
+        digits -= 1;
+    }
+    digits
+}
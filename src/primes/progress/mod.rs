@@ -0,0 +1,98 @@
+use crate::primes::Generator;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 30;
+
+// Redrawing on every prime would spend more time on the terminal than on the primes.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// Wraps a generator and redraws a progress bar on stderr as primes are pulled through it. Since it
+// only writes to stderr, stdout can still be piped somewhere else.
+pub struct Progress {
+    inner: Box<dyn Generator>,
+    found: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(inner: Box<dyn Generator>) -> Self {
+        Progress {
+            inner,
+            found: 0,
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    fn fraction(&self) -> f64 {
+        let limit = self.inner.limit();
+        if limit == 0 {
+            return 1.0;
+        }
+        (self.inner.cursor().min(limit) as f64 / limit as f64).min(1.0)
+    }
+
+    fn draw(&mut self, fraction: f64) {
+        let now = Instant::now();
+        self.last_draw = Some(now);
+
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let eta = if fraction > 0.0 {
+            format!("{:.0}s", elapsed * (1.0 - fraction) / fraction)
+        } else {
+            "?".to_string()
+        };
+        let filled = (fraction * WIDTH as f64) as usize;
+
+        let stderr = io::stderr();
+        let mut stderr = stderr.lock();
+        // If we can't draw the progress bar there's nobody to tell, and it shouldn't interrupt
+        // the primes themselves, so ignore the error.
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:5.1}% sieved, {} primes found, ETA {:<8}",
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            fraction * 100.0,
+            self.found,
+            eta
+        );
+        let _ = stderr.flush();
+    }
+}
+
+impl Iterator for Progress {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        let next = self.inner.next();
+        match next {
+            Some(_) => {
+                self.found += 1;
+                let due = self
+                    .last_draw
+                    .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+                if due {
+                    let fraction = self.fraction();
+                    self.draw(fraction);
+                }
+            }
+            None => {
+                self.draw(1.0);
+                eprintln!();
+            }
+        }
+        next
+    }
+}
+
+impl Generator for Progress {
+    fn cursor(&self) -> u64 {
+        self.inner.cursor()
+    }
+
+    fn limit(&self) -> u64 {
+        self.inner.limit()
+    }
+}
@@ -0,0 +1,26 @@
+use crate::primes::factor::{factor, powers};
+use crate::primes::primality::pow_mod;
+use crate::primes::sieve::Table;
+
+// Whether n passes the Fermat test base^(n-1) = 1 (mod n), as every prime not dividing base does.
+fn passes_fermat(n: u64, base: u64) -> bool {
+    pow_mod(u128::from(base), u128::from(n - 1), u128::from(n)) == 1
+}
+
+// The composites up to table.max() that the Fermat test to base calls prime. The table is what
+// tells us which ones really are.
+pub fn fermat(table: &Table, base: u64) -> impl Iterator<Item = u64> + '_ {
+    (4..=table.max()).filter(move |&n| !table.contains(n) && passes_fermat(n, base))
+}
+
+// The Carmichael numbers up to table.max(), which fool the Fermat test for every base they have no
+// factor in common with. Every odd one is a base 2 pseudoprime, and they're all odd, so only those
+// need checking with Korselt's criterion: n is squarefree, and p - 1 divides n - 1 for every prime
+// p dividing it.
+pub fn carmichael(table: &Table) -> impl Iterator<Item = u64> + '_ {
+    fermat(table, 2).filter(|&n| {
+        powers(&factor(u128::from(n)))
+            .iter()
+            .all(|&(p, e)| e == 1 && u128::from(n - 1).is_multiple_of(p - 1))
+    })
+}
@@ -0,0 +1,129 @@
+use crate::primes::count::{self, count};
+use crate::primes::error::{Error, Result};
+use crate::primes::factor::factor;
+use crate::primes::memory::{self, Bytes};
+use crate::primes::primality::{is_prime, Primality};
+use crate::primes::sieve::Table;
+use std::convert::TryFrom;
+
+// Numbers up to this are answered from a sieve table, anything bigger goes to Miller-Rabin. The
+// table costs a byte per number, so this caps it at 64MiB.
+const TABLE_LIMIT: u64 = 1 << 26;
+
+// Answers one query per line, e.g. "isprime 97", "factor 360" or "count 10^6". The sieve table is
+// kept between queries and only rebuilt when a query needs it to cover more, which is the point of
+// batching queries into one process.
+pub struct Answerer {
+    rounds: usize,
+    max_memory: Option<Bytes>,
+    table: Option<Table>,
+}
+
+impl Answerer {
+    pub fn new(rounds: usize, max_memory: Option<Bytes>) -> Self {
+        Answerer {
+            rounds,
+            max_memory,
+            table: None,
+        }
+    }
+
+    // The answer to a line, formatted like the matching subcommand would print it. Blank lines and
+    // lines starting with # have no answer.
+    pub fn answer(&mut self, line: &str) -> Result<Option<String>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let mut words = line.split_whitespace();
+        let query = words.next().unwrap_or_default();
+        let n = match (words.next(), words.next()) {
+            (Some(n), None) => parse_number(n)?,
+            _ => {
+                return Err(Error::Parse {
+                    what: "query",
+                    value: line.to_string(),
+                })
+            }
+        };
+        let answer = match query {
+            "isprime" | "is-prime" => {
+                // 0 and 1 are neither prime nor composite.
+                if n < 2 {
+                    return Err(Error::Argument {
+                        argument: "<n>",
+                        message: "must be at least 2".to_string(),
+                    });
+                }
+                let verdict = match self.is_prime(n) {
+                    Primality::Prime => "prime",
+                    Primality::ProbablePrime => "probably prime",
+                    Primality::Composite => "composite",
+                };
+                format!("{}: {}", n, verdict)
+            }
+            "factor" => {
+                // Every number divides 0, so it has no factorization.
+                if n == 0 {
+                    return Err(Error::Argument {
+                        argument: "<n>",
+                        message: "must be at least 1".to_string(),
+                    });
+                }
+                let factors: Vec<String> = factor(n).iter().map(|f| f.to_string()).collect();
+                format!("{}: {}", n, factors.join(" "))
+            }
+            "count" => {
+                let x = u64::try_from(n).map_err(|_| Error::Argument {
+                    argument: "count",
+                    message: format!("only works up to {}", u64::MAX),
+                })?;
+                memory::guard("<x>", count::footprint(x), self.max_memory)?;
+                count(x).to_string()
+            }
+            query => {
+                return Err(Error::Parse {
+                    what: "query",
+                    value: query.to_string(),
+                })
+            }
+        };
+        Ok(Some(answer))
+    }
+
+    fn is_prime(&mut self, n: u128) -> Primality {
+        if n > u128::from(TABLE_LIMIT) {
+            return is_prime(n, self.rounds);
+        }
+        let n = n as u64;
+        let covered = self.table.as_ref().is_some_and(|t| t.max() >= n);
+        if !covered {
+            // Grow geometrically, so a stream of slowly increasing queries doesn't rebuild the
+            // table every time.
+            let max = self.table.as_ref().map_or(0, |t| t.max());
+            let max = n.max(max.saturating_mul(2)).clamp(1 << 16, TABLE_LIMIT);
+            self.table = Some(Table::new(max));
+        }
+        match &self.table {
+            Some(table) if table.contains(n) => Primality::Prime,
+            _ => Primality::Composite,
+        }
+    }
+}
+
+// Numbers in queries can be written as powers, e.g. 10^6, since counts are usually wanted at round
+// numbers.
+pub fn parse_number(s: &str) -> Result<u128> {
+    let invalid = || Error::Parse {
+        what: "number",
+        value: s.to_string(),
+    };
+    match s.split_once('^') {
+        Some((base, exp)) => {
+            let base: u128 = base.parse().map_err(|_| invalid())?;
+            let exp: u32 = exp.parse().map_err(|_| invalid())?;
+            base.checked_pow(exp).ok_or_else(invalid)
+        }
+        None => s.parse().map_err(|_| invalid()),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::primes::primality::{is_prime, next_prime, Primality, Witnesses};
+use rand::Rng;
+
+// A uniformly random prime with exactly `bits` bits, or None if there aren't any (bits < 2) or
+// they wouldn't fit in a u128 (bits > 128). We draw uniformly from the whole k-bit range and reject
+// composites, which keeps every prime equally likely. Above 2 bits every prime is odd, so forcing
+// the low bit on halves the rejections without skewing anything.
+pub fn random_prime<R: Rng>(bits: u32, rounds: usize, rng: &mut R) -> Option<u128> {
+    if !(2..=128).contains(&bits) {
+        return None;
+    }
+    let low: u128 = 1 << (bits - 1);
+    loop {
+        let mut candidate = low + rng.gen_range(0..low);
+        if bits > 2 {
+            candidate |= 1;
+        }
+        if is_prime(candidate, rounds) != Primality::Composite {
+            return Some(candidate);
+        }
+    }
+}
+
+// A uniformly random prime in low..=high, or None if there aren't any. Like random_prime it draws
+// from the whole range and rejects composites, so every prime in it is equally likely, but first
+// it makes sure there's a prime to find, since otherwise it would never stop looking.
+pub fn random_prime_in<R: Rng>(low: u128, high: u128, rounds: usize, rng: &mut R) -> Option<u128> {
+    next_prime(low, rounds, Witnesses::Fixed).filter(|&p| p <= high)?;
+    loop {
+        let candidate = rng.gen_range(low..=high);
+        if is_prime(candidate, rounds) != Primality::Composite {
+            return Some(candidate);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::primes::error::{Error, Result};
+use crate::primes::query::parse_number;
+use crate::primes::{segmented, Generator};
+use std::convert::TryFrom;
+
+// One line of a --ranges file, as an inclusive (low, high) pair. Ranges are written like Rust's,
+// either 100..200 or 100..=199, and like queries the ends can be powers, e.g. 10^6..10^7. Blank
+// lines, comments and empty ranges give None.
+pub fn parse(line: &str) -> Result<Option<(u64, u64)>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let invalid = || Error::Parse {
+        what: "range",
+        value: line.to_string(),
+    };
+    let end = |s: &str| {
+        parse_number(s.trim())
+            .ok()
+            .and_then(|n| u64::try_from(n).ok())
+            .ok_or_else(invalid)
+    };
+    let (low, high) = match line.split_once("..=") {
+        Some((low, high)) => (end(low)?, end(high)?),
+        None => {
+            let (low, high) = line.split_once("..").ok_or_else(invalid)?;
+            match end(high)?.checked_sub(1) {
+                Some(high) => (end(low)?, high),
+                None => return Ok(None),
+            }
+        }
+    };
+    Ok(if low <= high { Some((low, high)) } else { None })
+}
+
+// Sorts ranges and joins the ones that overlap or touch, so that each number is covered once.
+pub fn merge(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (low, high) in ranges {
+        match merged.last_mut() {
+            Some((_, last)) if low <= last.saturating_add(1) => *last = (*last).max(high),
+            _ => merged.push((low, high)),
+        }
+    }
+    merged
+}
+
+// A segmented sieve for each range. They share one set of base primes, sieved up to the square
+// root of the highest range, and each takes as many of them as it needs.
+pub fn primes(ranges: &[(u64, u64)], segment_size: u64) -> Vec<Box<dyn Generator>> {
+    let highest = ranges.iter().map(|&(_, high)| high).max().unwrap_or(0);
+    let base = segmented::base_primes(highest);
+    ranges
+        .iter()
+        .map(|&(low, high)| {
+            let needed = base
+                .iter()
+                .take_while(|&&p| p <= high / p)
+                .copied()
+                .collect();
+            let primes = segmented::Primes::new(high, low, needed).segment_size(segment_size);
+            Box::new(primes) as Box<dyn Generator>
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use crate::primes::memory::Bytes;
+use crate::primes::options::{Algorithm, Opt};
+use crate::primes::{naive, segmented, sieve, Generator};
+
+// Everything we know about an algorithm. Listing, auto-selection and construction all work from
+// the REGISTRY table below, so adding an algorithm means adding a variant to Algorithm, which clap
+// parses by the same name as its row, and a row here.
+pub struct Entry {
+    pub algorithm: Algorithm,
+    pub name: &'static str,
+    pub time: &'static str,
+    pub space: &'static str,
+    // The largest <max> the algorithm can handle on this platform.
+    pub max: u64,
+    // auto picks the first algorithm in the table whose auto_up_to covers <max>. None means auto
+    // never picks it.
+    pub auto_up_to: Option<u64>,
+    // How much memory the algorithm allocates up front, if it's enough to be worth guarding.
+    pub footprint: Option<fn(&Opt, u64) -> Bytes>,
+    // Whether the algorithm spreads its work over --threads threads.
+    pub parallel: bool,
+    pub primes: fn(&Opt, u64) -> Box<dyn Generator>,
+}
+
+pub static REGISTRY: &[Entry] = &[
+    Entry {
+        algorithm: Algorithm::Sieve,
+        name: "sieve",
+        time: "O(n log log n)",
+        space: "O(n)",
+        // Sieve allocates a vector sized at max + 1. This limits us to addressable memory on the
+        // system based on the size of usize.
+        max: (usize::MAX - 1) as u64,
+        auto_up_to: Some(1 << 24),
+        footprint: Some(|_, max| sieve::footprint(max)),
+        parallel: false,
+        primes: |_, max| sieve::primes(max),
+    },
+    Entry {
+        algorithm: Algorithm::Segmented,
+        name: "segmented",
+        time: "O(n log log n)",
+        space: "O(sqrt n)",
+        max: u64::MAX,
+        auto_up_to: Some(u64::MAX),
+        footprint: Some(|opt, max| segmented::footprint(max, opt.segment_size.0)),
+        parallel: false,
+        primes: |opt, max| segmented::primes(max, opt.segment_size.0),
+    },
+    Entry {
+        algorithm: Algorithm::Naive,
+        name: "naive",
+        time: "O(n^2 / log^2 n)",
+        space: "O(n / log n)",
+        max: u64::MAX,
+        auto_up_to: None,
+        footprint: None,
+        parallel: false,
+        primes: |_, max| naive::primes(max),
+    },
+    Entry {
+        algorithm: Algorithm::NaiveParallel,
+        name: "naive-parallel",
+        time: "O(n^2 / log^2 n)",
+        space: "O(n / log n)",
+        max: u64::MAX,
+        auto_up_to: None,
+        footprint: None,
+        parallel: true,
+        primes: |opt, max| naive::parallel::primes(max, opt.threads()),
+    },
+];
+
+// The entry for an algorithm, with auto resolved to whichever algorithm it picks for max.
+pub fn lookup(algorithm: Algorithm, max: u64) -> &'static Entry {
+    let entry = match algorithm {
+        Algorithm::Auto => REGISTRY
+            .iter()
+            .find(|entry| entry.auto_up_to.is_some_and(|up_to| max <= up_to)),
+        algorithm => REGISTRY.iter().find(|entry| entry.algorithm == algorithm),
+    };
+    // Every algorithm has a row, and the last automatic row covers every u64.
+    entry.expect("algorithm missing from registry")
+}
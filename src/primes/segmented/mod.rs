@@ -0,0 +1,263 @@
+use crate::primes::memory::Bytes;
+use crate::primes::{sieve, Generator};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+// Small enough that a segment's elimination table stays in a typical L1 data cache while we mark
+// it. Machines with bigger caches may do better with larger segments, hence the --segment-size
+// option.
+pub const DEFAULT_SEGMENT_SIZE: u64 = 32 * 1024;
+
+// Rather than allocate an elimination table for the whole range like the sieve, we only sieve the
+// primes up to sqrt(max) up front, and then use them to eliminate composites from one fixed-size
+// segment of the range at a time.
+#[derive(Debug)]
+pub struct Primes {
+    max: u64,
+    next: u64,
+    low: u64,
+    done: bool,
+    segment_size: u64,
+    base: Vec<u64>,
+    // Reused for every segment, so that the same memory stays hot in the cache rather than us
+    // allocating a fresh table each time.
+    eliminated: Vec<bool>,
+    found: Vec<u64>,
+    index: usize,
+}
+
+// How many sieved segments the producer may get ahead of the consumer by. Just enough to keep
+// both sides busy, without buffering a big chunk of the range in memory.
+const STREAM_DEPTH: usize = 2;
+
+// The primes needed to sieve every segment up to max.
+pub fn base_primes(max: u64) -> Vec<u64> {
+    let root = max.isqrt();
+    if root < 2 {
+        return Vec::new();
+    }
+    sieve::primes(root).collect()
+}
+
+// How many bytes primes(max) will allocate for its base primes and a single segment.
+pub fn footprint(max: u64, segment_size: u64) -> Bytes {
+    let root = max.isqrt();
+    // The base primes are sieved with the plain sieve, which dominates until the range is huge.
+    let base = (root + 1).saturating_mul(mem::size_of::<bool>() as u64);
+    let segment = segment_size.saturating_mul(mem::size_of::<bool>() as u64);
+    Bytes(base.saturating_add(segment))
+}
+
+// By boxing it up and returning a trait object, we can use it anywhere a generator of u64's is
+// needed, so that all of our different implementations can have compatible types.
+pub fn primes(max: u64, segment_size: u64) -> Box<dyn Generator> {
+    Box::new(Streaming::new(max, segment_size))
+}
+
+// The primes in low..=high a segment at a time, as each segment's range along with the primes in
+// it, for anything that would rather work through them in blocks than one by one. Together the
+// ranges cover low..=high exactly (unless it's all below 2, when there are none), and only one
+// segment's primes are held at a time.
+pub fn segments(low: u64, high: u64, segment_size: u64) -> Segments {
+    Segments {
+        start: low,
+        high,
+        primes: Primes::new(high, low, base_primes(high)).segment_size(segment_size),
+    }
+}
+
+pub struct Segments {
+    // Where the next range starts. That's only ever different from the segment's own start for
+    // the first one, which Primes begins at 2 at the earliest.
+    start: u64,
+    high: u64,
+    primes: Primes,
+}
+
+impl Iterator for Segments {
+    type Item = (RangeInclusive<u64>, Vec<u64>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (low, found) = self.primes.next_segment()?;
+        let end = low
+            .saturating_add(self.primes.segment_size - 1)
+            .min(self.high);
+        let range = self.start.min(low)..=end;
+        self.start = end.saturating_add(1);
+        Some((range, found))
+    }
+}
+
+impl Primes {
+    // Start sieving from low, using base primes from a prior call to base_primes(max). Starting
+    // anywhere other than 2 is how an interrupted run is resumed.
+    pub fn new(max: u64, low: u64, base: Vec<u64>) -> Self {
+        let low = low.max(2);
+        Primes {
+            max,
+            next: low,
+            low,
+            done: low > max,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            base,
+            eliminated: Vec::new(),
+            found: Vec::new(),
+            index: 0,
+        }
+    }
+
+    // How many numbers each segment covers. Zero is treated as one.
+    pub fn segment_size(mut self, segment_size: u64) -> Self {
+        self.segment_size = segment_size.max(1);
+        self
+    }
+
+    // Sieves the next segment, returning where it starts and the primes in it, or None once we've
+    // passed max.
+    pub fn next_segment(&mut self) -> Option<(u64, Vec<u64>)> {
+        if self.done {
+            return None;
+        }
+        let low = self.low;
+        let high = low.saturating_add(self.segment_size - 1).min(self.max);
+        let eliminated = &mut self.eliminated;
+        eliminated.clear();
+        eliminated.resize((high - low + 1) as usize, false);
+
+        for &p in &self.base {
+            if p * p > high {
+                break;
+            }
+            // Start from the first multiple of p inside the segment, but never below p * p since
+            // anything smaller has a smaller prime factor and p itself isn't composite.
+            let mut multiple = match low.div_ceil(p).checked_mul(p) {
+                Some(m) => m.max(p * p),
+                None => continue,
+            };
+            while multiple <= high {
+                eliminated[(multiple - low) as usize] = true;
+                multiple = match multiple.checked_add(p) {
+                    Some(m) => m,
+                    None => break,
+                };
+            }
+        }
+
+        let found = eliminated
+            .iter()
+            .enumerate()
+            .filter(|(_, &eliminated)| !eliminated)
+            .map(|(offset, _)| low + offset as u64)
+            .collect();
+
+        match high.checked_add(1) {
+            Some(next) if high < self.max => self.low = next,
+            _ => self.done = true,
+        }
+        Some((low, found))
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(&p) = self.found.get(self.index) {
+                self.index += 1;
+                self.next = p.saturating_add(1);
+                return Some(p);
+            }
+            match self.next_segment() {
+                Some((low, found)) => {
+                    // Everything below the segment we just sieved has already been yielded.
+                    self.next = low;
+                    self.found = found;
+                    self.index = 0;
+                }
+                None => {
+                    self.next = self.max.saturating_add(1);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Generator for Primes {
+    fn cursor(&self) -> u64 {
+        self.next
+    }
+
+    fn limit(&self) -> u64 {
+        self.max
+    }
+}
+
+// Sieves segments on a separate thread and hands them over a small channel, so the primes in one
+// segment can be written out while the next one is being sieved. Even the base primes are sieved
+// over there, so output starts as soon as the first segment is done.
+pub struct Streaming {
+    max: u64,
+    next: u64,
+    rx: Receiver<(u64, Vec<u64>)>,
+    found: Vec<u64>,
+    index: usize,
+}
+
+impl Streaming {
+    pub fn new(max: u64, segment_size: u64) -> Self {
+        let (tx, rx) = sync_channel(STREAM_DEPTH);
+        thread::spawn(move || {
+            let mut primes = Primes::new(max, 2, base_primes(max)).segment_size(segment_size);
+            while let Some(segment) = primes.next_segment() {
+                // If the consumer has gone away nobody wants the rest, so stop sieving.
+                if tx.send(segment).is_err() {
+                    return;
+                }
+            }
+        });
+        Streaming {
+            max,
+            next: 2,
+            rx,
+            found: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl Iterator for Streaming {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(&p) = self.found.get(self.index) {
+                self.index += 1;
+                self.next = p.saturating_add(1);
+                return Some(p);
+            }
+            // The producer hangs up once it's sieved the last segment.
+            match self.rx.recv() {
+                Ok((low, found)) => {
+                    self.next = low;
+                    self.found = found;
+                    self.index = 0;
+                }
+                Err(_) => {
+                    self.next = self.max.saturating_add(1);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Generator for Streaming {
+    fn cursor(&self) -> u64 {
+        self.next
+    }
+
+    fn limit(&self) -> u64 {
+        self.max
+    }
+}
@@ -0,0 +1,88 @@
+use crate::primes::error::{Error, Result};
+use crate::primes::memory::Bytes;
+use crate::primes::query::parse_number;
+use crate::primes::segmented;
+use std::convert::TryFrom;
+use std::mem;
+
+// Every prime up to max, sieved once and kept sorted, so each question is a binary search. For a
+// range this size it's also far smaller than a sieve table, since only about 1 in ln(max) numbers
+// is prime.
+pub struct Primes {
+    max: u64,
+    primes: Vec<u64>,
+}
+
+// How many bytes Primes::new(max) will keep, on top of what the segmented sieve needs while
+// building it. pi(x) < 1.25506 x / ln(x) for every x > 1, so this never underestimates.
+pub fn footprint(max: u64) -> Bytes {
+    let count = if max < 3 {
+        1.0
+    } else {
+        1.25506 * max as f64 / (max as f64).ln()
+    };
+    Bytes((count * mem::size_of::<u64>() as f64) as u64)
+}
+
+impl Primes {
+    pub fn new(max: u64, segment_size: u64) -> Self {
+        Primes {
+            max,
+            primes: segmented::primes(max, segment_size).collect(),
+        }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    // Answers one line of the protocol: "contains n", "next n" or "count n". Like the query
+    // subcommand, numbers may be written as powers, but they have to be within max since that's
+    // all we've sieved.
+    pub fn answer(&self, line: &str) -> Result<Option<String>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let mut words = line.split_whitespace();
+        let request = words.next().unwrap_or_default();
+        let n = match (words.next(), words.next()) {
+            (Some(n), None) => self.number(n)?,
+            _ => {
+                return Err(Error::Parse {
+                    what: "request",
+                    value: line.to_string(),
+                })
+            }
+        };
+        let answer = match request {
+            "contains" => self.primes.binary_search(&n).is_ok().to_string(),
+            "next" => {
+                let next = self.primes.get(self.primes.partition_point(|&p| p < n));
+                let next = next.ok_or_else(|| {
+                    Error::NoAnswer(format!("there is no prime >= {} up to {}", n, self.max))
+                })?;
+                next.to_string()
+            }
+            "count" => self.primes.partition_point(|&p| p <= n).to_string(),
+            request => {
+                return Err(Error::Parse {
+                    what: "request",
+                    value: request.to_string(),
+                })
+            }
+        };
+        Ok(Some(answer))
+    }
+
+    fn number(&self, s: &str) -> Result<u64> {
+        let n = parse_number(s)?;
+        u64::try_from(n)
+            .ok()
+            .filter(|&n| n <= self.max)
+            .ok_or_else(|| Error::Argument {
+                argument: "request",
+                message: format!("must be at most --max {}", self.max),
+            })
+    }
+}
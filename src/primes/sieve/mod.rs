@@ -1,38 +1,100 @@
-#[derive(Debug)]
-pub struct Primes {
-    max: u64,
-    next: u64,
-    eliminated: Vec<bool>,
+use crate::primes::error::Error;
+use crate::primes::memory::Bytes;
+use crate::primes::Generator;
+use memmap2::MmapMut;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::str::FromStr;
+
+pub use crate::portable::sieve::{Eliminated, Primes, Table};
+
+// Where the sieve keeps its elimination table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // A byte per number, in memory.
+    Memory,
+    // A bit per number, in a memory-mapped temporary file, so the kernel can page it out to disk
+    // when the table doesn't fit in memory.
+    Mmap,
 }
 
-// By boxing it up and returning a trait object, we can use it anywhere an iterator of u64's is
-// needed, so that all of our different implementations can have compatible types.
-pub fn primes(max: u64) -> Box<dyn Iterator<Item = u64>> {
-    let mut eliminated = vec![false; (max + 1) as usize];
-    eliminated[0] = true;
-    eliminated[1] = true;
-    let iter = Primes {
-        max,
-        next: 1,
-        eliminated,
-    };
-    Box::new(iter)
-}
-
-impl Iterator for Primes {
-    type Item = u64;
-    fn next(&mut self) -> Option<u64> {
-        for n in self.next..=self.max {
-            if !self.eliminated[n as usize] {
-                let mut current = n + n;
-                while current <= self.max {
-                    self.eliminated[current as usize] = true;
-                    current += n;
-                }
-                self.next = n + 1;
-                return Some(n);
-            }
+impl FromStr for Backend {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(Backend::Memory),
+            "mmap" => Ok(Backend::Mmap),
+            _ => Err(Error::Parse {
+                what: "backend",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Memory => write!(f, "memory"),
+            Backend::Mmap => write!(f, "mmap"),
         }
-        None
+    }
+}
+
+// A bit per number in a memory-mapped file. The file is already unlinked, so it goes away with
+// us however we exit.
+pub struct Mapped {
+    map: MmapMut,
+}
+
+impl Mapped {
+    pub fn new(max: u64) -> io::Result<Self> {
+        let file = tempfile::tempfile()?;
+        // Extending the file leaves it full of zeros, i.e. with nothing eliminated yet, without
+        // having to write them.
+        file.set_len(max / 8 + 1)?;
+        // Safe as long as nothing else modifies the file while it's mapped, and nothing else can
+        // even open it, since it has no name.
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Mapped { map })
+    }
+}
+
+impl Eliminated for Mapped {
+    fn is_eliminated(&self, n: u64) -> bool {
+        self.map[(n / 8) as usize] & (1 << (n % 8)) != 0
+    }
+
+    fn eliminate(&mut self, n: u64) {
+        self.map[(n / 8) as usize] |= 1 << (n % 8);
+    }
+}
+
+// How many bytes primes(max) will allocate for its elimination table.
+pub fn footprint(max: u64) -> Bytes {
+    Bytes((max + 1).saturating_mul(mem::size_of::<bool>() as u64))
+}
+
+// By boxing it up and returning a trait object, we can use it anywhere a generator of u64's is
+// needed, so that all of our different implementations can have compatible types.
+pub fn primes(max: u64) -> Box<dyn Generator> {
+    // The table always covers 0 and 1, even when max is 0, since they're eliminated up front.
+    Box::new(Primes::with(max, vec![false; (max.max(1) + 1) as usize]))
+}
+
+// Like primes, but with the elimination table in a temporary file rather than memory. It's much
+// slower, but it only needs disk space, and an eighth as much of it.
+pub fn mapped(max: u64) -> io::Result<Box<dyn Generator>> {
+    Ok(Box::new(Primes::with(max, Mapped::new(max)?)))
+}
+
+impl<E: Eliminated> Generator for Primes<E> {
+    fn cursor(&self) -> u64 {
+        self.next_candidate()
+    }
+
+    fn limit(&self) -> u64 {
+        self.max()
     }
 }
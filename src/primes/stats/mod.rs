@@ -0,0 +1,90 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Summary statistics for a range of primes. Collecting them consumes the generator, so they work
+// the same for every algorithm.
+#[derive(Debug)]
+pub struct Stats {
+    pub max: u64,
+    pub count: u64,
+    pub sum: u128,
+    // The widest gap between consecutive primes, as (gap, the prime before it, the prime after it).
+    pub largest_gap: Option<(u64, u64, u64)>,
+    // decades[k] is how many primes fall in [10^k, 10^(k+1)).
+    pub decades: Vec<u64>,
+    pub elapsed: Duration,
+    // How many threads found them, for the parallel algorithms.
+    pub threads: Option<usize>,
+}
+
+impl Stats {
+    pub fn collect<I: Iterator<Item = u64>>(primes: I, max: u64) -> Self {
+        let started = Instant::now();
+        let mut stats = Stats {
+            max,
+            count: 0,
+            sum: 0,
+            largest_gap: None,
+            decades: Vec::new(),
+            elapsed: Duration::default(),
+            threads: None,
+        };
+        let mut previous = None;
+        for p in primes {
+            stats.count += 1;
+            stats.sum += u128::from(p);
+            if let Some(previous) = previous {
+                let gap = p - previous;
+                if stats
+                    .largest_gap
+                    .is_none_or(|(largest, _, _)| gap > largest)
+                {
+                    stats.largest_gap = Some((gap, previous, p));
+                }
+            }
+            previous = Some(p);
+
+            let decade = p.ilog10() as usize;
+            if stats.decades.len() <= decade {
+                stats.decades.resize(decade + 1, 0);
+            }
+            stats.decades[decade] += 1;
+        }
+        stats.elapsed = started.elapsed();
+        stats
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "count: {}", self.count)?;
+        writeln!(f, "sum: {}", self.sum)?;
+        match self.largest_gap {
+            Some((gap, before, after)) => {
+                writeln!(f, "largest gap: {} ({} to {})", gap, before, after)?
+            }
+            None => writeln!(f, "largest gap: none")?,
+        }
+        writeln!(f, "density per decade:")?;
+        for (k, &count) in self.decades.iter().enumerate() {
+            let low = 10u64.pow(k as u32);
+            // The last decade is cut short by max, so only count the part of it we covered.
+            let high = 10u64
+                .checked_pow(k as u32 + 1)
+                .map_or(self.max, |h| (h - 1).min(self.max));
+            let width = high - low + 1;
+            writeln!(
+                f,
+                "  {}..={}: {} primes, {:.4}%",
+                low,
+                high,
+                count,
+                count as f64 / width as f64 * 100.0
+            )?;
+        }
+        if let Some(threads) = self.threads {
+            writeln!(f, "threads: {}", threads)?;
+        }
+        write!(f, "elapsed: {:.3}s", self.elapsed.as_secs_f64())
+    }
+}
@@ -0,0 +1,354 @@
+use crate::error::IoError;
+use crate::{debug, info};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, Write};
+use std::mem;
+use std::path::PathBuf;
+
+// How lines compare: byte by byte, as in the C locale, or by the numbers they start with, like
+// sort -n, and either way round. Lines that are equal as numbers, or that aren't numbers at all,
+// which count as 0, are put in order byte by byte as a last resort, so there's only one right
+// answer, unless all that matters is the number, as for sort -u.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Order {
+    pub numeric: bool,
+    pub reverse: bool,
+}
+
+impl Order {
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        self.directed(self.key(a, b).then_with(|| a.cmp(b)))
+    }
+
+    // Without the last resort: lines with the same number are equal, with -n.
+    pub fn compare_keys(&self, a: &[u8], b: &[u8]) -> Ordering {
+        self.directed(self.key(a, b))
+    }
+
+    fn key(&self, a: &[u8], b: &[u8]) -> Ordering {
+        if self.numeric {
+            Number::parse(a).cmp(&Number::parse(b))
+        } else {
+            a.cmp(b)
+        }
+    }
+
+    fn directed(&self, ordering: Ordering) -> Ordering {
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+// The number a line starts with, after any blanks: an optional minus sign, then digits with
+// maybe a decimal point among them. Kept as its digits rather than parsed, so numbers of any
+// length compare exactly.
+#[derive(Debug, PartialEq, Eq)]
+struct Number<'a> {
+    negative: bool,
+    // Without leading zeros.
+    whole: &'a [u8],
+    // Without trailing zeros.
+    fraction: &'a [u8],
+}
+
+impl<'a> Number<'a> {
+    fn parse(line: &'a [u8]) -> Self {
+        let start = line
+            .iter()
+            .position(|b| !matches!(b, b' ' | b'\t'))
+            .unwrap_or(line.len());
+        let mut rest = &line[start..];
+        let negative = rest.first() == Some(&b'-');
+        if negative {
+            rest = &rest[1..];
+        }
+        let digits = |s: &'a [u8]| {
+            let end = s
+                .iter()
+                .position(|b| !b.is_ascii_digit())
+                .unwrap_or(s.len());
+            &s[..end]
+        };
+        let whole = digits(rest);
+        let fraction = match rest[whole.len()..].strip_prefix(b".") {
+            Some(after) => digits(after),
+            None => &[],
+        };
+        let zeros = whole.iter().take_while(|&&b| b == b'0').count();
+        let whole = &whole[zeros..];
+        let end = fraction.len() - fraction.iter().rev().take_while(|&&b| b == b'0').count();
+        let fraction = &fraction[..end];
+        Number {
+            // -0 is just 0.
+            negative: negative && !(whole.is_empty() && fraction.is_empty()),
+            whole,
+            fraction,
+        }
+    }
+
+    fn magnitude(&self, other: &Self) -> Ordering {
+        self.whole
+            .len()
+            .cmp(&other.whole.len())
+            .then_with(|| self.whole.cmp(other.whole))
+            .then_with(|| self.fraction.cmp(other.fraction))
+    }
+}
+
+impl Ord for Number<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude(other),
+            (true, true) => other.magnitude(self),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Number<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// How much each line costs in memory besides its bytes, near enough.
+const OVERHEAD: usize = mem::size_of::<Vec<u8>>();
+
+// How many runs are merged at once. Any more are merged a batch at a time into longer runs
+// first, so as not to have too many files open, as GNU sort does.
+const FAN_IN: usize = 16;
+
+// Sorts lines, like sort, in as much memory as it's allowed. Input that doesn't fit is sorted
+// a memory's worth at a time, each sorted run spilled to a temporary file, then the runs are
+// merged back together, reading a line at a time from each.
+#[derive(Debug, Clone)]
+pub struct Sorter {
+    pub order: Order,
+    // Only the first of each set of lines that are the same.
+    pub unique: bool,
+    // Roughly how many bytes of lines to hold at once.
+    pub budget: usize,
+    // Where to spill to; the system's temporary directory if None. The files have no names, so
+    // go away by themselves, however sorting ends.
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl Sorter {
+    pub fn sort<R: BufRead, W: Write>(&self, mut input: R, out: &mut W) -> Result<(), IoError> {
+        let mut runs = Vec::new();
+        let mut lines = Vec::new();
+        let mut held = 0;
+        loop {
+            let mut line = Vec::new();
+            if input
+                .read_until(b'\n', &mut line)
+                .map_err(IoError::context("reading stdin"))?
+                == 0
+            {
+                break;
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            held += line.len() + OVERHEAD;
+            lines.push(line);
+            if held >= self.budget {
+                runs.push(self.spill(&mut lines)?);
+                held = 0;
+            }
+        }
+
+        let write = |err| IoError::new("writing to stdout", err);
+        if runs.is_empty() {
+            self.sort_run(&mut lines);
+            for line in &lines {
+                out.write_all(line).map_err(write)?;
+                out.write_all(b"\n").map_err(write)?;
+            }
+            return out.flush().map_err(write);
+        }
+        if !lines.is_empty() {
+            runs.push(self.spill(&mut lines)?);
+        }
+        // Runs stay in the order of the input, so the first of the lines that are the same is
+        // still the first.
+        while runs.len() > FAN_IN {
+            debug!("merging {} runs {} at a time", runs.len(), FAN_IN);
+            let mut merged = Vec::new();
+            let mut rest = runs.into_iter().peekable();
+            while rest.peek().is_some() {
+                let batch: Vec<File> = rest.by_ref().take(FAN_IN).collect();
+                let mut file = self.temp_file()?;
+                let mut writer = BufWriter::new(&mut file);
+                self.merge(batch, &mut writer)
+                    .and_then(|()| writer.flush())
+                    .map_err(|err| IoError::new("spilling to a temporary file", err))?;
+                drop(writer);
+                merged.push(rewound(file)?);
+            }
+            runs = merged;
+        }
+        info!("merging {} runs", runs.len());
+        self.merge(runs, out).map_err(write)?;
+        out.flush().map_err(write)
+    }
+
+    // With unique, of the lines that are the same, the one that came first is kept, as sort -u
+    // does, so they're sorted stably, by key alone.
+    fn sort_run(&self, lines: &mut Vec<Vec<u8>>) {
+        if self.unique {
+            lines.sort_by(|a, b| self.order.compare_keys(a, b));
+            lines.dedup_by(|a, b| self.same(a, b));
+        } else {
+            lines.sort_unstable_by(|a, b| self.order.compare(a, b));
+        }
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        if self.unique {
+            self.order.compare_keys(a, b)
+        } else {
+            self.order.compare(a, b)
+        }
+    }
+
+    fn same(&self, a: &[u8], b: &[u8]) -> bool {
+        self.order.compare_keys(a, b) == Ordering::Equal
+    }
+
+    // Sorts lines, writes them to a temporary file and hands it back, ready to be read from the
+    // start, with lines left empty for the next run.
+    fn spill(&self, lines: &mut Vec<Vec<u8>>) -> Result<File, IoError> {
+        self.sort_run(lines);
+        debug!("spilling a run of {} lines", lines.len());
+        let mut file = self.temp_file()?;
+        let mut writer = BufWriter::new(&mut file);
+        let context = |err| IoError::new("spilling to a temporary file", err);
+        for line in lines.drain(..) {
+            writer.write_all(&line).map_err(context)?;
+            writer.write_all(b"\n").map_err(context)?;
+        }
+        writer.flush().map_err(context)?;
+        drop(writer);
+        rewound(file)
+    }
+
+    fn temp_file(&self) -> Result<File, IoError> {
+        let file = match &self.temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir),
+            None => tempfile::tempfile(),
+        };
+        file.map_err(IoError::context("creating a temporary file"))
+    }
+
+    // Writes the lines of every run, each already sorted, in order. Runs that come up with the
+    // same line are taken in turn, so the earlier run's copy comes first, and with unique, is
+    // the one kept.
+    fn merge<W: Write>(&self, runs: Vec<File>, out: &mut W) -> io::Result<()> {
+        let mut readers: Vec<BufReader<File>> = runs.into_iter().map(BufReader::new).collect();
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(line) = next_line(reader)? {
+                heap.push(Head {
+                    line,
+                    run,
+                    sorter: self,
+                });
+            }
+        }
+        let mut last: Option<Vec<u8>> = None;
+        while let Some(Head { line, run, .. }) = heap.pop() {
+            if let Some(next) = next_line(&mut readers[run])? {
+                heap.push(Head {
+                    line: next,
+                    run,
+                    sorter: self,
+                });
+            }
+            if self.unique {
+                if let Some(last) = &last {
+                    if self.same(last, &line) {
+                        continue;
+                    }
+                }
+            }
+            out.write_all(&line)?;
+            out.write_all(b"\n")?;
+            if self.unique {
+                last = Some(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn rewound(mut file: File) -> Result<File, IoError> {
+    file.rewind()
+        .map_err(IoError::context("rewinding a temporary file"))?;
+    Ok(file)
+}
+
+fn next_line<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+// The next line from one of the runs being merged. The heap puts its greatest first, so these
+// count the line that comes first as greatest.
+struct Head<'s> {
+    line: Vec<u8>,
+    run: usize,
+    sorter: &'s Sorter,
+}
+
+impl Ord for Head<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorter
+            .compare(&other.line, &self.line)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head<'_> {}
+
+// A size like sort -S takes: a number of bytes, or of K, M or G, which are powers of 1024.
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, ""),
+    };
+    let shift = match unit {
+        "" => 0,
+        "K" | "k" => 10,
+        "M" | "m" => 20,
+        "G" | "g" => 30,
+        _ => return Err(format!("unknown unit {}, expected K, M or G", unit)),
+    };
+    let n: usize = digits.parse().map_err(|err| format!("{}", err))?;
+    n.checked_mul(1 << shift)
+        .ok_or_else(|| format!("{} is too big", s))
+}
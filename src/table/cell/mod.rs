@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthStr;
+
+// The eight colors every terminal has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    // Its SGR code for the foreground.
+    fn code(self) -> u8 {
+        30 + self as u8
+    }
+}
+
+// What goes in a table: some text, and how to style it on a terminal. The styling is kept apart
+// from the text rather than written into it, so the table can pad the text by how wide it
+// actually is, and leave the styling out where it doesn't belong, e.g. in markdown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cell<'a> {
+    text: Cow<'a, str>,
+    color: Option<Color>,
+    bold: bool,
+}
+
+impl<'a> Cell<'a> {
+    pub fn new<T: Into<Cow<'a, str>>>(text: T) -> Self {
+        Cell {
+            text: text.into(),
+            color: None,
+            bold: false,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Whether its text is borrowed from somewhere else, rather than a copy of its own.
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.text, Cow::Borrowed(_))
+    }
+
+    // Swaps the text for something else, keeping the styling.
+    pub(crate) fn replace_text(&mut self, text: String) {
+        self.text = Cow::Owned(text);
+    }
+
+    // Its color, if it has one, and whether it's bold.
+    pub(crate) fn style(&self) -> (Option<Color>, bool) {
+        (self.color, self.bold)
+    }
+
+    // The escape sequence that turns on its styling, if it has any. Writing RESET afterwards
+    // turns it off again.
+    pub(crate) fn sgr(&self) -> Option<String> {
+        let codes: Vec<String> = self
+            .bold
+            .then(|| "1".to_string())
+            .into_iter()
+            .chain(self.color.map(|color| color.code().to_string()))
+            .collect();
+        (!codes.is_empty()).then(|| format!("\x1b[{}m", codes.join(";")))
+    }
+}
+
+pub(crate) const RESET: &str = "\x1b[0m";
+
+impl<'a> From<&'a str> for Cell<'a> {
+    fn from(text: &'a str) -> Self {
+        Cell::new(text)
+    }
+}
+
+impl From<String> for Cell<'_> {
+    fn from(text: String) -> Self {
+        Cell::new(text)
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Cell<'a> {
+    fn from(text: Cow<'a, str>) -> Self {
+        Cell::new(text)
+    }
+}
+
+// Text without any ANSI escape sequences in it, i.e. just what a terminal would show, for text
+// that arrives already colored. That's CSI sequences like \x1b[1;31m, which end at their first
+// byte from @ to ~; OSC sequences like hyperlinks, which end with BEL or ESC \; and the two
+// character escapes.
+pub fn visible(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+    let mut shown = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            shown.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(shown)
+}
+
+// How many columns text takes up on a terminal, which isn't its length in bytes or even chars:
+// CJK characters and most emoji take two, and combining marks and escape sequences none. Text
+// with line breaks in it is as wide as its widest line.
+pub fn width_of(text: &str) -> usize {
+    text.lines()
+        .map(|line| visible(line).width())
+        .max()
+        .unwrap_or(0)
+}
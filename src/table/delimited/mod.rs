@@ -0,0 +1,111 @@
+use std::io::{self, BufRead};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("reading line {line}")]
+    Io {
+        line: usize,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("the quote opened on line {line} is never closed")]
+    Unclosed { line: usize },
+}
+
+// The records in delimited text like CSV or TSV, one per line, split into fields at each
+// delimiter. With quoting, a field can be wrapped in double quotes as RFC 4180 has it, so that it
+// can hold delimiters and line breaks, with "" for a quote inside it. Without, quotes are just
+// characters. Either way, a line ends with \n or \r\n, and blank lines are skipped.
+pub struct Records<R> {
+    input: R,
+    delimiter: char,
+    quoting: bool,
+    line: usize,
+    buf: String,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(input: R, delimiter: char, quoting: bool) -> Self {
+        Records {
+            input,
+            delimiter,
+            quoting,
+            line: 0,
+            buf: String::new(),
+        }
+    }
+
+    // The next line, or None at the end of the input.
+    fn read_line(&mut self) -> Result<Option<&str>, Error> {
+        self.buf.clear();
+        let read = self
+            .input
+            .read_line(&mut self.buf)
+            .map_err(|source| Error::Io {
+                line: self.line + 1,
+                source,
+            })?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        let line = self.buf.strip_suffix('\n').unwrap_or(&self.buf);
+        Ok(Some(line.strip_suffix('\r').unwrap_or(line)))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Vec<String>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (delimiter, quoting) = (self.delimiter, self.quoting);
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        // Where the quote we're inside of, if any, was opened.
+        let mut quoted: Option<usize> = None;
+        loop {
+            let line = self.line + 1;
+            let text = match self.read_line() {
+                Ok(Some(text)) => text,
+                Ok(None) => match quoted {
+                    Some(line) => return Some(Err(Error::Unclosed { line })),
+                    None => return None,
+                },
+                Err(err) => return Some(Err(err)),
+            };
+            if quoted.is_none() && text.is_empty() {
+                continue;
+            }
+            // A quoted field that carries on from the line before kept its line break.
+            if quoted.is_some() {
+                field.push('\n');
+            }
+            let mut chars = text.chars().peekable();
+            // Only a quote at the very start of a field opens one; anywhere else it's literal.
+            let mut at_start = quoted.is_none();
+            while let Some(c) = chars.next() {
+                match (c, quoted) {
+                    ('"', Some(_)) if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    ('"', Some(_)) => quoted = None,
+                    (c, Some(_)) => field.push(c),
+                    ('"', None) if quoting && at_start => quoted = Some(line),
+                    (c, None) if c == delimiter => {
+                        fields.push(std::mem::take(&mut field));
+                        at_start = true;
+                        continue;
+                    }
+                    (c, None) => field.push(c),
+                }
+                at_start = false;
+            }
+            if quoted.is_none() {
+                fields.push(field);
+                return Some(Ok(fields));
+            }
+        }
+    }
+}
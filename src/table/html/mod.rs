@@ -0,0 +1,143 @@
+use super::cell::{visible, Cell, Color};
+use super::{Align, Table};
+use std::fmt;
+
+// A table written as HTML, to go in a report or a dashboard: the header in a thead, and each
+// group of rows in a tbody of its own. All the text is escaped, so a cell can say <b> without
+// being taken for markup. Columns on the right or in the middle say so with their style, and
+// styled cells keep their color and boldness. Borders and limits are left to the stylesheet.
+pub struct Html<'t, 'a> {
+    table: &'t Table<'a>,
+    class: Option<&'t str>,
+}
+
+impl<'a> Table<'a> {
+    pub fn html(&self) -> Html<'_, 'a> {
+        Html {
+            table: self,
+            class: None,
+        }
+    }
+}
+
+impl<'t> Html<'t, '_> {
+    // A class for the table element, for a stylesheet to pick it out by.
+    pub fn class(mut self, class: &'t str) -> Self {
+        self.class = Some(class);
+        self
+    }
+}
+
+impl fmt::Display for Html<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let table = self.table;
+        let aligns = table.alignments();
+        match self.class {
+            Some(class) => writeln!(f, "<table class=\"{}\">", Escaped(class))?,
+            None => writeln!(f, "<table>")?,
+        }
+        if let Some(header) = &table.header {
+            writeln!(f, "  <thead>")?;
+            row(f, "th", header, &aligns)?;
+            writeln!(f, "  </thead>")?;
+        }
+        let mut bounds = vec![0];
+        bounds.extend(&table.groups);
+        bounds.push(table.rows.len());
+        for group in bounds.windows(2).filter(|group| group[0] < group[1]) {
+            writeln!(f, "  <tbody>")?;
+            for cells in &table.rows[group[0]..group[1]] {
+                row(f, "td", cells, &aligns)?;
+            }
+            writeln!(f, "  </tbody>")?;
+        }
+        writeln!(f, "</table>")
+    }
+}
+
+// A tr of th or td elements, padded out with empty ones to the width of the table, like the
+// short rows of a text table.
+fn row(
+    f: &mut fmt::Formatter<'_>,
+    element: &str,
+    cells: &[Cell<'_>],
+    aligns: &[Align],
+) -> fmt::Result {
+    write!(f, "    <tr>")?;
+    for (i, align) in aligns.iter().enumerate() {
+        let style = match align {
+            Align::Left => None,
+            // There's no lining up decimal points in CSS that browsers agree on.
+            Align::Right | Align::Decimal => Some("text-align: right"),
+            Align::Center => Some("text-align: center"),
+        };
+        match style {
+            Some(style) => write!(f, "<{} style=\"{}\">", element, style)?,
+            None => write!(f, "<{}>", element)?,
+        }
+        if let Some(cell) = cells.get(i) {
+            content(f, cell)?;
+        }
+        write!(f, "</{}>", element)?;
+    }
+    writeln!(f, "</tr>")
+}
+
+// A cell's text, escaped, with a <br> for each line break and any styling turned into markup.
+// Escape sequences mean nothing to a browser, so they're dropped.
+fn content(f: &mut fmt::Formatter<'_>, cell: &Cell<'_>) -> fmt::Result {
+    let (color, bold) = cell.style();
+    if let Some(color) = color {
+        write!(f, "<span style=\"color: {}\">", css(color))?;
+    }
+    if bold {
+        write!(f, "<strong>")?;
+    }
+    for (n, line) in visible(cell.text()).lines().enumerate() {
+        if n > 0 {
+            write!(f, "<br>")?;
+        }
+        write!(f, "{}", Escaped(line))?;
+    }
+    if bold {
+        write!(f, "</strong>")?;
+    }
+    if color.is_some() {
+        write!(f, "</span>")?;
+    }
+    Ok(())
+}
+
+fn css(color: Color) -> &'static str {
+    match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::White => "white",
+    }
+}
+
+// Text with the characters that mean something in HTML, in an element or an attribute, escaped.
+struct Escaped<'s>(&'s str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.0;
+        while let Some(i) = rest.find(&['&', '<', '>', '"', '\''][..]) {
+            f.write_str(&rest[..i])?;
+            f.write_str(match rest.as_bytes()[i] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'"' => "&quot;",
+                _ => "&#39;",
+            })?;
+            rest = &rest[i + 1..];
+        }
+        f.write_str(rest)
+    }
+}
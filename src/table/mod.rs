@@ -0,0 +1,853 @@
+pub mod cell;
+pub mod delimited;
+pub mod html;
+pub mod row;
+pub mod stream;
+
+use self::cell::{visible, width_of, Cell, RESET};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use unicode_width::UnicodeWidthChar;
+
+// Rows of cells, written with each column padded to its widest cell so the columns line up, like
+// column -t. Cells hold Cows so that static strings can go in without being copied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table<'a> {
+    header: Option<Vec<Cell<'a>>>,
+    rows: Vec<Vec<Cell<'a>>>,
+    // The rows that start a new group, with a line above them to set them apart from the last.
+    groups: Vec<usize>,
+    // Only for the columns that were told how to align; the rest work it out from their cells.
+    aligns: Vec<Option<Align>>,
+    // How wide each column may get, for those that have a limit.
+    limits: Vec<Option<(usize, Overflow)>>,
+    // How to write the numbers in each column told how to.
+    numbers: Vec<Option<Numbers>>,
+    border: Border,
+}
+
+// What to do with a cell too wide for its column: cut it short with an ellipsis, or wrap it onto
+// as many lines as it takes, between words where it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Truncate,
+    Wrap,
+}
+
+// Which side of its column a cell sits on. Center leans left when the padding won't split evenly.
+// Decimal lines up the decimal points of the numbers in the column, with anything that isn't a
+// number on the right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+    Decimal,
+}
+
+impl FromStr for Align {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Align::Left),
+            "right" => Ok(Align::Right),
+            "center" => Ok(Align::Center),
+            "decimal" => Ok(Align::Decimal),
+            _ => Err(format!(
+                "unknown alignment {}, expected left, right, center or decimal",
+                s
+            )),
+        }
+    }
+}
+
+// How to write the numbers in a column: with a comma between each three digits of the whole
+// part, as in 1,234,567, and to a fixed number of decimal places, rounding half up or padding
+// with zeros to get there. Written e.g. "," or ".2" or ",.2", like a Python format spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Numbers {
+    pub separators: bool,
+    pub decimals: Option<usize>,
+}
+
+impl FromStr for Numbers {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (separators, rest) = match s.strip_prefix(',') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let decimals = match rest.strip_prefix('.') {
+            Some(places) => Some(
+                places
+                    .parse()
+                    .map_err(|_| format!("expected a number of decimal places, not {}", places))?,
+            ),
+            None if rest.is_empty() && separators => None,
+            None => return Err(format!("expected , and/or .<places>, not {}", s)),
+        };
+        Ok(Numbers {
+            separators,
+            decimals,
+        })
+    }
+}
+
+impl Numbers {
+    // A number written this way, or None if it isn't one. A sign and a percent sign stay as they
+    // were. This works on the digits rather than going through a float, so a count with more
+    // digits than an f64 holds keeps all of them.
+    fn format(self, text: &str) -> Option<String> {
+        if !looks_numeric(text) {
+            return None;
+        }
+        let text = text.trim();
+        let (sign, rest) = match text.strip_prefix(&['-', '+'][..]) {
+            Some(rest) => (&text[..1], rest),
+            None => ("", text),
+        };
+        let (rest, percent) = match rest.strip_suffix('%') {
+            Some(rest) => (rest, "%"),
+            None => (rest, ""),
+        };
+        let digits: String = rest
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+        let mut whole = if whole.is_empty() { "0" } else { whole }.to_string();
+        let mut fraction = fraction.to_string();
+        if let Some(places) = self.decimals {
+            if fraction.len() > places {
+                let up = fraction.as_bytes()[places] >= b'5';
+                fraction.truncate(places);
+                if up {
+                    round_up(&mut whole, &mut fraction);
+                }
+            } else {
+                fraction.extend(std::iter::repeat_n('0', places - fraction.len()));
+            }
+        }
+        // Rounding can leave nothing but zeros, which -0 would only make look like it hadn't.
+        let zero = whole.bytes().chain(fraction.bytes()).all(|b| b == b'0');
+        let sign = if zero && sign == "-" { "" } else { sign };
+        if self.separators {
+            whole = separated(&whole);
+        }
+        Some(if fraction.is_empty() {
+            format!("{}{}{}", sign, whole, percent)
+        } else {
+            format!("{}{}.{}{}", sign, whole, fraction, percent)
+        })
+    }
+}
+
+// Adds one in the last place of a number split at its decimal point, carrying as far as it has to.
+fn round_up(whole: &mut String, fraction: &mut String) {
+    let mut digits: Vec<u8> = whole.bytes().chain(fraction.bytes()).collect();
+    let mut carry = true;
+    for digit in digits.iter_mut().rev() {
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            carry = false;
+            break;
+        }
+    }
+    if carry {
+        digits.insert(0, b'1');
+    }
+    let point = digits.len() - fraction.len();
+    let digits = String::from_utf8(digits).expect("only ever digits");
+    *fraction = digits[point..].to_string();
+    *whole = digits[..point].to_string();
+}
+
+// The whole part of a number with a comma between each three digits, counting from the right.
+fn separated(whole: &str) -> String {
+    let mut separated = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i).is_multiple_of(3) {
+            separated.push(',');
+        }
+        separated.push(c);
+    }
+    separated
+}
+
+// How sort compares cells: as text, or by the numbers in them. Cells that aren't numbers sort
+// after those that are, either way round, and among themselves as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Text,
+    Numeric,
+}
+
+// The space between one column and the next, when there's no border between them.
+const GAP: &str = "  ";
+
+// What goes around and between the cells: nothing but space; +, - and | as in
+// +------+-------+
+// | name | count |
+// +------+-------+
+// the same in unicode box drawing characters; or the pipes of a markdown table. Markdown has
+// nothing to draw between groups with, and insists on a header, so without one the first row
+// stands in for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Border {
+    #[default]
+    None,
+    Ascii,
+    Unicode,
+    Markdown,
+}
+
+impl FromStr for Border {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Border::None),
+            "ascii" => Ok(Border::Ascii),
+            "unicode" => Ok(Border::Unicode),
+            "markdown" => Ok(Border::Markdown),
+            _ => Err(format!(
+                "unknown border {}, expected none, ascii, unicode or markdown",
+                s
+            )),
+        }
+    }
+}
+
+// The left edge, fill, crossing and right edge of a horizontal line.
+type Rule = [&'static str; 4];
+
+// How a border draws each part of the table. sides are the left edge, the divider between
+// cells and the right edge of each row, and inset how much space they leave either side of a
+// cell.
+struct Drawing {
+    top: Option<Rule>,
+    under_header: Option<Rule>,
+    between_groups: Option<Rule>,
+    bottom: Option<Rule>,
+    sides: [&'static str; 3],
+    inset: usize,
+}
+
+impl Border {
+    fn drawing(self) -> Drawing {
+        match self {
+            Border::None => Drawing {
+                top: None,
+                under_header: Some(["", "-", GAP, ""]),
+                between_groups: Some(["", "-", GAP, ""]),
+                bottom: None,
+                sides: ["", GAP, ""],
+                inset: 0,
+            },
+            Border::Ascii => Drawing {
+                top: Some(["+", "-", "+", "+"]),
+                under_header: Some(["+", "-", "+", "+"]),
+                between_groups: Some(["+", "-", "+", "+"]),
+                bottom: Some(["+", "-", "+", "+"]),
+                sides: ["| ", " | ", " |"],
+                inset: 1,
+            },
+            Border::Unicode => Drawing {
+                top: Some(["┌", "─", "┬", "┐"]),
+                under_header: Some(["├", "─", "┼", "┤"]),
+                between_groups: Some(["├", "─", "┼", "┤"]),
+                bottom: Some(["└", "─", "┴", "┘"]),
+                sides: ["│ ", " │ ", " │"],
+                inset: 1,
+            },
+            // Only the sides; the rest is up to Table::markdown.
+            Border::Markdown => Drawing {
+                top: None,
+                under_header: None,
+                between_groups: None,
+                bottom: None,
+                sides: ["| ", " | ", " |"],
+                inset: 1,
+            },
+        }
+    }
+}
+
+impl<'a> Table<'a> {
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    // The row that names the columns, written above the rest with a line beneath it.
+    pub fn header<I>(&mut self, cells: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Cell<'a>>,
+    {
+        self.header = Some(cells.into_iter().map(Into::into).collect());
+        self
+    }
+
+    // Sets the rows from here on apart from those before, with a line between them. A group
+    // with nothing before it has nothing to be set apart from, so gets no line.
+    pub fn group(&mut self) -> &mut Self {
+        if !self.rows.is_empty() && self.groups.last() != Some(&self.rows.len()) {
+            self.groups.push(self.rows.len());
+        }
+        self
+    }
+
+    // Rows don't have to be the same length; a short one is padded out with empty cells.
+    pub fn row<I>(&mut self, cells: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Cell<'a>>,
+    {
+        let row = self.formatted(cells.into_iter().map(Into::into).collect());
+        self.rows.push(row);
+        self
+    }
+
+    // Writes the numbers in a column this way, those in the rows so far and any that come
+    // after. The header's left alone, and so are any cells that aren't numbers.
+    pub fn numbers(&mut self, column: usize, numbers: Numbers) -> &mut Self {
+        if self.numbers.len() <= column {
+            self.numbers.resize(column + 1, None);
+        }
+        self.numbers[column] = Some(numbers);
+        for row in &mut self.rows {
+            if let Some(cell) = row.get_mut(column) {
+                if let Some(text) = numbers.format(&visible(cell.text())) {
+                    cell.replace_text(text);
+                }
+            }
+        }
+        self
+    }
+
+    fn formatted(&self, mut row: Vec<Cell<'a>>) -> Vec<Cell<'a>> {
+        for (cell, numbers) in row.iter_mut().zip(&self.numbers) {
+            if let Some(text) = numbers.and_then(|numbers| numbers.format(&visible(cell.text()))) {
+                cell.replace_text(text);
+            }
+        }
+        row
+    }
+
+    // Orders the rows by a column's cells, keeping rows with equal cells in the order they came.
+    // Each group is sorted on its own, so the groups stay as they were. A row too short to have
+    // the column sorts as though its cell were empty.
+    pub fn sort(&mut self, column: usize, compare: Compare, descending: bool) -> &mut Self {
+        let key = |row: &Vec<Cell<'a>>| -> (Option<f64>, String) {
+            let text = row
+                .get(column)
+                .map_or(String::new(), |cell| visible(cell.text()).into_owned());
+            let number = match compare {
+                Compare::Numeric => number_in(&text),
+                Compare::Text => None,
+            };
+            (number, text)
+        };
+        let mut bounds = vec![0];
+        bounds.extend(&self.groups);
+        bounds.push(self.rows.len());
+        for group in bounds.windows(2) {
+            let rows = &mut self.rows[group[0]..group[1]];
+            // Working out each key once, rather than on every comparison.
+            let mut keyed: Vec<_> = rows
+                .iter_mut()
+                .map(|row| (key(row), std::mem::take(row)))
+                .collect();
+            keyed.sort_by(|((a_number, a_text), _), ((b_number, b_text), _)| {
+                let order = match (a_number, b_number) {
+                    (Some(a), Some(b)) => a.total_cmp(b),
+                    // Whichever way round the numbers go, the rest come after them.
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => a_text.cmp(b_text),
+                };
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+            for (row, (_, sorted)) in rows.iter_mut().zip(keyed) {
+                *row = sorted;
+            }
+        }
+        self
+    }
+
+    pub fn border(&mut self, border: Border) -> &mut Self {
+        self.border = border;
+        self
+    }
+
+    pub fn align(&mut self, column: usize, align: Align) -> &mut Self {
+        if self.aligns.len() <= column {
+            self.aligns.resize(column + 1, None);
+        }
+        self.aligns[column] = Some(align);
+        self
+    }
+
+    // How each column aligns: as it was told, or otherwise right if all its cells look like
+    // numbers, so their digits line up, and left if not.
+    pub fn alignments(&self) -> Vec<Align> {
+        (0..self.widths().len())
+            .map(|column| self.alignment(column))
+            .collect()
+    }
+
+    fn alignment(&self, column: usize) -> Align {
+        match self.aligns.get(column).copied().flatten() {
+            Some(align) => align,
+            None if self.numeric(column) => Align::Right,
+            None => Align::Left,
+        }
+    }
+
+    // Empty cells don't count either way, but a column of nothing but them isn't numeric. Nor
+    // does the header, which names the numbers rather than being one.
+    fn numeric(&self, column: usize) -> bool {
+        let mut cells = self
+            .rows
+            .iter()
+            .filter_map(|row| row.get(column))
+            .map(|cell| visible(cell.text()))
+            .filter(|text| !text.trim().is_empty())
+            .peekable();
+        cells.peek().is_some() && cells.all(|text| looks_numeric(&text))
+    }
+
+    pub fn head(&self) -> Option<&[Cell<'a>]> {
+        self.header.as_deref()
+    }
+
+    pub fn rows(&self) -> &[Vec<Cell<'a>>] {
+        &self.rows
+    }
+
+    // Keeps a column to at most width. Markdown is meant to be pasted somewhere else, to be
+    // laid out there, so it ignores this.
+    pub fn max_width(&mut self, column: usize, width: usize, overflow: Overflow) -> &mut Self {
+        if self.limits.len() <= column {
+            self.limits.resize(column + 1, None);
+        }
+        self.limits[column] = Some((width, overflow));
+        self
+    }
+
+    // Narrows the widest columns, as little as it can, until the whole table fits in width,
+    // borders and all, e.g. to keep one long path from pushing everything off the side of the
+    // terminal. Columns never get narrower than one, so a table with a great many of them may
+    // still not fit.
+    pub fn fit(&mut self, width: usize, overflow: Overflow) -> &mut Self {
+        let mut widths = self.widths();
+        let columns = widths.len();
+        if columns == 0 {
+            return self;
+        }
+        let [left, middle, right] = self.border.drawing().sides;
+        let around = width_of(left) + width_of(middle) * (columns - 1) + width_of(right);
+        let available = width.saturating_sub(around).max(columns);
+        while widths.iter().sum::<usize>() > available {
+            let excess = widths.iter().sum::<usize>() - available;
+            let widest = *widths.iter().max().expect("there's at least one column");
+            if widest <= 1 {
+                break;
+            }
+            // Bring every column that's widest down together, no further than the next widest.
+            let count = widths.iter().filter(|&&w| w == widest).count();
+            let next = widths
+                .iter()
+                .copied()
+                .filter(|&w| w < widest)
+                .max()
+                .unwrap_or(1);
+            let target = widest
+                .saturating_sub(excess.div_ceil(count))
+                .max(next)
+                .max(1);
+            for (column, w) in widths.iter_mut().enumerate() {
+                if *w == widest {
+                    *w = target;
+                    self.max_width(column, target, overflow);
+                }
+            }
+        }
+        self
+    }
+
+    // How wide each column is drawn: as wide as its widest cell, unless that's over its limit.
+    // Lining up decimal points can take more than that, e.g. for 1.25 and 100.
+    pub fn widths(&self) -> Vec<usize> {
+        let mut widths = column_widths(self.header.iter().chain(&self.rows));
+        for (column, width) in widths.iter_mut().enumerate() {
+            if let Some((whole, fraction)) = self.decimal(column) {
+                *width = (*width).max(whole + fraction);
+            }
+        }
+        for (width, limit) in widths.iter_mut().zip(&self.limits) {
+            if let Some((limit, _)) = limit {
+                *width = (*width).min(*limit);
+            }
+        }
+        widths
+    }
+
+    // For a column that lines up decimal points, how wide the widest of its numbers are either
+    // side of the point.
+    fn decimal(&self, column: usize) -> Option<(usize, usize)> {
+        if self.alignment(column) != Align::Decimal {
+            return None;
+        }
+        let parts = self
+            .rows
+            .iter()
+            .filter_map(|row| row.get(column))
+            .flat_map(|cell| cell.text().lines())
+            .filter_map(around_point);
+        Some(parts.fold((0, 0), |(whole, fraction), (w, f)| {
+            (whole.max(w), fraction.max(f))
+        }))
+    }
+
+    // How wide the widest fraction of each column is, for lining up their decimal points; 0 for
+    // columns that don't.
+    fn fractions(&self, columns: usize) -> Vec<usize> {
+        (0..columns)
+            .map(|column| self.decimal(column).map_or(0, |(_, fraction)| fraction))
+            .collect()
+    }
+
+    // A GitHub flavored markdown pipe table, with the alignment of each column marked under the
+    // header. Cells are escaped so a | doesn't end them early, with <br> for line breaks, which
+    // markdown cells can't hold, and lose any styling, which markdown can't show.
+    fn markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (header, rows) = match (&self.header, self.rows.split_first()) {
+            (Some(header), _) => (header, &self.rows[..]),
+            (None, Some((first, rest))) => (first, rest),
+            (None, None) => return Ok(()),
+        };
+        let header = escape_markdown(header);
+        let rows: Vec<Vec<Cell<'_>>> = rows.iter().map(|row| escape_markdown(row)).collect();
+        let widths = markdown_widths(std::iter::once(&header).chain(&rows));
+        let aligns = self.alignments();
+        let drawing = self.border.drawing();
+
+        row_of(f, &header, &widths, &aligns, &[], &[], &drawing)?;
+        markers(f, &widths, &aligns)?;
+        for row in &rows {
+            row_of(f, row, &widths, &aligns, &[], &[], &drawing)?;
+        }
+        Ok(())
+    }
+}
+
+fn escape_markdown<'a>(row: &'a [Cell<'_>]) -> Vec<Cell<'a>> {
+    row.iter()
+        .map(|cell| {
+            let text = visible(cell.text());
+            if text.contains(&['|', '\n'][..]) {
+                Cell::new(text.replace('|', "\\|").replace('\n', "<br>"))
+            } else {
+                Cell::new(text)
+            }
+        })
+        .collect()
+}
+
+// The markers need at least one dash between any colons.
+fn markdown_widths<'a, 'b: 'a, I>(rows: I) -> Vec<usize>
+where
+    I: Iterator<Item = &'a Vec<Cell<'b>>>,
+{
+    column_widths(rows)
+        .into_iter()
+        .map(|width| width.max(1))
+        .collect()
+}
+
+// The line under a markdown header, with colons on the side of each column its cells align to.
+fn markers(f: &mut dyn fmt::Write, widths: &[usize], aligns: &[Align]) -> fmt::Result {
+    let mut markers = String::from("|");
+    for (&width, &align) in widths.iter().zip(aligns) {
+        let dashes = width + 2;
+        let (left, right) = match align {
+            Align::Left => (":", ""),
+            Align::Right => ("", ":"),
+            Align::Center => (":", ":"),
+            // Markdown can't line up decimal points, but the numbers are on the right at least.
+            Align::Decimal => ("", ":"),
+        };
+        markers.push_str(left);
+        markers.extend(std::iter::repeat_n('-', dashes - left.len() - right.len()));
+        markers.push_str(right);
+        markers.push('|');
+    }
+    writeln!(f, "{}", markers)
+}
+
+fn column_widths<'a, 'b: 'a, I>(rows: I) -> Vec<usize>
+where
+    I: Iterator<Item = &'a Vec<Cell<'b>>>,
+{
+    let mut widths = Vec::new();
+    for row in rows {
+        if widths.len() < row.len() {
+            widths.resize(row.len(), 0);
+        }
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(width_of(cell.text()));
+        }
+    }
+    widths
+}
+
+// How wide a number is before its decimal point, and from it on, or None if it isn't a number.
+// Without a point, anything after the digits, i.e. a percent sign, counts as past it.
+fn around_point(line: &str) -> Option<(usize, usize)> {
+    let line = visible(line);
+    if !looks_numeric(&line) {
+        return None;
+    }
+    let fraction = line
+        .find('.')
+        .or_else(|| line.find('%'))
+        .map_or(0, |point| width_of(&line[point..]));
+    Some((width_of(&line) - fraction, fraction))
+}
+
+// Like -12, 3.5, 1,024 or 45%: an optional sign, then digits with maybe a decimal point and
+// separators between them, then maybe a percent sign.
+fn looks_numeric(cell: &str) -> bool {
+    let cell = cell.trim();
+    let cell = cell.strip_prefix(&['-', '+'][..]).unwrap_or(cell);
+    let cell = cell.strip_suffix('%').unwrap_or(cell);
+    let mut points = 0;
+    let mut digits = 0;
+    for c in cell.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            '.' => points += 1,
+            ',' | '_' => {}
+            _ => return false,
+        }
+    }
+    digits > 0 && points <= 1
+}
+
+// The number in a cell that looks like one, ignoring its separators and any percent sign.
+fn number_in(text: &str) -> Option<f64> {
+    if !looks_numeric(text) {
+        return None;
+    }
+    let digits: String = text
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ',' | '_' | '%'))
+        .collect();
+    digits.parse().ok()
+}
+
+// A line of a cell padded out to width on whichever sides its alignment says, styled if it says
+// so. Only the text is styled, so that e.g. an underline doesn't run on into the padding. A cell
+// that's already too wide, as a markdown one can be when streamed, just isn't padded. fraction is
+// how much of the column is taken up from the points of the numbers in it, when lining them up.
+fn pad(
+    line: &mut String,
+    cell: &str,
+    width: usize,
+    align: Align,
+    fraction: usize,
+    sgr: Option<&str>,
+) {
+    let padding = width.saturating_sub(width_of(cell));
+    let (before, after) = match align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+        Align::Decimal => {
+            let after = around_point(cell)
+                .map_or(0, |(_, f)| fraction.saturating_sub(f))
+                .min(padding);
+            (padding - after, after)
+        }
+    };
+    line.extend(std::iter::repeat_n(' ', before));
+    match sgr {
+        Some(sgr) if !cell.is_empty() => {
+            line.push_str(sgr);
+            line.push_str(cell);
+            line.push_str(RESET);
+        }
+        _ => line.push_str(cell),
+    }
+    line.extend(std::iter::repeat_n(' ', after));
+}
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.border == Border::Markdown {
+            return self.markdown(f);
+        }
+        let widths = self.widths();
+        let aligns = self.alignments();
+        let fractions = self.fractions(widths.len());
+        let drawing = self.border.drawing();
+        let draw = |f: &mut fmt::Formatter<'_>, rule: Option<Rule>| match rule {
+            Some(rule) => line_across(f, &widths, rule, drawing.inset),
+            None => Ok(()),
+        };
+        draw(f, drawing.top)?;
+        if let Some(header) = &self.header {
+            row_of(f, header, &widths, &aligns, &[], &self.limits, &drawing)?;
+            draw(f, drawing.under_header)?;
+        }
+        for (n, row) in self.rows.iter().enumerate() {
+            if self.groups.contains(&n) {
+                draw(f, drawing.between_groups)?;
+            }
+            row_of(f, row, &widths, &aligns, &fractions, &self.limits, &drawing)?;
+        }
+        draw(f, drawing.bottom)
+    }
+}
+
+fn row_of(
+    f: &mut dyn fmt::Write,
+    row: &[Cell<'_>],
+    widths: &[usize],
+    aligns: &[Align],
+    fractions: &[usize],
+    limits: &[Option<(usize, Overflow)>],
+    drawing: &Drawing,
+) -> fmt::Result {
+    let [left, middle, right] = drawing.sides;
+    // A cell with line breaks in it takes up as many lines as it has, and the rest of its row
+    // is padded out to match.
+    let cells: Vec<Vec<Cow<'_, str>>> = (0..widths.len())
+        .map(|i| {
+            let text = row.get(i).map_or("", |cell| cell.text());
+            match limits.get(i).copied().flatten() {
+                Some((limit, overflow)) => text
+                    .lines()
+                    .flat_map(|line| overflowed(line, limit, overflow))
+                    .collect(),
+                None => text.lines().map(Cow::Borrowed).collect(),
+            }
+        })
+        .collect();
+    let sgrs: Vec<Option<String>> = row.iter().map(Cell::sgr).collect();
+    let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    for n in 0..height {
+        let mut line = String::from(left);
+        for (i, (&width, &align)) in widths.iter().zip(aligns).enumerate() {
+            let cell = cells[i].get(n).map_or("", |line| line.as_ref());
+            if i > 0 {
+                line.push_str(middle);
+            }
+            let sgr = sgrs.get(i).and_then(Option::as_deref);
+            let fraction = fractions.get(i).copied().unwrap_or(0);
+            pad(&mut line, cell, width, align, fraction, sgr);
+        }
+        line.push_str(right);
+        // Without a border to close it off, padding the last column would only leave trailing
+        // whitespace.
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+// A line of a cell that's too wide for its column, made to fit. Escape sequences are dropped
+// from the line first, since cutting one in half would leave the terminal in a mess; the cell's
+// own styling is kept apart from its text, so survives.
+fn overflowed(line: &str, limit: usize, overflow: Overflow) -> Vec<Cow<'_, str>> {
+    if width_of(line) <= limit {
+        return vec![Cow::Borrowed(line)];
+    }
+    let line = visible(line);
+    match overflow {
+        Overflow::Truncate => {
+            let mut cut = String::new();
+            let mut width = 0;
+            // Leaving room for the ellipsis.
+            for c in line.chars() {
+                let w = c.width().unwrap_or(0);
+                if width + w + 1 > limit {
+                    break;
+                }
+                cut.push(c);
+                width += w;
+            }
+            if limit > 0 {
+                cut.push('…');
+            }
+            vec![Cow::Owned(cut)]
+        }
+        Overflow::Wrap => wrap(&line, limit.max(1))
+            .into_iter()
+            .map(Cow::Owned)
+            .collect(),
+    }
+}
+
+// Splits text into lines no wider than limit, filling each with as many words as fit. A word
+// that won't fit on a line of its own is split wherever it has to be.
+fn wrap(text: &str, limit: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut width = 0;
+    for word in text.split_whitespace() {
+        let w = width_of(word);
+        if width > 0 && width + 1 + w <= limit {
+            line.push(' ');
+            line.push_str(word);
+            width += 1 + w;
+            continue;
+        }
+        if width > 0 {
+            lines.push(std::mem::take(&mut line));
+            width = 0;
+        }
+        for c in word.chars() {
+            let cw = c.width().unwrap_or(0);
+            if width + cw > limit && width > 0 {
+                lines.push(std::mem::take(&mut line));
+                width = 0;
+            }
+            line.push(c);
+            width += cw;
+        }
+    }
+    if width > 0 || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// A horizontal line across the table, from its left edge, across each column, through the
+// crossings between them, to its right edge.
+fn line_across(
+    f: &mut dyn fmt::Write,
+    widths: &[usize],
+    [left, fill, cross, right]: Rule,
+    inset: usize,
+) -> fmt::Result {
+    f.write_str(left)?;
+    for (i, &width) in widths.iter().enumerate() {
+        if i > 0 {
+            f.write_str(cross)?;
+        }
+        for _ in 0..width + 2 * inset {
+            f.write_str(fill)?;
+        }
+    }
+    writeln!(f, "{}", right)
+}
@@ -0,0 +1,55 @@
+use super::cell::Cell;
+use std::iter::FromIterator;
+
+// A row of cells, built up a cell at a time, e.g.
+//     Row::new().cell("total").cell(format!("{}", total)).cell(Cell::new("ok").bold())
+// Each cell is a Cow, so a &str goes in, and on into the table, without being copied, and only
+// the cells that had to be worked out, like the total, allocate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Row<'a> {
+    cells: Vec<Cell<'a>>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new() -> Self {
+        Row::default()
+    }
+
+    // A row with room for this many cells, so that adding them won't have to grow it.
+    pub fn with_capacity(cells: usize) -> Self {
+        Row {
+            cells: Vec::with_capacity(cells),
+        }
+    }
+
+    pub fn cell<C: Into<Cell<'a>>>(mut self, cell: C) -> Self {
+        self.cells.push(cell.into());
+        self
+    }
+
+    // Like cell, for adding to a row in a loop.
+    pub fn push<C: Into<Cell<'a>>>(&mut self, cell: C) -> &mut Self {
+        self.cells.push(cell.into());
+        self
+    }
+
+    pub fn cells(&self) -> &[Cell<'a>] {
+        &self.cells
+    }
+}
+
+impl<'a> IntoIterator for Row<'a> {
+    type Item = Cell<'a>;
+    type IntoIter = std::vec::IntoIter<Cell<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.into_iter()
+    }
+}
+
+impl<'a, C: Into<Cell<'a>>> FromIterator<C> for Row<'a> {
+    fn from_iter<I: IntoIterator<Item = C>>(cells: I) -> Self {
+        Row {
+            cells: cells.into_iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use super::{
+    escape_markdown, line_across, markdown_widths, markers, row_of, Align, Border, Cell, Overflow,
+    Rule, Table,
+};
+use std::io::{self, Write};
+
+// Where a Stream gets the widths of its columns from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Widths {
+    // Just these, so every row can be written the moment it comes in.
+    Fixed(Vec<usize>),
+    // As wide as the first this many rows need, which are held back until they're all in, or
+    // there are no more.
+    Learn(usize),
+}
+
+// A table written a row at a time as the rows come in, rather than all at once when they're all
+// in, so that e.g. gigabytes of piped input needn't be held in memory first, and there's
+// something to see straight away. The catch is that the columns can't be as wide as their widest
+// cell, since that could come last. Instead, once the widths are settled, cells too wide for
+// their column are cut short or wrapped, and any past the last column are left out.
+//
+// The Table it starts from says how it's drawn: its header, border, alignments and limits. The
+// header is written along with the first rows.
+pub struct Stream<'a, W: Write> {
+    // Holds the rows that widths are being learned from, until they're written.
+    table: Table<'a>,
+    learn: usize,
+    fixed: Option<Vec<usize>>,
+    overflow: Overflow,
+    fit: Option<usize>,
+    // How the columns are laid out once it's settled, when rows can be written as they come.
+    settled: Option<Settled>,
+    written: usize,
+    grouped: bool,
+    out: W,
+}
+
+struct Settled {
+    widths: Vec<usize>,
+    aligns: Vec<Align>,
+    // For lining up decimal points, as in Table::fractions.
+    fractions: Vec<usize>,
+}
+
+impl<'a, W: Write> Stream<'a, W> {
+    pub fn new(table: Table<'a>, widths: Widths, out: W) -> Self {
+        let (learn, fixed) = match widths {
+            // The header, if there is one, can be written first thing.
+            Widths::Fixed(widths) => (0, Some(widths)),
+            // There's nothing to learn from nothing.
+            Widths::Learn(rows) => (rows.max(1), None),
+        };
+        Stream {
+            table,
+            learn,
+            fixed,
+            overflow: Overflow::Truncate,
+            fit: None,
+            settled: None,
+            written: 0,
+            grouped: false,
+            out,
+        }
+    }
+
+    // What to do with cells too wide for the widths, in columns without a limit of their own
+    // saying so already.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    // Narrows learned widths like Table::fit. Fixed widths are as they were asked for.
+    pub fn fit(mut self, width: usize) -> Self {
+        self.fit = Some(width);
+        self
+    }
+
+    pub fn row<I>(&mut self, cells: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<Cell<'a>>,
+    {
+        if self.settled.is_none() {
+            self.table.row(cells);
+            if self.table.rows.len() >= self.learn {
+                self.settle()?;
+            }
+            return Ok(());
+        }
+        let row = self
+            .table
+            .formatted(cells.into_iter().map(Into::into).collect());
+        self.write_row(&row)
+    }
+
+    // Like Table::group, the rows from here on are set apart from those before.
+    pub fn group(&mut self) -> &mut Self {
+        if self.settled.is_none() {
+            self.table.group();
+        } else if self.written > 0 {
+            self.grouped = true;
+        }
+        self
+    }
+
+    // Writes whatever's still held back, and the bottom of the border, handing back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.settled.is_none() {
+            self.settle()?;
+        }
+        if let Some(settled) = &self.settled {
+            if let Some(rule) = self.table.border.drawing().bottom {
+                self.rule(&settled.widths.clone(), rule)?;
+            }
+        }
+        Ok(self.out)
+    }
+
+    fn markdown(&self) -> bool {
+        self.table.border == Border::Markdown
+    }
+
+    // Works out the widths, from the rows held back if they aren't fixed, then writes the header
+    // and those rows.
+    fn settle(&mut self) -> io::Result<()> {
+        if self.markdown() && self.table.header.is_none() {
+            // Markdown has to have a header, so the first row stands in for it, as in a Table.
+            // Until there is one, there's nothing to write.
+            if self.table.rows.is_empty() {
+                return Ok(());
+            }
+            self.table.header = Some(self.table.rows.remove(0));
+        }
+        let widths = match self.fixed.take() {
+            Some(widths) if self.markdown() => widths.into_iter().map(|w| w.max(1)).collect(),
+            Some(widths) => widths,
+            None if self.markdown() => {
+                let header = self.table.header.iter().map(|row| escape_markdown(row));
+                let rows = self.table.rows.iter().map(|row| escape_markdown(row));
+                markdown_widths(header.chain(rows).collect::<Vec<_>>().iter())
+            }
+            None => {
+                if let Some(width) = self.fit.filter(|&width| width > 0) {
+                    self.table.fit(width, self.overflow);
+                }
+                self.table.widths()
+            }
+        };
+        let aligns: Vec<Align> = (0..widths.len())
+            .map(|column| match self.learn {
+                // One row is too little to tell a column of numbers by.
+                0 | 1 => self
+                    .table
+                    .aligns
+                    .get(column)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(Align::Left),
+                _ => self.table.alignment(column),
+            })
+            .collect();
+        // Markdown ignores limits, as in a Table; everything else keeps to the widths from here.
+        if !self.markdown() {
+            for (column, &width) in widths.iter().enumerate() {
+                let overflow = match self.table.limits.get(column).copied().flatten() {
+                    Some((_, overflow)) => overflow,
+                    None => self.overflow,
+                };
+                self.table.max_width(column, width, overflow);
+            }
+        }
+        self.settled = Some(Settled {
+            widths: widths.clone(),
+            aligns,
+            fractions: self.table.fractions(widths.len()),
+        });
+
+        let drawing = self.table.border.drawing();
+        if let Some(rule) = drawing.top {
+            self.rule(&widths, rule)?;
+        }
+        if let Some(header) = self.table.header.take() {
+            self.write_row(&header)?;
+            if self.markdown() {
+                let settled = self.settled.as_ref().expect("just settled");
+                let mut line = String::new();
+                markers(&mut line, &settled.widths, &settled.aligns)
+                    .expect("writing to a String can't fail");
+                self.out.write_all(line.as_bytes())?;
+            } else if let Some(rule) = drawing.under_header {
+                self.rule(&widths, rule)?;
+            }
+            self.written = 0;
+        }
+        let rows = std::mem::take(&mut self.table.rows);
+        let groups = std::mem::take(&mut self.table.groups);
+        for (n, row) in rows.iter().enumerate() {
+            if groups.contains(&n) {
+                self.group();
+            }
+            self.write_row(row)?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[Cell<'_>]) -> io::Result<()> {
+        let Settled {
+            widths,
+            aligns,
+            fractions,
+        } = self
+            .settled
+            .as_ref()
+            .expect("rows are only written once settled");
+        let drawing = self.table.border.drawing();
+        let mut lines = String::new();
+        if std::mem::take(&mut self.grouped) {
+            if let Some(rule) = drawing.between_groups {
+                line_across(&mut lines, widths, rule, drawing.inset)
+                    .expect("writing to a String can't fail");
+            }
+        }
+        if self.markdown() {
+            row_of(
+                &mut lines,
+                &escape_markdown(row),
+                widths,
+                aligns,
+                &[],
+                &[],
+                &drawing,
+            )
+        } else {
+            row_of(
+                &mut lines,
+                row,
+                widths,
+                aligns,
+                fractions,
+                &self.table.limits,
+                &drawing,
+            )
+        }
+        .expect("writing to a String can't fail");
+        self.written += 1;
+        self.out.write_all(lines.as_bytes())
+    }
+
+    fn rule(&mut self, widths: &[usize], rule: Rule) -> io::Result<()> {
+        let mut line = String::new();
+        line_across(&mut line, widths, rule, self.table.border.drawing().inset)
+            .expect("writing to a String can't fail");
+        self.out.write_all(line.as_bytes())
+    }
+}
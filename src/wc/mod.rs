@@ -0,0 +1,162 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead};
+use std::ops::AddAssign;
+use unicode_width::UnicodeWidthChar;
+
+// What wc counts in a file, like GNU wc: newlines, words, characters, bytes and the display
+// width of the longest line. Characters are UTF-8 ones, and words are runs of anything other
+// than whitespace, as in a UTF-8 locale.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: u64,
+    pub words: u64,
+    pub chars: u64,
+    pub bytes: u64,
+    pub max_line: u64,
+}
+
+impl AddAssign for Counts {
+    // For the total, whose longest line is the longest of anyone's.
+    fn add_assign(&mut self, other: Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line = self.max_line.max(other.max_line);
+    }
+}
+
+// Which of the counts to write, in the order GNU wc writes them in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Columns {
+    pub lines: bool,
+    pub words: bool,
+    pub chars: bool,
+    pub bytes: bool,
+    pub max_line: bool,
+}
+
+impl Default for Columns {
+    // Lines, words and bytes, when none are asked for.
+    fn default() -> Self {
+        Columns {
+            lines: true,
+            words: true,
+            chars: false,
+            bytes: true,
+            max_line: false,
+        }
+    }
+}
+
+impl Columns {
+    // What each column is called, for a table's header.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.pick(["lines", "words", "chars", "bytes", "max-line"])
+    }
+
+    pub fn values(&self, counts: &Counts) -> Vec<u64> {
+        self.pick([
+            counts.lines,
+            counts.words,
+            counts.chars,
+            counts.bytes,
+            counts.max_line,
+        ])
+    }
+
+    pub fn len(&self) -> usize {
+        self.names().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pick<T>(&self, all: [T; 5]) -> Vec<T> {
+        let wanted = [
+            self.lines,
+            self.words,
+            self.chars,
+            self.bytes,
+            self.max_line,
+        ];
+        IntoIterator::into_iter(all)
+            .zip(wanted)
+            .filter_map(|(value, wanted)| wanted.then_some(value))
+            .collect()
+    }
+}
+
+// A line at a time, so a file needn't fit in memory, unless it has no newlines in it.
+pub fn count<R: BufRead>(mut reader: R) -> io::Result<Counts> {
+    let mut counts = Counts::default();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(counts);
+        }
+        counts.bytes += line.len() as u64;
+        if line.ends_with(b"\n") {
+            counts.lines += 1;
+        }
+        let mut in_word = false;
+        let mut width = 0;
+        // Bytes that aren't UTF-8 aren't characters to GNU wc, but are part of a word.
+        for chunk in line.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                counts.chars += 1;
+                if c.is_whitespace() {
+                    in_word = false;
+                } else if !in_word {
+                    in_word = true;
+                    counts.words += 1;
+                }
+                width = match c {
+                    '\t' => width + 8 - width % 8,
+                    '\n' => width,
+                    c => width + c.width().unwrap_or(0) as u64,
+                };
+            }
+            if !chunk.invalid().is_empty() && !in_word {
+                in_word = true;
+                counts.words += 1;
+            }
+        }
+        counts.max_line = counts.max_line.max(width);
+    }
+}
+
+// How wide GNU wc makes every count, so they line up: wide enough for the total size of the
+// files, or at least 7 when any of them, like a pipe, has no size to go on. sizes are those of
+// each file, or None for those that aren't regular files.
+pub fn number_width<I: IntoIterator<Item = Option<u64>>>(sizes: I) -> usize {
+    let mut minimum = 1;
+    let mut total: u64 = 0;
+    for size in sizes {
+        match size {
+            Some(size) => total = total.saturating_add(size),
+            None => minimum = 7,
+        }
+    }
+    total.to_string().len().max(minimum)
+}
+
+// A line of wc's output: each count right aligned to width, with a space between, and then the
+// name, if there is one.
+pub fn line(columns: &Columns, counts: &Counts, width: usize, name: Option<&str>) -> String {
+    let mut line = String::new();
+    for (n, value) in columns.values(counts).into_iter().enumerate() {
+        if n > 0 {
+            line.push(' ');
+        }
+        // Writing to a String can't fail.
+        let _ = write!(line, "{:>width$}", value, width = width);
+    }
+    if let Some(name) = name {
+        line.push(' ');
+        line.push_str(name);
+    }
+    line
+}
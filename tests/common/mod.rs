@@ -0,0 +1,51 @@
+// Shared by the tests of each binary, which don't all use all of it.
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// How a binary went: its exit code and everything it wrote.
+#[derive(Debug)]
+pub struct Run {
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Run {
+    pub fn lines(&self) -> Vec<&str> {
+        self.stdout.lines().collect()
+    }
+
+    // Like lines, for stderr.
+    pub fn errors(&self) -> Vec<&str> {
+        self.stderr.lines().collect()
+    }
+}
+
+// Runs a binary, as built for these tests, with args and nothing on stdin.
+pub fn run(bin: &str, args: &[&str]) -> Run {
+    run_with_input(bin, args, "")
+}
+
+pub fn run_with_input(bin: &str, args: &[&str], input: &str) -> Run {
+    let mut child = Command::new(bin)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| panic!("running {}: {}", bin, err));
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())
+        .expect("writing to stdin");
+    let output = child.wait_with_output().expect("waiting for the binary");
+    Run {
+        code: output.status.code(),
+        stdout: String::from_utf8(output.stdout).expect("stdout isn't utf-8"),
+        stderr: String::from_utf8(output.stderr).expect("stderr isn't utf-8"),
+    }
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::run;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const DU_LITE: &str = env!("CARGO_BIN_EXE_du-lite");
+
+// A tree of 30000 bytes in big, 100 in small and 20 in small/tiny, each of which has a file of
+// that many bytes. Directories take up room of their own too, however much the filesystem says.
+fn tree() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, size) in [("big", 30000), ("small", 100), ("small/tiny", 20)] {
+        fs::create_dir(dir.path().join(path)).unwrap();
+        fs::write(dir.path().join(path).join("file"), vec![b'x'; size]).unwrap();
+    }
+    dir
+}
+
+fn own(path: &Path) -> u64 {
+    fs::symlink_metadata(path).unwrap().len()
+}
+
+fn path(path: &Path) -> String {
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn sizes_include_everything_below() {
+    let dir = tree();
+    let root = dir.path();
+    let tiny = own(&root.join("small/tiny")) + 20;
+    let small = own(&root.join("small")) + 100 + tiny;
+    let big = own(&root.join("big")) + 30000;
+    let all = own(root) + small + big;
+    for threads in ["1", "4"] {
+        let run = run(
+            DU_LITE,
+            &["--apparent-size", "-h", "-j", threads, &path(root)],
+        );
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let sizes: Vec<(&str, &str)> = run
+            .lines()
+            .into_iter()
+            .map(|line| line.trim_start().split_once("  ").unwrap())
+            .collect();
+        let expected = [
+            (all, root.to_path_buf()),
+            (big, root.join("big")),
+            (small, root.join("small")),
+            (tiny, root.join("small/tiny")),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(size, dir)| (learning_rust::du::human(*size), path(dir)))
+            .collect();
+        let sizes: Vec<(String, String)> = sizes
+            .into_iter()
+            .map(|(size, dir)| (size.to_string(), dir.to_string()))
+            .collect();
+        assert_eq!(sizes, expected, "{} threads", threads);
+    }
+}
+
+#[test]
+fn max_depth_and_sort_by_size() {
+    let dir = tree();
+    let root = dir.path();
+    let run = run(
+        DU_LITE,
+        &["--apparent-size", "-d", "1", "--sort", "size", &path(root)],
+    );
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    let dirs: Vec<&str> = run
+        .lines()
+        .into_iter()
+        .map(|line| line.trim_start().split_once("  ").unwrap().1)
+        .collect();
+    assert_eq!(
+        dirs,
+        [
+            path(root),
+            path(&root.join("big")),
+            path(&root.join("small"))
+        ]
+    );
+}
+
+#[test]
+fn human_sizes() {
+    use learning_rust::du::human;
+    assert_eq!(human(0), "0");
+    assert_eq!(human(1023), "1023");
+    assert_eq!(human(1024), "1.0K");
+    assert_eq!(human(1025), "1.1K");
+    assert_eq!(human(10 * 1024 - 1), "10K");
+    assert_eq!(human(1536 * 1024), "1.5M");
+    assert_eq!(human(1024 * 1024 - 1), "1.0M");
+}
+
+#[test]
+fn missing_paths_are_reported() {
+    let run = run(DU_LITE, &["/nonexistent"]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(
+        run.errors(),
+        ["error: /nonexistent: No such file or directory (os error 2)"]
+    );
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::run;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const EXECUTE_IN_DIRS: &str = env!("CARGO_BIN_EXE_execute-in-dirs");
+
+// A command that says which directory it's in, and fails in any with a file named fail.
+const FAKE: &str = "#!/bin/sh
+echo \"in $(basename \"$PWD\")\"
+if [ -e fail ]; then
+    echo failing >&2
+    exit 3
+fi
+";
+
+// Directories a and b to run in, and the fake command, out of the way of both.
+struct Dirs {
+    root: TempDir,
+}
+
+impl Dirs {
+    fn new() -> Self {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["a", "b"] {
+            fs::create_dir(root.path().join(dir)).unwrap();
+        }
+        let fake = root.path().join("fake");
+        fs::write(&fake, FAKE).unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).unwrap();
+        Dirs { root }
+    }
+
+    fn dir(&self, name: &str) -> String {
+        path(&self.root.path().join(name))
+    }
+
+    fn fake(&self) -> String {
+        self.dir("fake")
+    }
+}
+
+fn path(path: &Path) -> String {
+    path.to_str().unwrap().to_string()
+}
+
+// The commands run at the same time, so their lines can come in any order.
+fn sorted(lines: Vec<&str>) -> Vec<&str> {
+    let mut lines = lines;
+    lines.sort_unstable();
+    lines
+}
+
+#[test]
+fn prefixes_each_line_with_its_directory() {
+    let dirs = Dirs::new();
+    let (a, b) = (dirs.dir("a"), dirs.dir("b"));
+    let run = run(EXECUTE_IN_DIRS, &[&a, &b, "--", &dirs.fake()]);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        sorted(run.lines()),
+        [format!("{}: in a", a), format!("{}: in b", b)]
+    );
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn trailing_slashes_are_left_out_of_the_prefix() {
+    let dirs = Dirs::new();
+    let a = dirs.dir("a");
+    let run = run(EXECUTE_IN_DIRS, &[&format!("{}//", a), "--", &dirs.fake()]);
+    assert_eq!(run.lines(), [format!("{}: in a", a)]);
+}
+
+#[test]
+fn failures_are_reported() {
+    let dirs = Dirs::new();
+    let (a, b) = (dirs.dir("a"), dirs.dir("b"));
+    fs::write(PathBuf::from(&b).join("fail"), "").unwrap();
+    let run = run(EXECUTE_IN_DIRS, &[&a, &b, "--", &dirs.fake()]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(
+        sorted(run.lines()),
+        [format!("{}: in a", a), format!("{}: in b", b)]
+    );
+    assert_eq!(
+        run.errors(),
+        [format!("{}: failing", b), format!("{}: exited 3", b)]
+    );
+}
+
+#[test]
+fn missing_command() {
+    let dirs = Dirs::new();
+    let a = dirs.dir("a");
+    let run = run(EXECUTE_IN_DIRS, &[&a, "--", "/nonexistent/command"]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(
+        run.errors(),
+        [format!(
+            "{}: running /nonexistent/command: No such file or directory (os error 2)",
+            a
+        )]
+    );
+}
+
+#[test]
+fn every_strategy_writes_the_same() {
+    let dirs = Dirs::new();
+    let (a, b) = (dirs.dir("a"), dirs.dir("b"));
+    for strategy in ["locked", "shared", "channel"] {
+        let run = run(
+            EXECUTE_IN_DIRS,
+            &["--strategy", strategy, &a, &b, "--", &dirs.fake()],
+        );
+        assert_eq!(run.code, Some(0), "{}: {}", strategy, run.stderr);
+        assert_eq!(
+            sorted(run.lines()),
+            [format!("{}: in a", a), format!("{}: in b", b)],
+            "{}",
+            strategy
+        );
+    }
+}
+
+#[test]
+fn tee() {
+    let dirs = Dirs::new();
+    let a = dirs.dir("a");
+    let transcript = dirs.dir("transcript");
+    let run = run(
+        EXECUTE_IN_DIRS,
+        &["--tee", &transcript, &a, "--", &dirs.fake()],
+    );
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.stdout, format!("{}: in a\n", a));
+    assert_eq!(fs::read_to_string(&transcript).unwrap(), run.stdout);
+}
+
+// Neither needs the directories and command that running does.
+#[test]
+fn man_page_and_completions() {
+    let man = run(EXECUTE_IN_DIRS, &["--man"]);
+    assert_eq!(man.code, Some(0), "{}", man.stderr);
+    assert!(man.stdout.starts_with(".TH \"EXECUTE-IN-DIRS\" 1"));
+    assert!(man.stdout.contains("\\fB\\-\\-strategy <STRATEGY>\\fR\n"));
+
+    let completions = run(EXECUTE_IN_DIRS, &["--completions", "bash"]);
+    assert_eq!(completions.code, Some(0), "{}", completions.stderr);
+    assert!(completions.stdout.contains("--strategy"));
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::run;
+use std::fs;
+
+const FIZZBUZZ: &str = env!("CARGO_BIN_EXE_fizzbuzz");
+
+#[test]
+fn classic_rules() {
+    let run = run(FIZZBUZZ, &["--end", "15"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(
+        run.lines(),
+        [
+            "1",
+            "2",
+            "fizz",
+            "4",
+            "buzz",
+            "fizz",
+            "7",
+            "8",
+            "fizz",
+            "buzz",
+            "11",
+            "fizz",
+            "13",
+            "14",
+            "FizzBuzz!"
+        ]
+    );
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn counting_down() {
+    let run = run(FIZZBUZZ, &["--start", "10", "--end", "1", "--step", "-3"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.lines(), ["buzz", "7", "4", "1"]);
+}
+
+#[test]
+fn range_that_starts_past_its_end() {
+    let run = run(FIZZBUZZ, &["--end", "0"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn count_only() {
+    let run = run(FIZZBUZZ, &["--end", "15", "--count-only"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.lines(), ["15 lines, 59 bytes"]);
+}
+
+#[test]
+fn output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.txt");
+    let run = run(
+        FIZZBUZZ,
+        &["--end", "5", "--output", path.to_str().unwrap()],
+    );
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout, "");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "1\n2\nfizz\n4\nbuzz\n");
+}
+
+// clap's own errors exit 1.
+#[test]
+fn zero_step() {
+    let run = run(FIZZBUZZ, &["--end", "5", "--step", "0"]);
+    assert_eq!(run.code, Some(1));
+    assert!(run.stderr.contains("must not be 0"), "{}", run.stderr);
+}
+
+#[test]
+fn missing_rules_file() {
+    let run = run(
+        FIZZBUZZ,
+        &["--end", "5", "--rules-file", "/nonexistent/rules.toml"],
+    );
+    assert_eq!(run.code, Some(2));
+    assert_eq!(
+        run.errors(),
+        ["error: reading /nonexistent/rules.toml: No such file or directory (os error 2)"]
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::{run, run_with_input};
+use std::fs;
+use tempfile::TempDir;
+
+const GREP_LITE: &str = env!("CARGO_BIN_EXE_grep-lite");
+
+// A tree of files to search, as
+//     a.txt
+//     sub/b.txt
+//     sub/deeper/c.txt
+fn tree() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("sub/deeper")).unwrap();
+    fs::write(dir.path().join("a.txt"), "apple\nbanana\ncherry\n").unwrap();
+    fs::write(dir.path().join("sub/b.txt"), "blueberry\napricot").unwrap();
+    fs::write(dir.path().join("sub/deeper/c.txt"), "cranberry\n").unwrap();
+    dir
+}
+
+fn grep_in(dir: &TempDir, args: &[&str]) -> common::Run {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| match arg.strip_prefix('@') {
+            Some(path) => dir.path().join(path).to_str().unwrap().to_string(),
+            None => arg.to_string(),
+        })
+        .collect();
+    run(
+        GREP_LITE,
+        &args.iter().map(String::as_str).collect::<Vec<_>>(),
+    )
+}
+
+#[test]
+fn stdin() {
+    let run = run_with_input(GREP_LITE, &["-n", "an"], "apple\nbanana\nmango\n");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.lines(), ["2:banana", "3:mango"]);
+}
+
+#[test]
+fn nothing_found() {
+    let run = run_with_input(GREP_LITE, &["kiwi"], "apple\n");
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.stdout, "");
+}
+
+// In name order, and the same whatever order they're searched in.
+#[test]
+fn recursive_in_order() {
+    let dir = tree();
+    let root = dir.path().to_str().unwrap();
+    for threads in ["1", "8"] {
+        let run = grep_in(&dir, &["-r", "-j", threads, "rr|ap", "@."]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.lines(),
+            [
+                format!("{}/./a.txt:apple", root),
+                format!("{}/./a.txt:cherry", root),
+                format!("{}/./sub/b.txt:blueberry", root),
+                format!("{}/./sub/b.txt:apricot", root),
+                format!("{}/./sub/deeper/c.txt:cranberry", root),
+            ]
+        );
+    }
+}
+
+#[test]
+fn files_with_matches_and_invert() {
+    let dir = tree();
+    let run = grep_in(&dir, &["-l", "-v", "rr", "@a.txt", "@sub/deeper/c.txt"]);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.lines(), [dir.path().join("a.txt").to_str().unwrap()]);
+}
+
+#[test]
+fn errors_are_reported_in_place() {
+    let dir = tree();
+    let run = grep_in(&dir, &["-i", "APPLE", "@a.txt", "@missing", "@sub"]);
+    assert_eq!(run.code, Some(2));
+    assert_eq!(
+        run.lines(),
+        [format!("{}:apple", dir.path().join("a.txt").display())]
+    );
+    assert_eq!(
+        run.errors(),
+        [
+            format!(
+                "error: {}: No such file or directory (os error 2)",
+                dir.path().join("missing").display()
+            ),
+            format!(
+                "error: {}: Is a directory (os error 21)",
+                dir.path().join("sub").display()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn invalid_pattern() {
+    let run = run(GREP_LITE, &["("]);
+    assert_eq!(run.code, Some(2));
+    assert!(run.stderr.starts_with("error: invalid pattern: "));
+}
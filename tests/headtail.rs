@@ -0,0 +1,112 @@
+mod common;
+
+use common::{run, run_with_input};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const HEADTAIL: &str = env!("CARGO_BIN_EXE_headtail");
+
+const NUMBERS: &str = "1\n2\n3\n4\n5\n";
+
+#[test]
+fn head_and_tail_of_stdin() {
+    let head = run_with_input(HEADTAIL, &["head", "-n", "2"], NUMBERS);
+    assert_eq!(head.code, Some(0), "{}", head.stderr);
+    assert_eq!(head.stdout, "1\n2\n");
+    let tail = run_with_input(HEADTAIL, &["tail", "-n", "2"], NUMBERS);
+    assert_eq!(tail.stdout, "4\n5\n");
+    let bytes = run_with_input(HEADTAIL, &["tail", "-c", "3"], NUMBERS);
+    assert_eq!(bytes.stdout, "\n5\n");
+}
+
+// With a +, the tail starts that far in, counting from 1, however long the rest of it is.
+#[test]
+fn tail_from_the_start() {
+    let lines = run_with_input(HEADTAIL, &["tail", "-n", "+4"], NUMBERS);
+    assert_eq!(lines.code, Some(0), "{}", lines.stderr);
+    assert_eq!(lines.stdout, "4\n5\n");
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a");
+    fs::write(&a, NUMBERS).unwrap();
+    let bytes = run(HEADTAIL, &["tail", "-c", "+8", a.to_str().unwrap()]);
+    assert_eq!(bytes.stdout, "\n5\n");
+    let head = run_with_input(HEADTAIL, &["head", "-n", "+2"], NUMBERS);
+    assert_eq!(head.stdout, "1\n2\n");
+}
+
+// Files have their tails found by reading back from the end, which a last line without a
+// newline mustn't put off.
+#[test]
+fn tail_of_files_with_headers() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, NUMBERS).unwrap();
+    fs::write(&b, "x\ny\nz").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+    let tail = run(HEADTAIL, &["tail", "-n", "2", a, b]);
+    assert_eq!(tail.code, Some(0), "{}", tail.stderr);
+    assert_eq!(
+        tail.stdout,
+        format!("==> {} <==\n4\n5\n\n==> {} <==\ny\nz", a, b)
+    );
+    let quiet = run(HEADTAIL, &["head", "-q", "-c", "2", a, b]);
+    assert_eq!(quiet.stdout, "1\nx\n");
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a");
+    fs::write(&a, NUMBERS).unwrap();
+    let a = a.to_str().unwrap();
+    let run = run(HEADTAIL, &["head", "-n", "1", "/nonexistent", a]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.stdout, format!("==> {} <==\n1\n", a));
+    assert_eq!(
+        run.errors(),
+        ["error: /nonexistent: No such file or directory (os error 2)"]
+    );
+}
+
+// Whatever's appended is written as it's noticed, whether by being told or by polling.
+#[test]
+fn follow() {
+    for poll in [false, true] {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        fs::write(&path, NUMBERS).unwrap();
+        let mut args = vec!["tail", "-n", "1", "-f", "-s", "0.05"];
+        if poll {
+            args.push("--poll");
+        }
+        let mut child = Command::new(HEADTAIL)
+            .args(&args)
+            .arg(&path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "5");
+
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        let started = Instant::now();
+        writeln!(log, "6").unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "6", "poll: {}", poll);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+// Each subcommand's options are in the man page, under its own name, and after it, --man still
+// asks for the page.
+#[test]
+fn man_page() {
+    let man = run(HEADTAIL, &["tail", "--man"]);
+    assert_eq!(man.code, Some(0), "{}", man.stderr);
+    assert!(man.stdout.contains(".SS \"headtail tail\"\n"));
+    assert!(man.stdout.contains("\\fB\\-f, \\-\\-follow\\fR\n"));
+    assert!(man.stdout.contains("\\fB\\-\\-poll\\fR\n"));
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::run;
+use std::fs;
+
+const PRIMES: &str = env!("CARGO_BIN_EXE_primes");
+
+const BELOW_50: [&str; 15] = [
+    "2", "3", "5", "7", "11", "13", "17", "19", "23", "29", "31", "37", "41", "43", "47",
+];
+
+#[test]
+fn sieve() {
+    let run = run(PRIMES, &["sieve", "50"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.lines(), BELOW_50);
+    assert_eq!(run.stderr, "");
+}
+
+// Every algorithm has to find the same primes, however it goes about it.
+#[test]
+fn algorithms_agree() {
+    let expected = run(PRIMES, &["sieve", "20000"]);
+    assert_eq!(expected.code, Some(0));
+    assert_eq!(expected.lines().len(), 2262);
+    for algorithm in ["auto", "segmented", "naive", "naive-parallel"] {
+        let run = run(PRIMES, &[algorithm, "20000"]);
+        assert_eq!(run.code, Some(0), "{}: {}", algorithm, run.stderr);
+        assert_eq!(run.stdout, expected.stdout, "{}", algorithm);
+    }
+}
+
+#[test]
+fn verify() {
+    let run = run(PRIMES, &["--verify", "segmented", "100000"]);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+}
+
+#[test]
+fn limit() {
+    let run = run(PRIMES, &["--limit", "4", "sieve", "50"]);
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.lines(), BELOW_50[..4]);
+}
+
+#[test]
+fn is_prime() {
+    assert_eq!(run(PRIMES, &["is-prime", "97"]).lines(), ["97: prime"]);
+    assert_eq!(run(PRIMES, &["is-prime", "91"]).lines(), ["91: composite"]);
+    for n in ["0", "1"] {
+        let run = run(PRIMES, &["is-prime", n]);
+        assert_eq!(run.code, Some(2));
+        assert_eq!(run.errors(), ["error: <n> must be at least 2"]);
+    }
+}
+
+#[test]
+fn output_and_tee() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("primes.txt");
+    let copy = dir.path().join("copy.txt");
+    let run = run(
+        PRIMES,
+        &[
+            "--output",
+            output.to_str().unwrap(),
+            "--tee",
+            copy.to_str().unwrap(),
+            "sieve",
+            "50",
+        ],
+    );
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+    let expected: String = BELOW_50.iter().map(|p| format!("{}\n", p)).collect();
+    assert_eq!(fs::read_to_string(&output).unwrap(), expected);
+    assert_eq!(fs::read_to_string(&copy).unwrap(), expected);
+}
+
+// Primes written after the last save were written again on resuming, so the output has to be cut
+// back to where it was then.
+#[test]
+fn resume() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("primes.txt");
+    let checkpoint = dir.path().join("checkpoint");
+    let saved: String = BELOW_50.iter().map(|p| format!("{}\n", p)).collect();
+    fs::write(&output, format!("{}53\n59\n", saved)).unwrap();
+    fs::write(
+        &checkpoint,
+        format!("max 100\nnext 50\nwritten {}\nbase 2 3 5 7\n", saved.len()),
+    )
+    .unwrap();
+    let args = [
+        "--checkpoint",
+        checkpoint.to_str().unwrap(),
+        "--resume",
+        "--output",
+        output.to_str().unwrap(),
+        "segmented",
+        "100",
+    ];
+    let resumed = run(PRIMES, &args);
+    assert_eq!(resumed.code, Some(0), "{}", resumed.stderr);
+    let expected = run(PRIMES, &["sieve", "100"]);
+    assert_eq!(fs::read_to_string(&output).unwrap(), expected.stdout);
+
+    let gzip = run(PRIMES, &[&["--gzip"], &args[..]].concat());
+    assert_eq!(gzip.code, Some(1));
+}
+
+// Each kind of failure has an exit code of its own.
+#[test]
+fn exit_codes() {
+    let run1 = run(PRIMES, &["--threads", "0", "sieve", "10"]);
+    assert_eq!(run1.code, Some(2));
+    assert_eq!(run1.errors(), ["error: --threads must be at least 1"]);
+
+    let run2 = run(
+        PRIMES,
+        &["--output", "/nonexistent/primes.txt", "sieve", "10"],
+    );
+    assert_eq!(run2.code, Some(4));
+    assert!(run2
+        .stderr
+        .starts_with("error: creating /nonexistent/primes.txt: "));
+
+    let run3 = run(PRIMES, &["prev", "1"]);
+    assert_eq!(run3.code, Some(7));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::run_with_input;
+use std::fs;
+
+const SORT_LITE: &str = env!("CARGO_BIN_EXE_sort-lite");
+
+#[test]
+fn sorts_lines() {
+    let run = run_with_input(SORT_LITE, &[], "pear\napple\nfig");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.stdout, "apple\nfig\npear\n");
+}
+
+// Numbers of any length, negative or with fractions; anything else counts as 0.
+#[test]
+fn numeric_reverse_unique() {
+    let input = "10\n-2.5\n9\nx\n  3\n100000000000000000000001\n-0\n9.0\n";
+    let numeric = run_with_input(SORT_LITE, &["-n"], input);
+    assert_eq!(
+        numeric.lines(),
+        [
+            "-2.5",
+            "-0",
+            "x",
+            "  3",
+            "9",
+            "9.0",
+            "10",
+            "100000000000000000000001"
+        ]
+    );
+    let reverse = run_with_input(SORT_LITE, &["-nru"], input);
+    assert_eq!(
+        reverse.lines(),
+        ["100000000000000000000001", "10", "9", "  3", "x", "-2.5"]
+    );
+    let unique = run_with_input(SORT_LITE, &["-u"], "b\na\nb\na\n");
+    assert_eq!(unique.lines(), ["a", "b"]);
+}
+
+// With next to no memory, every few lines are spilled to a file of their own, and there are
+// more runs than are merged at once, so it comes to the same by way of merging runs of runs.
+#[test]
+fn spills_to_temporary_files() {
+    let input: String = (0..2000)
+        .map(|n| format!("{}\n", (n * 7919) % 1000))
+        .collect();
+    let spill = tempfile::tempdir().unwrap();
+    let in_memory = run_with_input(SORT_LITE, &["-n"], &input);
+    let spilled = run_with_input(
+        SORT_LITE,
+        &[
+            "-n",
+            "-S",
+            "200",
+            "-T",
+            spill.path().to_str().unwrap(),
+            "-vv",
+        ],
+        &input,
+    );
+    assert_eq!(spilled.code, Some(0), "{}", spilled.stderr);
+    assert!(spilled.stderr.contains("spilling"), "{}", spilled.stderr);
+    assert!(spilled.stderr.contains("at a time"), "{}", spilled.stderr);
+    assert_eq!(spilled.stdout, in_memory.stdout);
+    assert_eq!(fs::read_dir(spill.path()).unwrap().count(), 0);
+
+    let unique = run_with_input(SORT_LITE, &["-nu", "-S", "200"], &input);
+    let expected: Vec<String> = (0..1000).map(|n| n.to_string()).collect();
+    assert_eq!(unique.lines(), expected);
+}
+
+#[test]
+fn bad_buffer_size() {
+    let run = run_with_input(SORT_LITE, &["-S", "12X"], "");
+    assert_eq!(run.code, Some(1));
+    assert!(run.stderr.contains("unknown unit X"), "{}", run.stderr);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::run_with_input;
+
+const TABLE: &str = env!("CARGO_BIN_EXE_table");
+
+const INPUT: &str = "name,count\nwidget,3\ngadget,12\n";
+
+#[test]
+fn lines_up_columns() {
+    let run = run_with_input(TABLE, &["--header"], INPUT);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        run.lines(),
+        [
+            "name    count",
+            "------  -----",
+            "widget      3",
+            "gadget     12"
+        ]
+    );
+}
+
+#[test]
+fn markdown() {
+    let run = run_with_input(TABLE, &["--header", "--format", "markdown"], INPUT);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        run.lines(),
+        [
+            "| name   | count |",
+            "|:-------|------:|",
+            "| widget |     3 |",
+            "| gadget |    12 |"
+        ]
+    );
+}
+
+#[test]
+fn unclosed_quote() {
+    let run = run_with_input(TABLE, &[], "a,\"b\n");
+    assert_eq!(run.code, Some(2));
+    assert_eq!(
+        run.errors(),
+        ["error: invalid input: the quote opened on line 1 is never closed"]
+    );
+}
+
+#[test]
+fn markdown_border() {
+    let run = run_with_input(TABLE, &["--border", "markdown"], INPUT);
+    assert_eq!(run.code, Some(1));
+    assert!(
+        run.stderr.contains("use --format markdown"),
+        "{}",
+        run.stderr
+    );
+}
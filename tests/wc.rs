@@ -0,0 +1,78 @@
+mod common;
+
+use common::{run, run_with_input};
+use std::fs;
+use tempfile::TempDir;
+
+const WC: &str = env!("CARGO_BIN_EXE_wc");
+
+// Files a, of 2 lines, 5 words and 25 bytes, and b, of 1 line, 1 word and 7 bytes.
+fn files() -> (TempDir, String, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    fs::write(&a, "one two three\nfour  five\n").unwrap();
+    fs::write(&b, "héllo\n").unwrap();
+    let path = |path: &std::path::Path| path.to_str().unwrap().to_string();
+    (dir, path(&a), path(&b))
+}
+
+// As GNU wc writes them: every count as wide as the files' total size, here 2 digits.
+#[test]
+fn lines_words_and_bytes() {
+    let (_dir, a, b) = files();
+    let run = run(WC, &[&a, &b]);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        run.lines(),
+        [
+            format!(" 2  5 25 {}", a),
+            format!(" 1  1  7 {}", b),
+            " 3  6 32 total".to_string()
+        ]
+    );
+}
+
+#[test]
+fn one_count_of_one_file() {
+    let (_dir, a, _) = files();
+    let run = run(WC, &["-l", &a]);
+    assert_eq!(run.stdout, format!("2 {}\n", a));
+}
+
+// From a pipe, with no size to go on, counts are 7 wide, and there's no name.
+#[test]
+fn stdin() {
+    let run = run_with_input(WC, &["-lmL"], "héllo\tx\n");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.stdout, "      1       8       9\n");
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let (_dir, a, _) = files();
+    let run = run(WC, &["-w", &a, "/nonexistent"]);
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.lines(), [format!(" 5 {}", a), " 5 total".to_string()]);
+    assert_eq!(
+        run.errors(),
+        ["error: /nonexistent: No such file or directory (os error 2)"]
+    );
+}
+
+#[test]
+fn table() {
+    let (_dir, a, b) = files();
+    let run = run(WC, &["--border", "markdown", "-lc", &a, &b]);
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        run.lines(),
+        [
+            format!("| lines | bytes | file{} |", " ".repeat(a.len() - 4)),
+            format!("|------:|------:|:{}|", "-".repeat(a.len() + 1)),
+            format!("|     2 |    25 | {} |", a),
+            format!("|     1 |     7 | {} |", b),
+            format!("|     3 |    32 | total{} |", " ".repeat(a.len() - 5)),
+        ]
+    );
+}
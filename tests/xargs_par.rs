@@ -0,0 +1,77 @@
+mod common;
+
+use common::{run, run_with_input};
+
+const XARGS_PAR: &str = env!("CARGO_BIN_EXE_xargs-par");
+
+// The commands run at the same time, so their lines can come in any order.
+fn sorted(lines: Vec<&str>) -> Vec<&str> {
+    let mut lines = lines;
+    lines.sort_unstable();
+    lines
+}
+
+#[test]
+fn substitutes_each_item() {
+    let run = run_with_input(XARGS_PAR, &["echo", "item={}", "again={}"], "a\nb\n");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(
+        sorted(run.lines()),
+        ["a: item=a again=a", "b: item=b again=b"]
+    );
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn appends_the_item_without_a_replace_string() {
+    let run = run_with_input(XARGS_PAR, &["echo", "hello"], "a\n\nb");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(sorted(run.lines()), ["a: hello a", "b: hello b"]);
+}
+
+#[test]
+fn replace_and_null() {
+    let run = run_with_input(
+        XARGS_PAR,
+        &["-0", "-I", "%", "echo", "[%]"],
+        "one line\0two\0",
+    );
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(sorted(run.lines()), ["one line: [one line]", "two: [two]"]);
+}
+
+#[test]
+fn failures_are_reported() {
+    let run = run_with_input(XARGS_PAR, &["sh", "-c", "exit {}"], "0\n3\n");
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.errors(), ["3: exited 3"]);
+}
+
+#[test]
+fn no_items_runs_nothing() {
+    let run = run_with_input(XARGS_PAR, &["false"], "");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn man_page() {
+    let man = run(XARGS_PAR, &["--man"]);
+    assert_eq!(man.code, Some(0), "{}", man.stderr);
+    assert!(man.stdout.starts_with(".TH \"XARGS-PAR\" 1"));
+    assert!(man
+        .stdout
+        .contains("\\fB\\-I, \\-\\-replace <REPLACE>\\fR\n"));
+}
+
+// Once the command has started, --man and --completions are its arguments, not ours.
+#[test]
+fn man_in_the_command() {
+    let run = run_with_input(XARGS_PAR, &["echo", "--man"], "a\n");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.lines(), ["a: --man a"]);
+
+    let run = run_with_input(XARGS_PAR, &["echo", "--completions"], "a\n");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert_eq!(run.lines(), ["a: --completions a"]);
+}